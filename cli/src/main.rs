@@ -0,0 +1,447 @@
+//! `vaiber`, managing an identity and its plog from the command line.
+//!
+//! Works on the same storage as the apps, so `--profile alice` is the identity the desktop
+//! app shows with `--profile alice`. Don't run both on the same profile at the same time.
+//!
+//! Credentials are read from `VAIBER_USERNAME` and `VAIBER_PASSWORD`, or prompted for.
+use bs::update::OpParams;
+use bs_peer::BsPeer;
+use bs_peer::peer::{DefaultBsPeer, PublicEvent, ResolverExt as _};
+use bs_peer::platform::StartConfig;
+use clap::{Parser, Subcommand};
+use libp2p::Multiaddr;
+use libp2p::futures::Stream;
+use multicid::Vlad;
+use provenance_log::{Key as ProvenanceKey, Log, Op, Value};
+use std::future::Future;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use vaiber_node::identity::IdentityError;
+use vaiber_node::prerotation::{self, RotationCheck};
+use vaiber_node::profile::{Profile, ProfileArgs};
+use vaiber_node::{
+    FileStorage, FollowList, FollowedPeer, KeyCodec, KeyMan, StorageProvider, WalletKeys, backup,
+    events, follow, identity, key_paths, mnemonic, plog, wallet,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "vaiber",
+    version,
+    about = "Manage a vaiber identity and its plog"
+)]
+struct Cli {
+    #[command(flatten)]
+    profile: ProfileArgs,
+
+    /// Peer to dial for commands that use the network, as a multiaddr with a /p2p/ peer ID.
+    /// Changes to the plog are published to the DHT when at least one is given.
+    #[arg(long, global = true)]
+    dial: Vec<Multiaddr>,
+
+    /// The identity in the profile to use. Creating a wallet under a new name adds it
+    #[arg(long, global = true, env = "VAIBER_IDENTITY", default_value = identity::DEFAULT_IDENTITY)]
+    identity: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a wallet and a new plog
+    Init {
+        /// The signing key algorithm: ed25519, secp256k1 or p256
+        #[arg(long, default_value_t)]
+        codec: KeyCodec,
+    },
+    /// Show our VLAD and plog entries
+    Show,
+    /// Set a key to a string value in a new plog entry
+    Set { key: String, value: String },
+    /// Delete a key in a new plog entry
+    Delete { key: String },
+    /// Rotate the signing key in a new plog entry
+    RotateKey {
+        /// The algorithm of the key committed to for the rotation after this one, the current
+        /// one's if not given
+        #[arg(long)]
+        codec: Option<KeyCodec>,
+    },
+    /// Look up someone's plog by their VLAD
+    Resolve { vlad: String },
+    /// Follow a VLAD, or list the followed VLADs if none is given
+    Follow { vlad: Option<String> },
+    /// Stop following a VLAD
+    Unfollow { vlad: String },
+    /// Write our plog to a file, or to stdout
+    Export {
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Write an encrypted backup of the wallet, plog, keys and follow list. The passphrase
+    /// comes from `VAIBER_BACKUP_PASSPHRASE`, or is prompted for
+    Backup {
+        #[arg(long, short, default_value = backup::FILE_NAME)]
+        output: PathBuf,
+    },
+    /// Restore a wallet from a backup into an empty profile
+    Restore { input: PathBuf },
+    /// Print the recovery words of the wallet seed
+    Words,
+    /// Rebuild the wallet from its recovery words under new credentials, and attach it to its
+    /// VLAD again. The words come from `VAIBER_WORDS`, or are prompted for
+    Recover { vlad: String },
+    /// Encrypt the wallet seed under a new username and password, read from
+    /// `VAIBER_NEW_USERNAME` and `VAIBER_NEW_PASSWORD` or prompted for
+    ChangePassword,
+    /// List the identities in the profile
+    Identities,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let data_dir = cli.profile.profile_or(Profile::Default)?.data_dir()?;
+    let root = StorageProvider::new_encrypted(FileStorage::with_dir(data_dir.clone())?);
+    if let Command::Identities = cli.command {
+        for name in identity::list(&root).await? {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+    let storage = identity::storage(&root, &cli.identity)?;
+
+    // Checked before the credentials, so a wrong passphrase fails early
+    let restored = match &cli.command {
+        Command::Restore { input } => Some(backup::open(
+            &std::fs::read(input)?,
+            &backup::read_passphrase()?,
+        )?),
+        _ => None,
+    };
+
+    let words = match &cli.command {
+        Command::Recover { .. } => Some(mnemonic::read_words()?),
+        _ => None,
+    };
+
+    // Refuse an unusable codec before a wallet is created for it
+    if let Command::Init { codec } = cli.command {
+        codec.ensure_available()?;
+    }
+
+    let (username, password) = wallet::read_credentials()?;
+    let wallet = match (&cli.command, &restored) {
+        (Command::Init { .. }, _) => wallet::create(&storage, &username, &password).await?,
+        (Command::ChangePassword, _) => {
+            let (new_username, new_password) = wallet::read_new_credentials()?;
+            wallet::change_credentials(&storage, &username, &password, &new_username, &new_password)
+                .await?
+        }
+        (Command::Recover { vlad }, _) => {
+            let words = words.as_deref().unwrap_or_default();
+            wallet::recover(&storage, words, &parse_vlad(vlad)?, &username, &password).await?
+        }
+        (_, Some(backup)) => backup::restore(&storage, backup, &username, &password).await?,
+        _ => wallet::open(&storage, &username, &password).await?,
+    };
+    if let Command::Init { .. } | Command::Restore { .. } | Command::Recover { .. } = cli.command {
+        match identity::add(&root, &cli.identity).await {
+            Ok(()) | Err(IdentityError::AlreadyExists(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    storage.unlock(wallet.seed());
+    let codec = match cli.command {
+        Command::Init { codec } => codec,
+        _ => KeyCodec::default(),
+    };
+    if let Command::Words = cli.command {
+        println!("{}", mnemonic::to_words(wallet.seed())?.join(" "));
+        return Ok(());
+    }
+    let keys = WalletKeys::new(wallet.seed(), codec)?;
+    drop(wallet);
+    if let Some(backup) = &restored {
+        backup.restore_keys(&keys)?;
+    }
+
+    match cli.command {
+        Command::Init { .. } => {
+            let (mut peer, _) = start_peer(keys.key_manager()?, data_dir).await?;
+            plog::generate(&mut peer, &storage).await?;
+            let plog = peer.plog().ok_or("Failed to generate a plog")?;
+            println!("{}", plog.vlad);
+        }
+        Command::Show => print_plog(&stored_plog(&storage).await?),
+        Command::Set { key, value } => {
+            let op = OpParams::UseStr {
+                key: parse_key(key)?,
+                s: value,
+            };
+            update(&storage, &keys, data_dir, &cli.dial, vec![op], None).await?;
+        }
+        Command::Delete { key } => {
+            let op = OpParams::Delete {
+                key: parse_key(key)?,
+            };
+            update(&storage, &keys, data_dir, &cli.dial, vec![op], None).await?;
+        }
+        Command::RotateKey { codec } => {
+            // Keep the current algorithm unless asked otherwise
+            keys.sync(&stored_plog(&storage).await?)?;
+            let codec = codec.unwrap_or_else(|| keys.signing_codec());
+            update(&storage, &keys, data_dir, &cli.dial, vec![], Some(codec)).await?;
+            println!(
+                "Signing with rotated {} key {}",
+                keys.signing_codec(),
+                keys.index()
+            );
+        }
+        Command::Resolve { vlad } => {
+            let vlad = parse_vlad(&vlad)?;
+            let (peer, peer_events) = start_peer(keys.key_manager()?, data_dir).await?;
+            let log = on_network(&peer, peer_events, &cli.dial, async {
+                let head = events::resolve_head(&peer, &vlad)
+                    .await
+                    .ok_or_else(|| format!("Could not find peer with VLAD: {vlad}"))?;
+                let network_client = peer
+                    .network_client
+                    .as_ref()
+                    .ok_or("Network client not initialized")?;
+                let resolved = network_client
+                    .resolve_plog(&head)
+                    .await
+                    .map_err(|e| format!("Failed to resolve plog: {e}"))?;
+                Ok(resolved.log)
+            })
+            .await?;
+            print_plog(&log);
+        }
+        Command::Follow { vlad: None } => {
+            let mut followed = follow::load_follow_list(&storage)
+                .await
+                .into_iter()
+                .collect::<Vec<_>>();
+            followed.sort_by_key(|(vlad, _)| vlad.to_string());
+            for (vlad, peer) in followed {
+                match peer.resolved_ago() {
+                    Some(ago) => println!("{vlad}  resolved {ago}"),
+                    None => println!("{vlad}  not resolved yet"),
+                }
+            }
+        }
+        Command::Follow { vlad: Some(vlad) } => {
+            let vlad = parse_vlad(&vlad)?;
+            if follow::load_follow_list(&storage).await.contains_key(&vlad) {
+                println!("Already following {vlad}");
+            } else {
+                // Resolved by the apps and vaiber-node the next time they start
+                follow::save_followed(&storage, &vlad, &FollowedPeer::default()).await?;
+                println!("Following {vlad}");
+            }
+        }
+        Command::Unfollow { vlad } => {
+            let vlad = parse_vlad(&vlad)?;
+            follow::remove_followed(&storage, &vlad).await?;
+            println!("Unfollowed {vlad}");
+        }
+        Command::Backup { output } => {
+            let passphrase = backup::read_passphrase()?;
+            if let Ok(plog) = stored_plog(&storage).await {
+                keys.sync(&plog)?;
+            }
+            let bundle = backup::export(&storage, &keys, &passphrase).await?;
+            std::fs::write(&output, bundle)?;
+            println!("Backup written to {}", output.display());
+        }
+        Command::Restore { .. } => match restored.as_ref().and_then(|backup| backup.plog()) {
+            Some(plog) => println!("Restored {}", plog.vlad),
+            None => println!("Restored a wallet without a plog"),
+        },
+        Command::Words => {}
+        Command::ChangePassword => println!("Credentials changed"),
+        Command::Identities => {}
+        Command::Recover { vlad } => {
+            let vlad = parse_vlad(&vlad)?;
+            let (peer, peer_events) = start_peer(keys.key_manager()?, data_dir).await?;
+            let mut attached = peer.clone();
+            on_network(&peer, peer_events, &cli.dial, async {
+                plog::attach(&mut attached, &storage, &keys, &vlad).await?;
+                Ok(())
+            })
+            .await?;
+            println!("Recovered {vlad}");
+        }
+        Command::Export { output } => {
+            let bytes: Vec<u8> = stored_plog(&storage).await?.into();
+            match output {
+                Some(path) => std::fs::write(path, &bytes)?,
+                None => std::io::stdout().write_all(&bytes)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Starts a peer for `key_manager`, returning it with its event stream.
+async fn start_peer(
+    key_manager: KeyMan,
+    data_dir: PathBuf,
+) -> Result<
+    (
+        DefaultBsPeer<KeyMan>,
+        impl Stream<Item = PublicEvent> + Unpin,
+    ),
+    Error,
+> {
+    let mut peer = BsPeer::new(
+        key_manager,
+        StartConfig {
+            base_path: Some(data_dir),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|e| format!("Failed to start peer: {e}"))?;
+    let peer_events = peer.events.take().ok_or("Peer has no event stream")?;
+    Ok((peer, peer_events))
+}
+
+/// Dials the `dial` peers, then runs `command` while handling network events.
+async fn on_network<T>(
+    peer: &DefaultBsPeer<KeyMan>,
+    peer_events: impl Stream<Item = PublicEvent> + Unpin,
+    dial: &[Multiaddr],
+    command: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let network_client = peer
+        .network_client
+        .as_ref()
+        .ok_or("Network client not initialized")?;
+    for addr in dial {
+        network_client
+            .dial(addr.clone())
+            .await
+            .map_err(|e| format!("Failed to dial {addr}: {e}"))?;
+    }
+
+    tokio::select! {
+        result = command => result,
+        _ = events::run(peer.clone(), peer_events, FollowList::new, |_| {}) => {
+            Err("Network stopped".into())
+        }
+    }
+}
+
+/// Adds an entry to our plog, then publishes it if there are peers to publish to.
+async fn update(
+    storage: &StorageProvider,
+    keys: &WalletKeys,
+    data_dir: PathBuf,
+    dial: &[Multiaddr],
+    ops: Vec<OpParams>,
+    rotate_key: Option<KeyCodec>,
+) -> Result<(), Error> {
+    let (mut peer, peer_events) = start_peer(keys.key_manager()?, data_dir).await?;
+    if !plog::load(&mut peer, storage).await? {
+        return Err("No plog found, run `vaiber init` first".into());
+    }
+    plog::update(&mut peer, storage, keys, ops, rotate_key).await?;
+    if let Some(plog) = peer.plog() {
+        println!("Head: {}", plog.head);
+    }
+
+    if !dial.is_empty() {
+        on_network(&peer, peer_events, dial, async {
+            peer.clone()
+                .record_plog_to_dht()
+                .await
+                .map_err(|e| format!("Failed to publish plog: {e}"))?;
+            Ok(())
+        })
+        .await?;
+        println!("Published to the DHT");
+    }
+    Ok(())
+}
+
+/// Our plog as last saved.
+async fn stored_plog(storage: &StorageProvider) -> Result<Log, Error> {
+    let bytes = storage
+        .try_load(plog::PLOG_STORAGE_KEY)
+        .await?
+        .ok_or("No plog found, run `vaiber init` first")?;
+    Log::try_from(bytes.as_slice()).map_err(|e| format!("Stored plog is unreadable: {e}").into())
+}
+
+fn parse_key(key: String) -> Result<ProvenanceKey, Error> {
+    ProvenanceKey::try_from(key.clone()).map_err(|e| format!("Invalid key {key}: {e}").into())
+}
+
+fn parse_vlad(vlad: &str) -> Result<Vlad, Error> {
+    Vlad::try_from_str(vlad).map_err(|e| format!("Invalid VLAD {vlad}: {e}").into())
+}
+
+fn print_plog(plog: &Log) {
+    println!("VLAD: {}", plog.vlad);
+    println!("Head: {}", plog.head);
+    let rotations = prerotation::check_rotations(plog);
+    for (idx, maybe_verified) in plog.verify().enumerate() {
+        match maybe_verified {
+            Ok((_count, entry, _kvp)) => {
+                println!("Entry {idx}:");
+                for op in entry.ops() {
+                    print_op(op);
+                }
+                let signing = ProvenanceKey::from(key_paths::SIGNING);
+                let rotates = entry
+                    .ops()
+                    .any(|op| matches!(op, Op::Update(key, _) if *key == signing));
+                match rotations.iter().find(|(rotated, _)| *rotated == idx) {
+                    Some((_, RotationCheck::Mismatch)) if rotates => {
+                        println!("  warning: rotation breaks the pre-commitment")
+                    }
+                    Some((_, RotationCheck::Mismatch)) => {
+                        println!("  warning: entry changes the pre-commitment without rotating")
+                    }
+                    Some((_, RotationCheck::Uncommitted)) => {
+                        println!("  note: rotation without a pre-commitment")
+                    }
+                    Some((_, RotationCheck::Recovered(approvals))) => {
+                        println!("  note: recovery approved by {approvals} guardian(s)")
+                    }
+                    _ => {}
+                }
+            }
+            Err(e) => println!("Error verifying entry {idx}: {e}"),
+        }
+    }
+}
+
+fn print_op(op: &Op) {
+    match op {
+        Op::Noop(key) => println!("  noop {key}"),
+        Op::Delete(key) => println!("  delete {key}"),
+        Op::Update(key, Value::Nil) => println!("  {key} = nil"),
+        Op::Update(key, Value::Str(s)) => println!("  {key} = {s}"),
+        Op::Update(key, Value::Data(data)) => println!("  {key} = <{} bytes>", data.len()),
+    }
+}
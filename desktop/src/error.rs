@@ -1,8 +1,8 @@
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    /// Failed to initialize the storage
-    #[error("Failed to initialize storage: {0}")]
-    StorageFailure(&'static str),
+    /// The profile could not be picked or located
+    #[error(transparent)]
+    Profile(#[from] vaiber_node::profile::ProfileError),
 
     /// From<std::io::Error>
     #[error("I/O error: {0}")]
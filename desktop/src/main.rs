@@ -1,42 +1,71 @@
 //! DESKTOP
 mod error;
 mod node;
-mod storage;
+mod profile;
 
 use error::Error;
+use profile::{Profile, ProfilePicker};
 
 use dioxus::desktop::{Config, LogicalSize, WindowBuilder};
 use dioxus::prelude::*;
 
 use ui::{Hero, StorageProvider};
+use vaiber_node::FileStorage;
 
 const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
 
 fn main() {
+    let profile = match profile::from_args() {
+        Ok(profile) => profile,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let title = match &profile {
+        Profile::Default => "PeerPiper vaiber".to_string(),
+        other => format!("PeerPiper vaiber ({})", other.name()),
+    };
+
     dioxus::LaunchBuilder::new()
         .with_cfg(desktop! {
             Config::new().with_window(
                 WindowBuilder::new()
-                    .with_title("PeerPiper vaiber")
+                    .with_title(title)
                     .with_inner_size(LogicalSize::new(700.0, 900.0)),
             )
         })
+        .with_context(profile)
         .launch(App)
 }
 
 #[component]
 fn App() -> Element {
-    // Build cool things ✌️
-    let storage = storage::DesktopStorage::new().unwrap();
-    let storage_provider = StorageProvider::new(storage.clone());
-
-    // provide storage in context for all child elements
-    use_context_provider(|| storage_provider);
+    let initial_profile = use_context::<Profile>();
+    let profile = use_signal(|| initial_profile);
 
     rsx! {
         // Global app resources
         document::Link { rel: "stylesheet", href: TAILWIND_CSS }
 
+        ProfilePicker { profile }
+
+        // Keyed on the profile, so switching profiles remounts everything below
+        ProfileApp { key: "{profile().name()}", profile: profile() }
+    }
+}
+
+#[component]
+fn ProfileApp(profile: Profile) -> Element {
+    // Build cool things ✌️
+    let storage = use_hook(|| FileStorage::with_dir(profile.data_dir().unwrap()).unwrap());
+
+    // provide storage in context for all child elements
+    let storage_provider = storage.clone();
+    use_context_provider(|| StorageProvider::new_encrypted(storage_provider));
+
+    rsx! {
         Hero { platform_content: rsx! { node::DektopNode { } }, base_path: storage.dir() }
     }
 }
@@ -0,0 +1,98 @@
+//! Picking the profile to run as, see [Profile].
+use crate::Error;
+use clap::Parser;
+use dioxus::prelude::*;
+use vaiber_node::profile::ProfileArgs;
+
+pub use vaiber_node::profile::Profile;
+
+/// Command line options for the desktop app.
+#[derive(Parser, Debug)]
+#[command(version, about = "PeerPiper vaiber")]
+struct Args {
+    #[command(flatten)]
+    profile: ProfileArgs,
+}
+
+/// Picks the profile from the command line and environment.
+pub fn from_args() -> Result<Profile, Error> {
+    Ok(Args::parse().profile.profile_or(Profile::Default)?)
+}
+
+/// Switches between profiles, or creates a new one. Switching remounts the whole app,
+/// which locks the wallet and restarts the node with the other profile's identity.
+#[component]
+pub fn ProfilePicker(profile: Signal<Profile>) -> Element {
+    let mut profiles = use_signal(|| Profile::list().unwrap_or_else(|_| vec![Profile::Default]));
+    let mut new_name = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    // A --data-dir overrides profiles altogether
+    if let Profile::Custom(dir) = profile() {
+        return rsx! {
+            div {
+                class: "fixed top-4 left-4 z-50 p-2 bg-white rounded-lg shadow-md border border-green-200 text-xs font-mono",
+                "Data dir: {dir.display()}"
+            }
+        };
+    }
+
+    let mut handle_create = move |_| match Profile::named(new_name().trim()) {
+        Ok(created) => {
+            if !profiles.read().contains(&created) {
+                profiles.write().push(created.clone());
+            }
+            error.set(None);
+            new_name.set(String::new());
+            profile.set(created);
+        }
+        Err(err) => error.set(Some(err.to_string())),
+    };
+
+    rsx! {
+        div {
+            class: "fixed top-4 left-4 z-50 p-2 bg-white rounded-lg shadow-md border border-green-200 flex flex-col gap-1 text-xs",
+            div {
+                class: "flex items-center gap-2",
+                label { r#for: "profile", class: "font-semibold text-gray-700", "Profile" }
+                select {
+                    id: "profile",
+                    class: "p-1 border rounded font-mono",
+                    onchange: move |e| {
+                        let selected = profiles.read().iter().find(|p| p.name() == e.value()).cloned();
+                        if let Some(selected) = selected {
+                            profile.set(selected);
+                        }
+                    },
+                    for p in profiles.read().iter() {
+                        option {
+                            key: "{p.name()}",
+                            value: "{p.name()}",
+                            selected: *p == profile(),
+                            "{p.name()}"
+                        }
+                    }
+                }
+            }
+            form {
+                class: "flex items-center gap-1",
+                onsubmit: move |e| handle_create(e),
+                input {
+                    class: "p-1 border rounded font-mono w-28",
+                    placeholder: "new profile",
+                    value: "{new_name}",
+                    oninput: move |e| new_name.set(e.value()),
+                }
+                button {
+                    class: "px-2 py-1 bg-green-500 hover:bg-green-600 text-white rounded",
+                    r#type: "submit",
+                    disabled: new_name().trim().is_empty(),
+                    "Add"
+                }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500", "{err}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Failed to initialize the storage
+    #[error("Failed to initialize storage: {0}")]
+    StorageFailure(&'static str),
+
+    /// From<std::io::Error>
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// From<jni::errors::Error>, asking Android for the files directory
+    #[cfg(target_os = "android")]
+    #[error("JNI error: {0}")]
+    Jni(#[from] jni::errors::Error),
+}
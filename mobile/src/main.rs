@@ -1,6 +1,13 @@
+//! MOBILE
+mod error;
+mod storage;
+
+use error::Error;
+
 use dioxus::prelude::*;
 
-use ui::Hero;
+use ui::{Hero, StorageProvider};
+use vaiber_node::FileStorage;
 
 const MAIN_CSS: Asset = asset!("/assets/main.css");
 
@@ -11,12 +18,17 @@ fn main() {
 #[component]
 fn App() -> Element {
     // Build cool things ✌️
+    let storage = use_hook(|| FileStorage::with_dir(storage::app_data_dir().unwrap()).unwrap());
+
+    // provide storage in context for all child elements
+    let storage_provider = storage.clone();
+    use_context_provider(|| StorageProvider::new_encrypted(storage_provider));
 
     rsx! {
         // Global app resources
         document::Link { rel: "stylesheet", href: MAIN_CSS }
 
-        Hero { platform_content: rsx! { } }
+        Hero { platform_content: rsx! { }, base_path: storage.dir() }
 
     }
 }
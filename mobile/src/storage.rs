@@ -0,0 +1,32 @@
+//! Mobile storage, in the app's private data directory
+use crate::Error;
+use std::path::PathBuf;
+
+/// The app's private files directory, from `Context.getFilesDir()`.
+#[cfg(target_os = "android")]
+pub fn app_data_dir() -> Result<PathBuf, Error> {
+    use jni::objects::{JObject, JString};
+
+    let context = ndk_context::android_context();
+    // SAFETY: ndk-context holds the app's JavaVM and Context for the lifetime of the process
+    let vm = unsafe { jni::JavaVM::from_raw(context.vm().cast())? };
+    let mut env = vm.attach_current_thread()?;
+    let context = unsafe { JObject::from_raw(context.context().cast()) };
+
+    let dir = env
+        .call_method(&context, "getFilesDir", "()Ljava/io/File;", &[])?
+        .l()?;
+    let path = env
+        .call_method(&dir, "getAbsolutePath", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let path: String = env.get_string(&JString::from(path))?.into();
+    Ok(PathBuf::from(path))
+}
+
+/// The sandboxed Application Support directory on iOS, or the usual data directory elsewhere.
+#[cfg(not(target_os = "android"))]
+pub fn app_data_dir() -> Result<PathBuf, Error> {
+    let project_dirs = directories::ProjectDirs::from("io", "peerpiper", "vaiber")
+        .ok_or(Error::StorageFailure("Failed to get project directories"))?;
+    Ok(project_dirs.data_dir().to_path_buf())
+}
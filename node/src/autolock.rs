@@ -0,0 +1,184 @@
+//! Locking the wallet by itself: after a while without input, when the window loses focus,
+//! or after the system was suspended.
+//!
+//! The apps report input with [IdleTimer::touch] and call [IdleTimer::check] every [TICK].
+//! Suspends are noticed as a jump of the wall clock between two checks, since timers do not
+//! run while the system sleeps. Each identity keeps its own [AutoLockSettings].
+use crate::storage::{StorageError, StorageProvider};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use web_time::SystemTime;
+
+/// Where each identity keeps its auto-lock settings.
+const SETTINGS_KEY: &str = "settings/auto-lock";
+
+/// How often the apps check for idleness.
+pub const TICK: Duration = Duration::from_secs(5);
+
+/// A gap this much longer than [TICK] between two checks means the system was asleep.
+const SUSPEND_SLACK: Duration = Duration::from_secs(30);
+
+/// When to lock the wallet without being asked.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoLockSettings {
+    /// Minutes without input before locking, 0 to never lock when idle
+    pub idle_minutes: u32,
+    /// Lock as soon as the window loses focus or is hidden
+    pub lock_on_blur: bool,
+    /// Lock once the system wakes up from a suspend
+    pub lock_on_suspend: bool,
+}
+
+impl Default for AutoLockSettings {
+    fn default() -> Self {
+        AutoLockSettings {
+            idle_minutes: 15,
+            lock_on_blur: false,
+            lock_on_suspend: true,
+        }
+    }
+}
+
+impl AutoLockSettings {
+    /// How long without input before locking, if at all.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_minutes > 0).then(|| Duration::from_secs(u64::from(self.idle_minutes) * 60))
+    }
+
+    /// The settings stored in `storage`, or the defaults if there are none we can read.
+    ///
+    /// `storage` has to be unlocked.
+    pub async fn load(storage: &StorageProvider) -> Self {
+        match storage.try_load(SETTINGS_KEY).await {
+            Ok(Some(bytes)) => ciborium::from_reader(bytes.as_slice()).unwrap_or_else(|e| {
+                tracing::error!("Unreadable auto-lock settings, using the defaults: {}", e);
+                Self::default()
+            }),
+            Ok(None) => Self::default(),
+            Err(e) => {
+                tracing::error!("Failed to load auto-lock settings: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub async fn save(&self, storage: &StorageProvider) -> Result<(), StorageError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).expect("Serializing to a Vec does not fail");
+        storage.save(SETTINGS_KEY, &bytes).await
+    }
+}
+
+/// Why the wallet locked itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockReason {
+    Idle,
+    Blurred,
+    Suspended,
+}
+
+impl LockReason {
+    /// A message for the login screen.
+    pub fn message(self) -> &'static str {
+        match self {
+            LockReason::Idle => "Wallet locked after a period of inactivity",
+            LockReason::Blurred => "Wallet locked when the window lost focus",
+            LockReason::Suspended => "Wallet locked after the system was suspended",
+        }
+    }
+}
+
+/// Tracks the last input and the last check, to tell when the wallet should lock.
+#[derive(Clone, Debug)]
+pub struct IdleTimer {
+    last_input: SystemTime,
+    last_check: SystemTime,
+}
+
+impl Default for IdleTimer {
+    fn default() -> Self {
+        let now = SystemTime::now();
+        IdleTimer {
+            last_input: now,
+            last_check: now,
+        }
+    }
+}
+
+impl IdleTimer {
+    /// Records input from the user.
+    pub fn touch(&mut self) {
+        self.last_input = SystemTime::now();
+    }
+
+    /// Whether the wallet should lock now, under `settings`.
+    pub fn check(&mut self, settings: &AutoLockSettings) -> Option<LockReason> {
+        let now = SystemTime::now();
+        let since_check = now.duration_since(self.last_check).unwrap_or_default();
+        self.last_check = now;
+        // A suspend longer than the idle timeout also shows up as idleness below
+        if settings.lock_on_suspend && since_check > TICK + SUSPEND_SLACK {
+            return Some(LockReason::Suspended);
+        }
+        let idle = now.duration_since(self.last_input).unwrap_or_default();
+        match settings.idle_timeout() {
+            Some(timeout) if idle >= timeout => Some(LockReason::Idle),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A timer whose last check was `ago`, as after a suspend of that long.
+    fn woken_after(ago: Duration) -> IdleTimer {
+        let mut timer = IdleTimer::default();
+        timer.last_check = SystemTime::now() - ago;
+        timer
+    }
+
+    #[test]
+    fn a_suspend_locks_whatever_the_blur_setting() {
+        let asleep = TICK + SUSPEND_SLACK + Duration::from_secs(1);
+        let settings = AutoLockSettings {
+            idle_minutes: 0,
+            lock_on_blur: false,
+            lock_on_suspend: true,
+        };
+        assert_eq!(
+            woken_after(asleep).check(&settings),
+            Some(LockReason::Suspended)
+        );
+
+        let settings = AutoLockSettings {
+            lock_on_blur: true,
+            lock_on_suspend: false,
+            ..settings
+        };
+        assert_eq!(woken_after(asleep).check(&settings), None);
+    }
+
+    #[test]
+    fn settings_saved_before_the_suspend_setting_lock_on_suspend() {
+        #[derive(Serialize)]
+        struct Older {
+            idle_minutes: u32,
+            lock_on_blur: bool,
+        }
+        let mut bytes = Vec::new();
+        ciborium::into_writer(
+            &Older {
+                idle_minutes: 5,
+                lock_on_blur: true,
+            },
+            &mut bytes,
+        )
+        .unwrap();
+        let settings: AutoLockSettings = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(settings.idle_minutes, 5);
+        assert!(settings.lock_on_blur && settings.lock_on_suspend);
+    }
+}
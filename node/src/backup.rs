@@ -0,0 +1,359 @@
+//! A single file holding everything needed to restore an identity on another device.
+//!
+//! The bundle holds the password encrypted wallet seed, our plog, the generation of each key
+//! we rotated and the follow list. It is sealed with ChaCha20-Poly1305
+//! under a key derived from a separate backup passphrase with Argon2id, so the file can be
+//! kept anywhere. The header is associated data, so it can't be changed unnoticed either. The
+//! seed inside still needs the wallet's username and password to open.
+use crate::follow::{self, FollowList, FollowRecord};
+use crate::keys::{KeyCodec, KeyError, WalletKeys};
+use crate::plog::PLOG_STORAGE_KEY;
+use crate::storage::{SEED_STORAGE_KEY, StorageError, StorageProvider};
+use crate::wallet::{self, WalletError};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore as _;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use provenance_log::Log;
+use seed_keeper_core::credentials::Wallet;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Marks a backup bundle, followed by the format version.
+const MAGIC: &[u8; 4] = b"VBAK";
+const VERSION: u8 = 2;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+/// Shortest backup passphrase we accept.
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// File name suggested for a backup.
+pub const FILE_NAME: &str = "vaiber-backup.vbak";
+
+/// Errors creating or restoring a backup.
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    /// The file is not a backup bundle
+    #[error("Not a vaiber backup")]
+    NotABackup,
+
+    /// The bundle is in a format we don't read, from a newer version or from before the
+    /// header was authenticated
+    #[error("Unsupported backup version {0}")]
+    UnsupportedVersion(u8),
+
+    /// The passphrase is too short
+    #[error("The backup passphrase must be at least {MIN_PASSPHRASE_LEN} characters")]
+    WeakPassphrase,
+
+    /// The bundle could not be decrypted, most likely because of the passphrase
+    #[error("Wrong passphrase, or the backup is damaged")]
+    WrongPassphrase,
+
+    /// The bundle decrypted, but its contents could not be read or written
+    #[error("Invalid backup: {0}")]
+    Invalid(String),
+
+    /// There is already a wallet in storage, which restoring would replace
+    #[error("A wallet already exists, delete it before restoring a backup")]
+    AlreadyExists,
+
+    /// The wallet seed could not be opened with the given credentials
+    #[error(transparent)]
+    Wallet(#[from] WalletError),
+
+    /// From<StorageError>
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    /// A rotated key could not be derived again
+    #[error(transparent)]
+    Keys(#[from] KeyError),
+}
+
+/// The sealed contents of a bundle.
+#[derive(Serialize, Deserialize)]
+struct Contents {
+    encrypted_seed: Vec<u8>,
+    plog: Option<Vec<u8>>,
+    /// `(path, generation, codec)` of every key in use
+    keys: Vec<(String, u64, String)>,
+    follows: Vec<FollowRecord>,
+}
+
+/// An opened backup, ready to be restored.
+pub struct Backup {
+    encrypted_seed: Vec<u8>,
+    plog: Option<Log>,
+    keys: Vec<(String, u64, KeyCodec)>,
+    follow_list: FollowList,
+}
+
+impl Backup {
+    /// Our plog at the time of the backup, if there was one.
+    pub fn plog(&self) -> Option<&Log> {
+        self.plog.as_ref()
+    }
+
+    pub fn follow_list(&self) -> &FollowList {
+        &self.follow_list
+    }
+
+    /// Puts every backed up key generation back into `keys`.
+    ///
+    /// The signing key is also found again from the plog, see [WalletKeys::sync].
+    pub fn restore_keys(&self, keys: &WalletKeys) -> Result<(), BackupError> {
+        for (path, generation, codec) in &self.keys {
+            keys.store(&path.as_str().into(), *generation, *codec)?;
+        }
+        Ok(())
+    }
+}
+
+/// Seals the wallet in `storage`, its plog, the state of `keys` and the follow list into a
+/// bundle encrypted with `passphrase`.
+///
+/// `storage` has to be unlocked.
+pub async fn export(
+    storage: &StorageProvider,
+    keys: &WalletKeys,
+    passphrase: &str,
+) -> Result<Vec<u8>, BackupError> {
+    check_passphrase(passphrase)?;
+    let encrypted_seed = storage
+        .try_load(SEED_STORAGE_KEY)
+        .await?
+        .ok_or(WalletError::NotFound)?;
+    let contents = Contents {
+        encrypted_seed,
+        plog: storage.try_load(PLOG_STORAGE_KEY).await?,
+        keys: keys
+            .generations()
+            .into_iter()
+            .map(|(path, generation, codec)| (path, generation, codec.to_string()))
+            .collect(),
+        follows: follow::load_follow_list(storage)
+            .await
+            .iter()
+            .map(|(vlad, followed)| FollowRecord::new(vlad, followed))
+            .collect(),
+    };
+
+    let mut plaintext = Zeroizing::new(Vec::new());
+    ciborium::into_writer(&contents, &mut *plaintext)
+        .map_err(|e| BackupError::Invalid(e.to_string()))?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut bundle = Vec::with_capacity(HEADER_LEN + plaintext.len());
+    bundle.extend_from_slice(MAGIC);
+    bundle.push(VERSION);
+    bundle.extend_from_slice(&salt);
+    bundle.extend_from_slice(&nonce);
+    let ciphertext = cipher(passphrase, &salt)?
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext.as_slice(),
+                aad: &bundle,
+            },
+        )
+        .map_err(|e| BackupError::Invalid(e.to_string()))?;
+    bundle.extend_from_slice(&ciphertext);
+    Ok(bundle)
+}
+
+/// Decrypts a bundle made by [export].
+pub fn open(bundle: &[u8], passphrase: &str) -> Result<Backup, BackupError> {
+    if bundle.len() < HEADER_LEN || !bundle.starts_with(MAGIC) {
+        return Err(BackupError::NotABackup);
+    }
+    let version = bundle[MAGIC.len()];
+    if version != VERSION {
+        return Err(BackupError::UnsupportedVersion(version));
+    }
+    let (header, ciphertext) = bundle.split_at(HEADER_LEN);
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = Nonce::from_slice(&header[HEADER_LEN - NONCE_LEN..]);
+    let plaintext = Zeroizing::new(
+        cipher(passphrase, salt)?
+            .decrypt(
+                nonce,
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| BackupError::WrongPassphrase)?,
+    );
+    let contents = ciborium::from_reader::<Contents, _>(plaintext.as_slice())
+        .map_err(|e| BackupError::Invalid(e.to_string()))?;
+
+    let plog = contents
+        .plog
+        .map(|bytes| Log::try_from(bytes.as_slice()))
+        .transpose()
+        .map_err(|e| BackupError::Invalid(format!("unreadable plog: {e}")))?;
+    let keys = contents
+        .keys
+        .into_iter()
+        .map(|(path, generation, codec)| Ok((path, generation, codec.parse::<KeyCodec>()?)))
+        .collect::<Result<_, String>>()
+        .map_err(BackupError::Invalid)?;
+    Ok(Backup {
+        encrypted_seed: contents.encrypted_seed,
+        plog,
+        keys,
+        follow_list: contents
+            .follows
+            .into_iter()
+            .filter_map(FollowRecord::decode)
+            .collect(),
+    })
+}
+
+/// Restores `backup` into an empty `storage`, opening its seed with the wallet's `username`
+/// and `password`. Leaves `storage` unlocked with the restored wallet.
+///
+/// The seed is written last, so an interrupted restore leaves no wallet behind and can be
+/// tried again.
+pub async fn restore(
+    storage: &StorageProvider,
+    backup: &Backup,
+    username: &str,
+    password: &str,
+) -> Result<Wallet, BackupError> {
+    if storage.try_load(SEED_STORAGE_KEY).await?.is_some() {
+        return Err(BackupError::AlreadyExists);
+    }
+    let wallet = wallet::wallet(username, password, Some(backup.encrypted_seed.clone()))?;
+    storage.unlock(wallet.seed());
+
+    if let Some(plog) = &backup.plog {
+        let plog_bytes: Vec<u8> = plog.clone().into();
+        storage.save(PLOG_STORAGE_KEY, &plog_bytes).await?;
+    }
+    let current = follow::load_follow_list(storage).await;
+    follow::save_changes(storage, &current, &backup.follow_list).await?;
+
+    match storage
+        .compare_and_swap(SEED_STORAGE_KEY, None, Some(&backup.encrypted_seed))
+        .await
+    {
+        Ok(()) => Ok(wallet),
+        Err(StorageError::Conflict(_)) => Err(BackupError::AlreadyExists),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), BackupError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(BackupError::WeakPassphrase);
+    }
+    Ok(())
+}
+
+/// The cipher for `passphrase` and `salt`.
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, BackupError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| BackupError::Invalid(e.to_string()))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(key.as_ref())))
+}
+
+/// Reads the backup passphrase from `VAIBER_BACKUP_PASSPHRASE`, or prompts for it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_passphrase() -> std::io::Result<String> {
+    match std::env::var("VAIBER_BACKUP_PASSPHRASE") {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => rpassword::prompt_password("Backup passphrase: "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::follow::FollowedPeer;
+    use crate::storage::memory::MemoryStorage;
+    use crate::test_util::vlad;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    /// An unlocked wallet storage with a seed and a followed peer.
+    async fn wallet_storage() -> StorageProvider {
+        let storage = StorageProvider::new_encrypted(MemoryStorage::default());
+        storage.unlock(&[7; 32]);
+        storage
+            .save(SEED_STORAGE_KEY, b"password encrypted seed")
+            .await
+            .unwrap();
+        follow::save_followed(&storage, &vlad(1), &FollowedPeer::default())
+            .await
+            .unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn open_returns_what_was_exported() {
+        let storage = wallet_storage().await;
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let bundle = export(&storage, &keys, PASSPHRASE).await.unwrap();
+
+        let backup = open(&bundle, PASSPHRASE).unwrap();
+        assert_eq!(backup.encrypted_seed, b"password encrypted seed");
+        assert!(backup.plog().is_none());
+        assert_eq!(backup.keys, keys.generations());
+        assert_eq!(
+            backup.follow_list().keys().collect::<Vec<_>>(),
+            vec![&vlad(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn open_rejects_another_passphrase_or_a_changed_header() {
+        let storage = wallet_storage().await;
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let bundle = export(&storage, &keys, PASSPHRASE).await.unwrap();
+
+        assert!(matches!(
+            open(&bundle, "another passphrase"),
+            Err(BackupError::WrongPassphrase)
+        ));
+
+        // Neither the first format, sealed without the header, nor a newer one is read
+        for version in [1, VERSION + 1] {
+            let mut other = bundle.clone();
+            other[MAGIC.len()] = version;
+            assert!(matches!(
+                open(&other, PASSPHRASE),
+                Err(BackupError::UnsupportedVersion(v)) if v == version
+            ));
+        }
+
+        let mut changed = bundle.clone();
+        changed[MAGIC.len() + 1] ^= 1;
+        assert!(matches!(
+            open(&changed, PASSPHRASE),
+            Err(BackupError::WrongPassphrase)
+        ));
+
+        assert!(matches!(
+            open(&bundle[..HEADER_LEN - 1], PASSPHRASE),
+            Err(BackupError::NotABackup)
+        ));
+    }
+
+    #[tokio::test]
+    async fn export_refuses_a_short_passphrase() {
+        let storage = wallet_storage().await;
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        assert!(matches!(
+            export(&storage, &keys, "short").await,
+            Err(BackupError::WeakPassphrase)
+        ));
+    }
+}
@@ -0,0 +1,27 @@
+//! Attachments to plog entries.
+//!
+//! Attachments go straight into the entry as data, so they are limited to [INLINE_LIMIT] and
+//! every data value is the attachment itself. Storing larger files apart and referencing them
+//! by CID is out of scope for now: it needs a way to publish blobs where other peers can fetch
+//! them, and a reference type that can't be mistaken for inline data.
+use bs::update::OpParams;
+use provenance_log::Key;
+
+/// The largest attachment, which is stored inline in the plog entry.
+pub const INLINE_LIMIT: usize = 16 * 1024;
+
+/// Errors attaching a file.
+#[derive(Debug, thiserror::Error)]
+pub enum BlobError {
+    /// The attachment can't be stored inline, see [INLINE_LIMIT]
+    #[error("Attachments can be at most {INLINE_LIMIT} bytes, this one is {0}")]
+    TooLarge(usize),
+}
+
+/// The op that attaches `data` under `key`, inline in the entry.
+pub fn attach(key: Key, data: Vec<u8>) -> Result<OpParams, BlobError> {
+    if data.len() > INLINE_LIMIT {
+        return Err(BlobError::TooLarge(data.len()));
+    }
+    Ok(OpParams::UseBin { key, data })
+}
@@ -0,0 +1,261 @@
+//! Handling of network events, and keeping followed peers up to date.
+//!
+//! Nothing here knows about the UI. Whatever a caller may want to show or persist is
+//! reported through a [NodeUpdate] callback.
+use crate::KeyMan;
+use crate::follow::{FollowList, FollowedPeer};
+use crate::recovery::{self, RecoveryError, RecoveryMessage};
+use bs_peer::peer::{DefaultBsPeer, Libp2pEvent, PublicEvent, ResolverExt as _};
+use futures::{Stream, StreamExt as _};
+use multicid::{Cid, Vlad};
+use provenance_log::Log;
+use provenance_log::resolver::Resolver as _;
+use std::future::Future;
+
+/// How many times a network lookup is retried, with exponential backoff.
+const MAX_RETRIES: u32 = 3;
+
+/// Something changed that the caller may want to reflect.
+#[derive(Clone, Debug)]
+pub enum NodeUpdate {
+    /// The node is listening on a new address
+    Listening(String),
+    /// A connection to the peer was established
+    Connected(String),
+    /// The connection to the peer was closed
+    Disconnected(String),
+    /// A followed peer's plog was resolved
+    Resolved(Vlad, FollowedPeer),
+    /// A message arrived on the recovery topic of the VLAD, see [crate::recovery]
+    Recovery(Vlad, RecoveryMessage),
+}
+
+/// Handles the peer's network events until the stream ends.
+///
+/// `followed` is asked for the current follow list whenever it is needed.
+pub async fn run(
+    peer: DefaultBsPeer<KeyMan>,
+    mut events: impl Stream<Item = PublicEvent> + Unpin,
+    followed: impl Fn() -> FollowList,
+    mut on_update: impl FnMut(NodeUpdate),
+) {
+    // Heads announced over PubSub that we haven't been ACKed for yet
+    let mut ack_list = Vec::<Vlad>::new();
+
+    while let Some(event) = events.next().await {
+        match event {
+            PublicEvent::ListenAddr { address, .. } => {
+                tracing::info!("Peer listening on: {}", address);
+                on_update(NodeUpdate::Listening(address.to_string()));
+            }
+            PublicEvent::NewConnection { peer: remote } => {
+                tracing::info!("New connection established with peer: {}", remote);
+                on_update(NodeUpdate::Connected(remote.to_string()));
+                if let Err(e) = peer.clone().record_plog_to_dht().await {
+                    tracing::error!("Failed to publish Plog records: {}", e);
+                } else {
+                    tracing::info!("Plog records published to DHT successfully.");
+                }
+            }
+            PublicEvent::ConnectionClosed {
+                peer: remote,
+                cause,
+            } => {
+                tracing::info!(
+                    "Connection closed with peer: {}, cause: {:?}",
+                    remote,
+                    cause
+                );
+                on_update(NodeUpdate::Disconnected(remote.to_string()));
+            }
+            PublicEvent::Message { topic, data, .. } => {
+                tracing::info!("Received message topic: {}, data: {:?}", topic, data);
+                if let Some(recovered) = topic.strip_prefix(recovery::TOPIC_PREFIX) {
+                    match (
+                        Vlad::try_from_str(recovered),
+                        RecoveryMessage::from_bytes(&data),
+                    ) {
+                        (Ok(vlad), Ok(message)) => on_update(NodeUpdate::Recovery(vlad, message)),
+                        _ => tracing::warn!("Invalid recovery message on topic: {}", topic),
+                    }
+                    continue;
+                }
+                let Ok(vlad) = Vlad::try_from_str(&topic) else {
+                    continue;
+                };
+                if ack_list.contains(&vlad) {
+                    continue;
+                }
+                ack_list.push(vlad.clone());
+
+                let Some(network_client) = peer.network_client.as_ref() else {
+                    continue;
+                };
+                let Ok(head) = Cid::try_from(data.as_slice()) else {
+                    tracing::warn!("Invalid VLAD: {}", vlad);
+                    continue;
+                };
+                match retry(|| network_client.resolve_plog(&head)).await {
+                    Ok(plog) => {
+                        tracing::info!("Resolved plog from PubSub for VLAD: {}", vlad);
+                        on_update(NodeUpdate::Resolved(
+                            vlad.clone(),
+                            FollowedPeer::resolved(plog.log),
+                        ));
+                    }
+                    Err(e) => tracing::error!(
+                        "Failed to resolve plog from PubSub for VLAD {}: {}",
+                        vlad,
+                        e
+                    ),
+                }
+                // Send ACK back to the sender
+                let ack_topic = format!("ack/{}", topic);
+                if let Err(e) = network_client.publish(data.clone(), ack_topic).await {
+                    tracing::error!("Failed to publish ACK: {}", e);
+                }
+            }
+            PublicEvent::Swarm(Libp2pEvent::PutRecordRequest { source }) => {
+                tracing::info!("Received PutRecordRequest from: {}", source);
+                let Some(network_client) = peer.network_client.as_ref() else {
+                    continue;
+                };
+                for (vlad, followed) in followed() {
+                    if followed.plog.is_some() {
+                        continue;
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    tokio::time::sleep(std::time::Duration::from_secs(4)).await;
+                    let Some(head) = resolve_head(&peer, &vlad).await else {
+                        continue;
+                    };
+                    if network_client.resolve(&head).await.is_err() {
+                        continue;
+                    }
+                    if let Ok(resolved_plog) = network_client.resolve_plog(&head).await {
+                        on_update(NodeUpdate::Resolved(
+                            vlad,
+                            FollowedPeer::resolved(resolved_plog.log),
+                        ));
+                    }
+                }
+            }
+            PublicEvent::Ack { topic, .. } => {
+                tracing::info!("Received ACK for topic: {}", topic);
+                if let Ok(vlad) = Vlad::try_from_str(&topic) {
+                    ack_list.retain(|v| v != &vlad);
+                }
+            }
+            _ => {
+                tracing::debug!("Received event: {:?}", event);
+            }
+        }
+    }
+}
+
+/// Re-subscribes to every VLAD in `followed`, and to its recovery topic in case we guard it,
+/// and resolves the ones whose head moved on.
+///
+/// `current_head` returns the head we already have for a VLAD, if any.
+pub async fn refresh_followed(
+    peer: &DefaultBsPeer<KeyMan>,
+    followed: Vec<Vlad>,
+    current_head: impl Fn(&Vlad) -> Option<Cid>,
+    mut on_update: impl FnMut(NodeUpdate),
+) {
+    let Some(network_client) = peer.network_client.as_ref() else {
+        return;
+    };
+    for vlad in followed {
+        if let Err(e) = network_client.subscribe(vlad.to_string()).await {
+            tracing::error!("Failed to re-subscribe to VLAD {}: {}", vlad, e);
+            continue;
+        }
+        subscribe_recovery(peer, &vlad).await;
+        let Some(head) = resolve_head(peer, &vlad).await else {
+            tracing::warn!("Could not refresh head for VLAD: {}", vlad);
+            continue;
+        };
+        if current_head(&vlad).as_ref() == Some(&head) {
+            continue;
+        }
+        match network_client.resolve_plog(&head).await {
+            Ok(resolved_plog) => {
+                on_update(NodeUpdate::Resolved(
+                    vlad,
+                    FollowedPeer::resolved(resolved_plog.log),
+                ));
+            }
+            Err(e) => tracing::error!("Failed to refresh plog for VLAD {}: {}", vlad, e),
+        }
+    }
+}
+
+/// Subscribes to the recovery topic of `vlad`, see [recovery::topic].
+pub async fn subscribe_recovery(peer: &DefaultBsPeer<KeyMan>, vlad: &Vlad) {
+    let Some(network_client) = peer.network_client.as_ref() else {
+        return;
+    };
+    if let Err(e) = network_client.subscribe(recovery::topic(vlad)).await {
+        tracing::error!("Failed to subscribe to recovery of VLAD {}: {}", vlad, e);
+    }
+}
+
+/// Sends `message` on the recovery topic of `vlad`.
+pub async fn send_recovery(
+    peer: &DefaultBsPeer<KeyMan>,
+    vlad: &Vlad,
+    message: &RecoveryMessage,
+) -> Result<(), RecoveryError> {
+    let network_client = peer
+        .network_client
+        .as_ref()
+        .ok_or_else(|| RecoveryError::Network("Network client not initialized".to_string()))?;
+    network_client
+        .publish(message.to_bytes(), recovery::topic(vlad))
+        .await
+        .map_err(|e| RecoveryError::Network(e.to_string()))?;
+    Ok(())
+}
+
+/// Looks up the current head [Cid] of `vlad` in the DHT.
+pub async fn resolve_head(peer: &DefaultBsPeer<KeyMan>, vlad: &Vlad) -> Option<Cid> {
+    let network_client = peer.network_client.as_ref()?;
+    let vlad_bytes: Vec<u8> = vlad.clone().into();
+    let cid_bytes = retry(|| network_client.get_record(vlad_bytes.clone()))
+        .await
+        .ok()?;
+    Cid::try_from(cid_bytes.as_slice()).ok()
+}
+
+/// Looks up the current plog of `vlad`, through its head in the DHT.
+pub async fn resolve_plog(peer: &DefaultBsPeer<KeyMan>, vlad: &Vlad) -> Option<Log> {
+    let network_client = peer.network_client.as_ref()?;
+    let head = resolve_head(peer, vlad).await?;
+    match retry(|| network_client.resolve_plog(&head)).await {
+        Ok(resolved) => Some(resolved.log),
+        Err(e) => {
+            tracing::error!("Failed to resolve plog for VLAD {}: {}", vlad, e);
+            None
+        }
+    }
+}
+
+/// Runs `f` until it succeeds or [MAX_RETRIES] retries have failed, backing off exponentially.
+async fn retry<T, E, F, Fut>(mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut retries = 0;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) if retries >= MAX_RETRIES => return Err(e),
+            Err(_) => {
+                retries += 1;
+                tokio::time::sleep(std::time::Duration::from_secs(2u64.pow(retries))).await;
+            }
+        }
+    }
+}
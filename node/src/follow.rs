@@ -0,0 +1,229 @@
+//! The list of VLADs we follow, persisted through the [StorageProvider].
+//!
+//! Each followed peer is stored under its own `peers/<digest>` key, and keeps the last head
+//! [Cid] and plog we resolved for it, so the list can be shown right away on startup and
+//! refreshed in the background.
+use crate::storage::{StorageError, StorageProvider};
+use data_encoding::BASE32_NOPAD;
+use multicid::{Cid, Vlad};
+use provenance_log::Log;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Each followed peer is stored under this namespace, see [followed_key].
+const FOLLOW_NAMESPACE: &str = "peers/";
+
+/// Bump this whenever the stored layout changes.
+const FOLLOW_LIST_VERSION: u8 = 1;
+
+/// The followed peers, keyed by their [Vlad].
+pub type FollowList = HashMap<Vlad, FollowedPeer>;
+
+/// A followed peer and whatever we last managed to resolve for it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FollowedPeer {
+    /// The head [Cid] of the plog the last time it was resolved
+    pub head: Option<Cid>,
+    /// The last resolved plog
+    pub plog: Option<Log>,
+    /// Unix timestamp, in seconds, of the last successful resolution
+    pub resolved_at: Option<u64>,
+}
+
+impl FollowedPeer {
+    /// A freshly resolved plog, stamped with the current time.
+    pub fn resolved(log: Log) -> Self {
+        Self {
+            head: Some(log.head.clone()),
+            plog: Some(log),
+            resolved_at: Some(now_secs()),
+        }
+    }
+
+    /// Human readable age of the last resolution, if there was one.
+    pub fn resolved_ago(&self) -> Option<String> {
+        let secs = now_secs().saturating_sub(self.resolved_at?);
+        Some(match secs {
+            0..=59 => format!("{secs}s ago"),
+            60..=3599 => format!("{}m ago", secs / 60),
+            3600..=86399 => format!("{}h ago", secs / 3600),
+            _ => format!("{}d ago", secs / 86400),
+        })
+    }
+}
+
+/// The on-disk form of a [FollowedPeer].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct FollowRecord {
+    vlad: Vec<u8>,
+    head: Option<Vec<u8>>,
+    plog: Option<Vec<u8>>,
+    resolved_at: Option<u64>,
+}
+
+impl FollowRecord {
+    pub(crate) fn new(vlad: &Vlad, followed: &FollowedPeer) -> Self {
+        Self {
+            vlad: vlad.clone().into(),
+            head: followed.head.clone().map(Into::into),
+            plog: followed.plog.clone().map(Into::into),
+            resolved_at: followed.resolved_at,
+        }
+    }
+
+    pub(crate) fn decode(self) -> Option<(Vlad, FollowedPeer)> {
+        let Ok(vlad) = Vlad::try_from(self.vlad.as_slice()) else {
+            tracing::warn!("Skipping followed peer with an invalid VLAD");
+            return None;
+        };
+        let followed = FollowedPeer {
+            head: self.head.and_then(|b| Cid::try_from(b.as_slice()).ok()),
+            plog: self.plog.and_then(|b| Log::try_from(b.as_slice()).ok()),
+            resolved_at: self.resolved_at,
+        };
+        Some((vlad, followed))
+    }
+}
+
+/// A single followed peer, as stored under [FOLLOW_NAMESPACE].
+#[derive(Serialize, Deserialize)]
+struct StoredFollow {
+    version: u8,
+    peer: FollowRecord,
+}
+
+/// The storage key of a followed peer: the base32 SHA-256 digest of its VLAD, since a VLAD
+/// is longer than a key segment may be. The VLAD itself is kept in the [FollowRecord].
+fn followed_key(vlad: &Vlad) -> String {
+    let vlad_bytes: Vec<u8> = vlad.clone().into();
+    let digest = Sha256::digest(&vlad_bytes);
+    format!("{FOLLOW_NAMESPACE}{}", BASE32_NOPAD.encode(&digest))
+}
+
+/// Loads the follow list from storage.
+///
+/// Entries that fail to load or decode are skipped, so one bad record doesn't lose the whole list.
+pub async fn load_follow_list(storage: &StorageProvider) -> FollowList {
+    let keys = match storage.list_keys(FOLLOW_NAMESPACE).await {
+        Ok(keys) => keys,
+        Err(err) => {
+            tracing::error!("Failed to list followed peers: {}", err);
+            return FollowList::new();
+        }
+    };
+
+    let mut list = FollowList::new();
+    for key in keys {
+        let bytes = match storage.load(&key).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                tracing::error!("Failed to load followed peer {}: {}", key, err);
+                continue;
+            }
+        };
+        let stored = match ciborium::from_reader::<StoredFollow, _>(bytes.as_slice()) {
+            Ok(stored) if stored.version == FOLLOW_LIST_VERSION => stored,
+            Ok(stored) => {
+                tracing::warn!(
+                    "Unknown version {} for followed peer {}",
+                    stored.version,
+                    key
+                );
+                continue;
+            }
+            Err(err) => {
+                tracing::error!("Failed to decode followed peer {}: {}", key, err);
+                continue;
+            }
+        };
+        list.extend(stored.peer.decode());
+    }
+    list
+}
+
+/// Saves one followed peer under its own key.
+pub async fn save_followed(
+    storage: &StorageProvider,
+    vlad: &Vlad,
+    followed: &FollowedPeer,
+) -> Result<(), StorageError> {
+    let stored = StoredFollow {
+        version: FOLLOW_LIST_VERSION,
+        peer: FollowRecord::new(vlad, followed),
+    };
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&stored, &mut bytes)
+        .map_err(|err| StorageError::Backend(format!("Failed to encode followed peer: {err}")))?;
+    storage.save(&followed_key(vlad), &bytes).await
+}
+
+/// Removes a peer we no longer follow.
+pub async fn remove_followed(storage: &StorageProvider, vlad: &Vlad) -> Result<(), StorageError> {
+    storage.delete(&followed_key(vlad)).await
+}
+
+/// Writes only what changed between the `previous` and `current` follow lists.
+pub async fn save_changes(
+    storage: &StorageProvider,
+    previous: &FollowList,
+    current: &FollowList,
+) -> Result<(), StorageError> {
+    for vlad in previous.keys().filter(|vlad| !current.contains_key(vlad)) {
+        remove_followed(storage, vlad).await?;
+    }
+    for (vlad, followed) in current {
+        if previous.get(vlad) != Some(followed) {
+            save_followed(storage, vlad, followed).await?;
+        }
+    }
+    Ok(())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+    use crate::storage::validate_key;
+    use crate::test_util::vlad;
+
+    #[test]
+    fn followed_key_is_a_valid_storage_key() {
+        let peer = vlad(1);
+        assert!(Vec::<u8>::from(peer.clone()).len() * 2 > 128);
+        let key = followed_key(&peer);
+        assert_eq!(validate_key(&key), Ok(()));
+        assert_eq!(key.len(), FOLLOW_NAMESPACE.len() + 52);
+        assert_eq!(key, followed_key(&vlad(1)));
+        assert_ne!(key, followed_key(&vlad(2)));
+    }
+
+    #[tokio::test]
+    async fn followed_peers_round_trip_through_storage() {
+        let storage = StorageProvider::new(MemoryStorage::default());
+        let vlad = vlad(1);
+        let followed = FollowedPeer {
+            resolved_at: Some(42),
+            ..Default::default()
+        };
+        let list = FollowList::from([(vlad, followed)]);
+
+        save_changes(&storage, &FollowList::new(), &list)
+            .await
+            .unwrap();
+        assert_eq!(load_follow_list(&storage).await, list);
+
+        save_changes(&storage, &list, &FollowList::new())
+            .await
+            .unwrap();
+        assert!(load_follow_list(&storage).await.is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+//! Several named identities in one install, each with its own wallet, plog and follow list.
+//!
+//! The `default` identity uses the storage as it is, so an install from before identities
+//! keeps its wallet. Every other identity keeps its data below `identities/<name>/`, see
+//! [StorageProvider::scoped], and is listed in the registry stored under [REGISTRY_KEY].
+use crate::storage::{SEED_STORAGE_KEY, StorageError, StorageProvider, validate_key};
+
+/// Where the names of the identities are stored, unencrypted so they can be listed before
+/// any wallet is unlocked.
+pub const REGISTRY_KEY: &str = "VAIBER_IDENTITIES";
+
+/// The identity every install has, kept in the storage itself.
+pub const DEFAULT_IDENTITY: &str = "default";
+
+/// Named identities are stored below this namespace.
+pub(crate) const NAMESPACE: &str = "identities";
+
+/// The throwaway identity of dev builds is stored below this namespace, see [dev_storage].
+pub(crate) const DEV_NAMESPACE: &str = "dev";
+
+/// Errors listing, adding or opening identities.
+#[derive(Debug, thiserror::Error)]
+pub enum IdentityError {
+    /// Identity names must be a single valid storage key segment
+    #[error("Invalid identity name {0:?}, use letters, digits, '-', '_' and '.'")]
+    InvalidName(String),
+
+    /// There is already an identity with this name
+    #[error("An identity named {0} already exists")]
+    AlreadyExists(String),
+
+    /// The registry could not be read
+    #[error("The identity registry is unreadable: {0}")]
+    Corrupted(String),
+
+    /// From<StorageError>
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+/// Checks that `name` can name an identity, e.g. `alice` or `work-2`.
+pub fn validate_name(name: &str) -> Result<(), IdentityError> {
+    if name.contains('/') || validate_key(name).is_err() {
+        return Err(IdentityError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// The names of every identity in `root`, the default one first.
+pub async fn list(root: &StorageProvider) -> Result<Vec<String>, IdentityError> {
+    let (_, names) = load_registry(root).await?;
+    Ok(std::iter::once(DEFAULT_IDENTITY.to_string())
+        .chain(names)
+        .collect())
+}
+
+/// Adds an identity called `name` to the registry in `root`. Its wallet is created as usual,
+/// in the storage from [storage].
+pub async fn add(root: &StorageProvider, name: &str) -> Result<(), IdentityError> {
+    validate_name(name)?;
+    if name == DEFAULT_IDENTITY {
+        return Err(IdentityError::AlreadyExists(name.to_string()));
+    }
+    // Someone else may add an identity at the same time, so retry until ours goes in
+    loop {
+        let (stored, mut names) = load_registry(root).await?;
+        if names.iter().any(|existing| existing == name) {
+            return Err(IdentityError::AlreadyExists(name.to_string()));
+        }
+        names.push(name.to_string());
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&names, &mut bytes)
+            .map_err(|e| IdentityError::Corrupted(e.to_string()))?;
+        match root
+            .compare_and_swap(REGISTRY_KEY, stored.as_deref(), Some(&bytes))
+            .await
+        {
+            Ok(()) => return Ok(()),
+            Err(StorageError::Conflict(_)) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// The storage of the identity called `name` in `root`.
+pub fn storage(root: &StorageProvider, name: &str) -> Result<StorageProvider, IdentityError> {
+    validate_name(name)?;
+    if name == DEFAULT_IDENTITY {
+        return Ok(root.clone());
+    }
+    Ok(root.scoped(&format!("{NAMESPACE}/{name}"))?)
+}
+
+/// Deletes everything the identity called `name` keeps in `root`: its wallet, plog, follow
+/// list and settings. It stays listed, so a new wallet can be created under its name.
+///
+/// The default identity shares `root` with the others, so their data and the registry are
+/// left alone. The wallet goes last, so a reset that fails half way can be tried again.
+pub async fn clear(root: &StorageProvider, name: &str) -> Result<(), IdentityError> {
+    let storage = storage(root, name)?;
+    let shared = [format!("{NAMESPACE}/"), format!("{DEV_NAMESPACE}/")];
+    let mut keys = storage
+        .list_keys("")
+        .await?
+        .into_iter()
+        .filter(|key| {
+            name != DEFAULT_IDENTITY
+                || !(key == REGISTRY_KEY || shared.iter().any(|prefix| key.starts_with(prefix)))
+        })
+        .collect::<Vec<_>>();
+    keys.sort_by_key(|key| key == SEED_STORAGE_KEY);
+    for key in keys {
+        storage.delete(&key).await?;
+    }
+    Ok(())
+}
+
+/// The storage of the throwaway identity dev builds use instead of logging in.
+///
+/// Dev builds make up a random wallet seed on every launch, so whatever an earlier launch
+/// left here can't be read anymore and has to be deleted first, see
+/// [StorageProvider::delete_all].
+pub fn dev_storage(root: &StorageProvider) -> Result<StorageProvider, IdentityError> {
+    Ok(root.scoped(DEV_NAMESPACE)?)
+}
+
+/// The stored registry, as read, and the names of the identities in it.
+async fn load_registry(
+    root: &StorageProvider,
+) -> Result<(Option<Vec<u8>>, Vec<String>), IdentityError> {
+    let Some(stored) = root.try_load(REGISTRY_KEY).await? else {
+        return Ok((None, Vec::new()));
+    };
+    let names = ciborium::from_reader(stored.as_slice())
+        .map_err(|e| IdentityError::Corrupted(e.to_string()))?;
+    Ok((Some(stored), names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[test]
+    fn names_are_a_single_key_segment() {
+        for name in ["alice", "work-2", "a_b.c"] {
+            assert!(validate_name(name).is_ok(), "{name}");
+        }
+        for name in ["", "a/b", "..", ".hidden", "with space", "ünicode"] {
+            assert!(
+                matches!(validate_name(name), Err(IdentityError::InvalidName(_))),
+                "{name}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn added_identities_are_listed_once_after_the_default() {
+        let root = StorageProvider::new(MemoryStorage::default());
+        assert_eq!(list(&root).await.unwrap(), vec![DEFAULT_IDENTITY]);
+
+        add(&root, "alice").await.unwrap();
+        add(&root, "bob").await.unwrap();
+        for name in ["alice", DEFAULT_IDENTITY] {
+            assert!(matches!(
+                add(&root, name).await,
+                Err(IdentityError::AlreadyExists(_))
+            ));
+        }
+        assert!(matches!(
+            add(&root, "../escape").await,
+            Err(IdentityError::InvalidName(_))
+        ));
+        assert_eq!(
+            list(&root).await.unwrap(),
+            vec![DEFAULT_IDENTITY, "alice", "bob"]
+        );
+    }
+
+    #[tokio::test]
+    async fn adding_retries_when_another_identity_goes_in_first() {
+        let memory = MemoryStorage::default();
+        let root = StorageProvider::new(memory.clone());
+        add(&root, "alice").await.unwrap();
+
+        // Another app adds carol between our read of the registry and our write
+        let mut theirs = Vec::new();
+        ciborium::into_writer(&["alice", "carol"], &mut theirs).unwrap();
+        memory.before_next_swap(move |values| {
+            values.insert(REGISTRY_KEY.to_string(), theirs);
+            Ok(())
+        });
+
+        add(&root, "bob").await.unwrap();
+        assert_eq!(
+            list(&root).await.unwrap(),
+            vec![DEFAULT_IDENTITY, "alice", "carol", "bob"]
+        );
+    }
+
+    #[tokio::test]
+    async fn clearing_an_identity_leaves_the_others_alone() {
+        let root = StorageProvider::new(MemoryStorage::default());
+        add(&root, "alice").await.unwrap();
+        let default = storage(&root, DEFAULT_IDENTITY).unwrap();
+        let alice = storage(&root, "alice").unwrap();
+        for identity in [&default, &alice] {
+            identity.save(SEED_STORAGE_KEY, b"seed").await.unwrap();
+            identity.save("peers/abc", b"followed").await.unwrap();
+        }
+
+        clear(&root, DEFAULT_IDENTITY).await.unwrap();
+        assert!(default.try_load(SEED_STORAGE_KEY).await.unwrap().is_none());
+        assert!(default.try_load("peers/abc").await.unwrap().is_none());
+        assert_eq!(alice.list_keys("").await.unwrap().len(), 2);
+        assert_eq!(list(&root).await.unwrap(), vec![DEFAULT_IDENTITY, "alice"]);
+
+        clear(&root, "alice").await.unwrap();
+        assert!(alice.list_keys("").await.unwrap().is_empty());
+        assert_eq!(list(&root).await.unwrap(), vec![DEFAULT_IDENTITY, "alice"]);
+    }
+}
@@ -0,0 +1,701 @@
+//! Keys derived from the wallet seed.
+//!
+//! Every key lives under a [provenance_log::Key] path and has a generation, which starts at 0
+//! and goes up each time the key at that path is replaced. The secret key for a path and
+//! generation comes from HKDF-SHA256 over a root derived from the seed, with the info
+//! `vaiber/key<path>/<generation>`, so all of them can be recovered from the username and
+//! password alone.
+//!
+//! The signing key keeps the derivation it had before there were other keys: generation 0 is
+//! generated from the seed itself, and later generations come from their own rotation root,
+//! with the info `vaiber/key/rotation/<generation>`.
+//!
+//! Keys can use any [KeyCodec]. For codecs other than Ed25519, `/<codec>` is appended to the
+//! info.
+//!
+//! The signing key is replaced by rotating it, and after a restart its current generation is
+//! found again by replaying the `/pubkey` history of our plog. The public keys there carry
+//! their codec, so the plog also records which algorithm each one uses.
+use bs_traits::sync::SyncSigner as _;
+use bs_wallets::memory::InMemoryKeyManager;
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore as _;
+use hkdf::Hkdf;
+use multicodec::Codec;
+use multihash::Multihash;
+use multikey::{Multikey, Views as _};
+use multisig::Multisig;
+use multiutil::CodecInfo as _;
+use provenance_log::{Key, Log, Op, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use zeroize::Zeroizing;
+
+/// The key manager every node uses.
+pub type KeyMan = InMemoryKeyManager<bs_peer::Error>;
+
+/// What [WalletKeys::lock] tries to sign with each wiped key, to check it is gone.
+const WIPE_PROBE: &[u8] = b"vaiber/key/wiped";
+
+/// HKDF info for the root every derived key comes from, so it never collides with other keys
+/// derived from the seed.
+const ROOT_KDF_INFO: &[u8] = b"vaiber/key/root/v1";
+
+/// HKDF info for the root the rotated signing keys come from.
+const ROTATION_KDF_INFO: &[u8] = b"vaiber/key/rotation/v1";
+
+/// The roots keys are derived from, instead of the seed itself.
+struct Roots {
+    keys: Zeroizing<[u8; 32]>,
+    rotation: Zeroizing<[u8; 32]>,
+}
+
+impl Roots {
+    fn new(seed: &[u8]) -> Self {
+        let expand = |info: &[u8]| {
+            let mut root = Zeroizing::new([0u8; 32]);
+            Hkdf::<Sha256>::new(None, seed)
+                .expand(info, root.as_mut())
+                .expect("32 bytes is a valid HKDF-SHA256 output length");
+            root
+        };
+        Roots {
+            keys: expand(ROOT_KDF_INFO),
+            rotation: expand(ROTATION_KDF_INFO),
+        }
+    }
+}
+
+/// The paths of the keys we derive.
+pub mod key_paths {
+    use bs::params::anykey::PubkeyParams;
+    use provenance_log::key::key_paths::ValidatedKeyParams as _;
+
+    /// The key our plog entries are signed with
+    pub const SIGNING: &str = PubkeyParams::KEY_PATH;
+    /// The key data is encrypted to us with
+    pub const ENCRYPTION: &str = "/vaiber/encryption";
+    /// The key held back to recover the plog when the signing key is lost or leaked
+    pub const RECOVERY: &str = "/vaiber/recovery";
+    /// Keys of other apps using our identity, one path per app below this
+    pub const APPS: &str = "/vaiber/apps/";
+
+    /// The key path for the app called `name`.
+    pub fn app(name: &str) -> String {
+        format!("{APPS}{name}")
+    }
+}
+
+/// The key algorithms we can derive keys for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyCodec {
+    #[default]
+    Ed25519,
+    /// For interop with existing secp256k1 tooling
+    Secp256k1,
+    /// NIST P-256, as used by hardware-backed keys
+    P256,
+}
+
+impl KeyCodec {
+    pub const ALL: [KeyCodec; 3] = [KeyCodec::Ed25519, KeyCodec::Secp256k1, KeyCodec::P256];
+
+    /// The codecs the key manager can generate keys for and sign with, which are the only
+    /// ones to offer. Each is tried with a throwaway key the first time.
+    pub fn available() -> &'static [KeyCodec] {
+        static AVAILABLE: OnceLock<Vec<KeyCodec>> = OnceLock::new();
+        AVAILABLE.get_or_init(|| {
+            KeyCodec::ALL
+                .into_iter()
+                .filter(|codec| match codec.probe() {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::warn!("Key codec {} is not available: {}", codec, e);
+                        false
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Fails with [KeyError::UnsupportedCodec] unless this codec is [KeyCodec::available].
+    pub fn ensure_available(self) -> Result<(), KeyError> {
+        if KeyCodec::available().contains(&self) {
+            Ok(())
+        } else {
+            Err(KeyError::UnsupportedCodec(self.secret_codec()))
+        }
+    }
+
+    /// Generates a throwaway key of this codec, then signs with it and verifies the signature.
+    fn probe(self) -> Result<(), KeyError> {
+        const MESSAGE: &[u8] = b"vaiber/key/probe";
+        let secret_key = KeyMan::generate_from_seed(&self.secret_codec(), &[1; 32])
+            .map_err(|e| KeyError::Derive(e.to_string()))?;
+        let key = DerivedKey::new(Key::default(), 0, self, secret_key)?;
+        if KeyCodec::of(&key.public_key) != Some(self) {
+            return Err(KeyError::UnsupportedCodec(key.public_key.codec()));
+        }
+        let signature = sign_with(&key.secret_key, MESSAGE)?;
+        key.public_key
+            .verify_view()
+            .and_then(|view| view.verify(&signature, Some(MESSAGE)))
+            .map_err(|e| KeyError::Sign(e.to_string()))
+    }
+
+    /// The codec of secret keys of this kind.
+    pub fn secret_codec(self) -> Codec {
+        match self {
+            KeyCodec::Ed25519 => Codec::Ed25519Priv,
+            KeyCodec::Secp256k1 => Codec::Secp256K1Priv,
+            KeyCodec::P256 => Codec::P256Priv,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyCodec::Ed25519 => "ed25519",
+            KeyCodec::Secp256k1 => "secp256k1",
+            KeyCodec::P256 => "p256",
+        }
+    }
+
+    /// The kind of a public or secret key codec.
+    pub fn from_codec(codec: Codec) -> Option<Self> {
+        match codec {
+            Codec::Ed25519Pub | Codec::Ed25519Priv => Some(KeyCodec::Ed25519),
+            Codec::Secp256K1Pub | Codec::Secp256K1Priv => Some(KeyCodec::Secp256k1),
+            Codec::P256Pub | Codec::P256Priv => Some(KeyCodec::P256),
+            _ => None,
+        }
+    }
+
+    /// The kind of `key`, if it is one we can derive.
+    pub fn of(key: &Multikey) -> Option<Self> {
+        Self::from_codec(key.codec())
+    }
+}
+
+impl fmt::Display for KeyCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for KeyCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyCodec::ALL
+            .into_iter()
+            .find(|codec| codec.name() == s)
+            .ok_or_else(|| {
+                let names = KeyCodec::ALL.map(KeyCodec::name).join(", ");
+                format!("Unknown key codec {s:?}, expected one of {names}")
+            })
+    }
+}
+
+/// Errors deriving or switching keys.
+#[derive(Debug, thiserror::Error)]
+pub enum KeyError {
+    /// A key, its public key or its fingerprint could not be generated
+    #[error("Failed to derive key: {0}")]
+    Derive(String),
+
+    /// The current signing key could not sign
+    #[error("Failed to sign: {0}")]
+    Sign(String),
+
+    /// The key manager did not take the key
+    #[error("Failed to store key: {0}")]
+    Store(String),
+
+    /// The key uses a codec we cannot derive keys for
+    #[error("Unsupported key codec {0:?}")]
+    UnsupportedCodec(Codec),
+
+    /// No key derived from the seed is the one the plog committed to for the next rotation
+    #[error("The plog committed to a next key that was not derived from this wallet")]
+    NotCommitted,
+
+    /// An entry of the plog does not verify, so its `/pubkey` history can't be trusted
+    #[error("The plog does not verify: {0}")]
+    Unverified(String),
+
+    /// No key derived from the seed is the plog's current signing key
+    #[error("The plog's signing key was not derived from this wallet, so it cannot be updated")]
+    NotDerived,
+
+    /// The keys were locked, see [WalletKeys::lock]
+    #[error("The wallet is locked")]
+    Locked,
+}
+
+/// A secret key at a path and generation, with its public key.
+pub(crate) struct DerivedKey {
+    pub path: Key,
+    pub generation: u64,
+    pub codec: KeyCodec,
+    pub secret_key: Multikey,
+    pub public_key: Multikey,
+    fingerprint: Multihash,
+}
+
+impl DerivedKey {
+    fn new(
+        path: Key,
+        generation: u64,
+        codec: KeyCodec,
+        secret_key: Multikey,
+    ) -> Result<Self, KeyError> {
+        let public_key = secret_key
+            .conv_view()
+            .and_then(|view| view.to_public_key())
+            .map_err(|e| KeyError::Derive(e.to_string()))?;
+        let fingerprint = secret_key
+            .fingerprint_view()
+            .and_then(|view| view.fingerprint(Codec::Sha2256))
+            .map_err(|e| KeyError::Derive(e.to_string()))?;
+        Ok(DerivedKey {
+            path,
+            generation,
+            codec,
+            secret_key,
+            public_key,
+            fingerprint,
+        })
+    }
+}
+
+/// The key manager our peer signs with, filled with keys derived from the wallet seed.
+///
+/// Only roots derived for the keys are kept, never the wallet seed. Clones share the key
+/// manager and the current generation of each key, and are all locked by [WalletKeys::lock].
+#[derive(Clone)]
+pub struct WalletKeys {
+    /// None once locked
+    key_manager: Arc<Mutex<Option<KeyMan>>>,
+    /// Generation 0 of the signing key for each codec, generated from the seed itself
+    initial: Arc<Mutex<HashMap<KeyCodec, Multikey>>>,
+    /// None once locked
+    roots: Arc<Mutex<Option<Roots>>>,
+    /// The generation and codec of each key in the key manager, by path
+    generations: Arc<Mutex<HashMap<String, (u64, KeyCodec)>>>,
+    /// The path, codec and public key of every secret key ever put in the key manager,
+    /// including the generations replaced since, so [WalletKeys::lock] can wipe them all
+    installed: Arc<Mutex<Vec<(Key, KeyCodec, Multikey)>>>,
+}
+
+impl WalletKeys {
+    /// Builds a key manager holding the `codec` signing key generated from the wallet `seed`.
+    ///
+    /// The codec only matters for a new plog, an existing one is followed by [WalletKeys::sync].
+    /// Fails with [KeyError::UnsupportedCodec] unless it is [KeyCodec::available].
+    pub fn new(seed: &[u8], codec: KeyCodec) -> Result<Self, KeyError> {
+        codec.ensure_available()?;
+        let key_manager = KeyMan::default();
+
+        // Generate the keys from seed, for whichever codec a plog names
+        let mut initial = HashMap::new();
+        for available in KeyCodec::available() {
+            let key = KeyMan::generate_from_seed(&available.secret_codec(), seed)
+                .map_err(|e| KeyError::Derive(e.to_string()))?;
+            initial.insert(*available, key);
+        }
+
+        // Store the key with a path
+        let signing =
+            DerivedKey::new(key_paths::SIGNING.into(), 0, codec, initial[&codec].clone())?;
+        let installed = vec![(signing.path.clone(), codec, signing.public_key.clone())];
+        put(&key_manager, signing)?;
+
+        Ok(WalletKeys {
+            key_manager: Arc::new(Mutex::new(Some(key_manager))),
+            initial: Arc::new(Mutex::new(initial)),
+            roots: Arc::new(Mutex::new(Some(Roots::new(seed)))),
+            generations: Arc::new(Mutex::new(HashMap::from([(
+                key_paths::SIGNING.to_string(),
+                (0, codec),
+            )]))),
+            installed: Arc::new(Mutex::new(installed)),
+        })
+    }
+
+    /// The key manager to start a peer with, which shares its keys with this one.
+    pub fn key_manager(&self) -> Result<KeyMan, KeyError> {
+        self.key_manager
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(KeyError::Locked)
+    }
+
+    /// The generation and codec of the key at `path` in the key manager, if it holds one.
+    pub fn generation(&self, path: &Key) -> Option<(u64, KeyCodec)> {
+        self.generations
+            .lock()
+            .unwrap()
+            .get(&path.to_string())
+            .copied()
+    }
+
+    /// The generation and codec of every key in the key manager, by path.
+    pub fn generations(&self) -> Vec<(String, u64, KeyCodec)> {
+        self.generations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, (generation, codec))| (path.clone(), *generation, *codec))
+            .collect()
+    }
+
+    /// The rotation index of the current signing key, 0 until the first rotation.
+    pub fn index(&self) -> u64 {
+        self.generation(&key_paths::SIGNING.into())
+            .map_or(0, |(generation, _)| generation)
+    }
+
+    /// The codec of the current signing key.
+    pub fn signing_codec(&self) -> KeyCodec {
+        self.generation(&key_paths::SIGNING.into())
+            .map(|(_, codec)| codec)
+            .unwrap_or_default()
+    }
+
+    /// The public key of the current signing key.
+    pub fn signing_public_key(&self) -> Result<Multikey, KeyError> {
+        self.public_key(
+            &key_paths::SIGNING.into(),
+            self.index(),
+            self.signing_codec(),
+        )
+    }
+
+    /// Derives the `codec` key at `path` and `generation`, and puts it in the key manager in
+    /// place of any other generation. Returns its public key.
+    pub fn store(
+        &self,
+        path: &Key,
+        generation: u64,
+        codec: KeyCodec,
+    ) -> Result<Multikey, KeyError> {
+        let key = self.derive(path, generation, codec)?;
+        let public_key = key.public_key.clone();
+        self.switch_to(key)?;
+        Ok(public_key)
+    }
+
+    /// The `codec` public key at `path` and `generation`, without touching the key manager.
+    pub fn public_key(
+        &self,
+        path: &Key,
+        generation: u64,
+        codec: KeyCodec,
+    ) -> Result<Multikey, KeyError> {
+        Ok(self.derive(path, generation, codec)?.public_key)
+    }
+
+    /// The `codec` key at `path` and `generation`.
+    pub(crate) fn derive(
+        &self,
+        path: &Key,
+        generation: u64,
+        codec: KeyCodec,
+    ) -> Result<DerivedKey, KeyError> {
+        let roots = self.roots.lock().unwrap();
+        let Some(roots) = roots.as_ref() else {
+            return Err(KeyError::Locked);
+        };
+        let signing = path.to_string() == key_paths::SIGNING;
+        if generation == 0 && signing {
+            let initial = self
+                .initial
+                .lock()
+                .unwrap()
+                .get(&codec)
+                .cloned()
+                .ok_or(KeyError::UnsupportedCodec(codec.secret_codec()))?;
+            return DerivedKey::new(path.clone(), 0, codec, initial);
+        }
+        let (root, mut info) = if signing {
+            (&roots.rotation, format!("vaiber/key/rotation/{generation}"))
+        } else {
+            (&roots.keys, format!("vaiber/key{path}/{generation}"))
+        };
+        if codec != KeyCodec::Ed25519 {
+            info.push_str(&format!("/{codec}"));
+        }
+        let mut key_seed = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, root.as_ref())
+            .expand(info.as_bytes(), key_seed.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        let secret_key = KeyMan::generate_from_seed(&codec.secret_codec(), key_seed.as_ref())
+            .map_err(|e| KeyError::Derive(e.to_string()))?;
+        DerivedKey::new(path.clone(), generation, codec, secret_key)
+    }
+
+    /// Signs `message` with the `codec` key at `path` and `generation`, giving the bytes of
+    /// the [Multisig]. The key is derived for this, never put in the key manager.
+    ///
+    /// This is for messages outside our plog, such as offering to guard someone.
+    pub(crate) fn sign(
+        &self,
+        path: &Key,
+        generation: u64,
+        codec: KeyCodec,
+        message: &[u8],
+    ) -> Result<Vec<u8>, KeyError> {
+        let key = self.derive(path, generation, codec)?;
+        Ok(sign_with(&key.secret_key, message)?.into())
+    }
+
+    /// Uses `key` for its path from now on.
+    pub(crate) fn switch_to(&self, key: DerivedKey) -> Result<(), KeyError> {
+        let (path, generation, codec) = (key.path.to_string(), key.generation, key.codec);
+        self.install(key)?;
+        self.generations
+            .lock()
+            .unwrap()
+            .insert(path, (generation, codec));
+        Ok(())
+    }
+
+    /// Puts `key` in the key manager under its path, and records it to be wiped on lock.
+    fn install(&self, key: DerivedKey) -> Result<(), KeyError> {
+        let guard = self.key_manager.lock().unwrap();
+        let key_manager = guard.as_ref().ok_or(KeyError::Locked)?;
+        self.installed
+            .lock()
+            .unwrap()
+            .push((key.path.clone(), key.codec, key.public_key.clone()));
+        put(key_manager, key)
+    }
+
+    /// Forgets every secret key and the roots they are derived from, in this and every clone.
+    ///
+    /// A peer started with [WalletKeys::key_manager] shares the key store and may outlive the
+    /// lock, so every secret key ever stored, the replaced generations too, is overwritten
+    /// with a throwaway key of its path and codec before the key manager is dropped. Nothing
+    /// can be derived or signed again until the wallet is opened and a new [WalletKeys] is made.
+    pub fn lock(&self) {
+        self.roots.lock().unwrap().take();
+        self.initial.lock().unwrap().clear();
+        self.generations.lock().unwrap().clear();
+        let installed = std::mem::take(&mut *self.installed.lock().unwrap());
+        let Some(key_manager) = self.key_manager.lock().unwrap().take() else {
+            return;
+        };
+
+        let mut wiped: Vec<(&Key, KeyCodec)> = Vec::new();
+        for (path, codec, _) in &installed {
+            if wiped.contains(&(path, *codec)) {
+                continue;
+            }
+            wiped.push((path, *codec));
+            let mut throwaway = Zeroizing::new([0u8; 32]);
+            OsRng.fill_bytes(throwaway.as_mut());
+            let replaced = KeyMan::generate_from_seed(&codec.secret_codec(), throwaway.as_ref())
+                .map_err(|e| KeyError::Derive(e.to_string()))
+                .and_then(|key| DerivedKey::new(path.clone(), 0, *codec, key))
+                .and_then(|key| put(&key_manager, key));
+            if let Err(e) = replaced {
+                tracing::error!("Failed to wipe key {}: {}", path, e);
+            }
+        }
+
+        // Whatever the key store keeps the secrets by, none of them may sign any more
+        for (path, _, public_key) in &installed {
+            if key_manager.try_sign(public_key, WIPE_PROBE).is_ok() {
+                tracing::error!("A key stored at {} still signs after locking", path);
+            }
+        }
+    }
+
+    /// Drops the key manager but keeps everything else, so installing a key fails the way it
+    /// would with a broken key store.
+    #[cfg(test)]
+    pub(crate) fn break_key_store(&self) {
+        self.key_manager.lock().unwrap().take();
+    }
+
+    /// Whether [WalletKeys::lock] was called.
+    pub fn is_locked(&self) -> bool {
+        self.roots.lock().unwrap().is_none()
+    }
+
+    /// Replays the `/pubkey` history of `plog`, and switches to the signing key it currently
+    /// names.
+    pub fn sync(&self, plog: &Log) -> Result<(), KeyError> {
+        let history = pubkey_history(plog)?;
+        let Some(current) = history.last() else {
+            return Ok(());
+        };
+
+        let codec = KeyCodec::of(current).ok_or(KeyError::UnsupportedCodec(current.codec()))?;
+
+        // Every /pubkey update after the first is a rotation, so that generation is tried first
+        let signing = Key::from(key_paths::SIGNING);
+        let expected = history.len() as u64 - 1;
+        for generation in std::iter::once(expected).chain(0..expected) {
+            let key = self.derive(&signing, generation, codec)?;
+            if &key.public_key == current {
+                if self.generation(&signing) != Some((generation, codec)) {
+                    tracing::info!("Signing with rotated key {}", generation);
+                    self.switch_to(key)?;
+                }
+                return Ok(());
+            }
+        }
+        Err(KeyError::NotDerived)
+    }
+}
+
+/// Stores `key` in `key_manager` under its path, and points the path at it.
+fn put(key_manager: &KeyMan, key: DerivedKey) -> Result<(), KeyError> {
+    // The path mapping is what switches keys, so a stored but unmapped key is never used
+    key_manager
+        .store_secret_key(key.path.clone(), key.secret_key)
+        .map_err(|e| KeyError::Store(e.to_string()))?;
+    key_manager
+        .update_path_mapping(key.path, key.fingerprint.into())
+        .map_err(|e| KeyError::Store(e.to_string()))
+}
+
+/// Signs `message` with `secret_key`.
+fn sign_with(secret_key: &Multikey, message: &[u8]) -> Result<Multisig, KeyError> {
+    secret_key
+        .sign_view()
+        .and_then(|view| view.sign(message, false, None))
+        .map_err(|e| KeyError::Sign(e.to_string()))
+}
+
+/// Every public key `plog` set as its `/pubkey`, oldest first.
+///
+/// Fails with [KeyError::Unverified] if an entry does not verify or names something other
+/// than a key, since leaving it out would make an older key look current.
+pub(crate) fn pubkey_history(plog: &Log) -> Result<Vec<Multikey>, KeyError> {
+    let pubkey_path = Key::from(key_paths::SIGNING);
+    let mut history = Vec::new();
+    for verified in plog.verify() {
+        let (_count, entry, _kvp) = verified.map_err(|e| KeyError::Unverified(e.to_string()))?;
+        for op in entry.ops() {
+            match op {
+                Op::Update(key, Value::Data(data)) if key == &pubkey_path => {
+                    let public_key = Multikey::try_from(data.as_slice())
+                        .map_err(|e| KeyError::Unverified(e.to_string()))?;
+                    history.push(public_key);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locking_drops_the_key_manager_in_every_clone() {
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let clone = keys.clone();
+        assert!(keys.key_manager().is_ok());
+        assert!(clone.signing_public_key().is_ok());
+
+        keys.lock();
+        assert!(clone.is_locked());
+        assert!(matches!(clone.key_manager(), Err(KeyError::Locked)));
+        assert!(matches!(clone.signing_public_key(), Err(KeyError::Locked)));
+        assert!(clone.generations().is_empty());
+    }
+
+    #[test]
+    fn a_key_manager_taken_before_locking_cannot_sign_after() {
+        const MESSAGE: &[u8] = b"vaiber/key/lock";
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let first = keys.signing_public_key().unwrap();
+        let rotated = keys
+            .store(&key_paths::SIGNING.into(), 1, KeyCodec::Ed25519)
+            .unwrap();
+        let peer_key_manager = keys.key_manager().unwrap();
+        let signed = peer_key_manager.try_sign(&rotated, MESSAGE).unwrap();
+        assert!(
+            rotated
+                .verify_view()
+                .and_then(|view| view.verify(&signed, Some(MESSAGE)))
+                .is_ok()
+        );
+
+        keys.lock();
+        // Neither the current generation nor the one it replaced can sign any more
+        assert!(peer_key_manager.try_sign(&rotated, MESSAGE).is_err());
+        assert!(peer_key_manager.try_sign(&first, MESSAGE).is_err());
+    }
+
+    #[test]
+    fn keys_depend_on_the_seed_path_generation_and_codec_alone() {
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let again = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let other_seed = WalletKeys::new(&[8; 32], KeyCodec::Ed25519).unwrap();
+        let paths: [Key; 3] = [
+            key_paths::SIGNING.into(),
+            key_paths::ENCRYPTION.into(),
+            key_paths::app("chat").as_str().into(),
+        ];
+
+        let mut seen = Vec::new();
+        for path in &paths {
+            for generation in 0..3 {
+                for &codec in KeyCodec::available() {
+                    let key = keys.public_key(path, generation, codec).unwrap();
+                    assert_eq!(again.public_key(path, generation, codec).unwrap(), key);
+                    assert_ne!(other_seed.public_key(path, generation, codec).unwrap(), key);
+                    assert_eq!(KeyCodec::of(&key), Some(codec));
+                    seen.push(key);
+                }
+            }
+        }
+        // Every path, generation and codec gives a key of its own
+        for (i, key) in seen.iter().enumerate() {
+            assert!(!seen[i + 1..].contains(key));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn sync_finds_the_rotated_key_again_after_a_restart() {
+        use crate::storage::StorageProvider;
+        use crate::storage::memory::MemoryStorage;
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageProvider::new(MemoryStorage::default());
+        let keys = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        let mut peer = crate::test_util::peer(&keys, &dir.path().join("peer")).await;
+        crate::plog::load_or_generate(&mut peer, &storage)
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            crate::plog::update(&mut peer, &storage, &keys, vec![], Some(KeyCodec::Ed25519))
+                .await
+                .unwrap();
+        }
+        assert_eq!(keys.index(), 2);
+        let plog = peer.plog().map(|plog| plog.clone()).unwrap();
+
+        // A restart starts over from the seed, at the first key
+        let restarted = WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap();
+        assert_eq!(restarted.index(), 0);
+        restarted.sync(&plog).unwrap();
+        assert_eq!(restarted.index(), 2);
+        assert_eq!(
+            restarted.signing_public_key().unwrap(),
+            keys.signing_public_key().unwrap()
+        );
+        assert_eq!(pubkey_history(&plog).unwrap().len(), 3);
+    }
+}
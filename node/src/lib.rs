@@ -0,0 +1,42 @@
+//! The vaiber node, independent of any UI.
+//!
+//! Storage, the follow list, our own plog and the handling of network events live here, so
+//! they can be driven by the Dioxus apps as well as by the headless `vaiber-node` binary.
+pub mod storage;
+#[cfg(not(target_arch = "wasm32"))]
+pub use storage::FileStorage;
+pub use storage::{
+    EncryptedStorage, SEED_STORAGE_KEY, StorageError, StorageProvider, WalletStorage, validate_key,
+};
+
+pub mod follow;
+pub use follow::{FollowList, FollowedPeer};
+
+pub mod plog;
+pub mod prerotation;
+pub mod recovery;
+
+pub mod blobs;
+
+pub mod events;
+pub use events::NodeUpdate;
+
+pub mod wallet;
+pub use wallet::WalletError;
+
+pub mod identity;
+
+pub mod autolock;
+
+pub mod backup;
+
+pub mod mnemonic;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod profile;
+
+mod keys;
+pub use keys::{KeyCodec, KeyError, KeyMan, WalletKeys, key_paths};
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,151 @@
+//! Headless vaiber node.
+//!
+//! Runs a [BsPeer] with file storage, unlocked with the same username and password as the
+//! apps, and follows the VLADs listed in a config file:
+//!
+//! ```toml
+//! follow = ["<vlad>", "<vlad>"]
+//! ```
+//!
+//! Credentials are read from `VAIBER_USERNAME` and `VAIBER_PASSWORD`, or prompted for on stdin.
+use bs_peer::BsPeer;
+use bs_peer::platform::StartConfig;
+use clap::Parser;
+use futures::StreamExt as _;
+use multicid::Vlad;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use vaiber_node::profile::{Profile, ProfileArgs};
+use vaiber_node::{
+    FileStorage, FollowList, FollowedPeer, KeyCodec, NodeUpdate, StorageProvider, WalletKeys,
+    events, follow, plog, wallet,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Headless vaiber node")]
+struct Args {
+    /// Where the wallet, plog and node state are kept, the `node` profile by default
+    #[command(flatten)]
+    profile: ProfileArgs,
+
+    /// TOML file listing the VLADs to follow
+    #[arg(long, env = "VAIBER_NODE_CONFIG")]
+    config: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct Config {
+    /// VLADs to follow, in addition to the ones followed before
+    #[serde(default)]
+    follow: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let config = match &args.config {
+        Some(path) => toml::from_str::<Config>(&std::fs::read_to_string(path)?)?,
+        None => Config::default(),
+    };
+
+    let data_dir = args
+        .profile
+        .profile_or(Profile::named("node")?)?
+        .data_dir()?;
+    let storage = StorageProvider::new_encrypted(FileStorage::with_dir(data_dir.clone())?);
+
+    let (username, password) = wallet::read_credentials()?;
+    let wallet = wallet::open_or_create(&storage, &username, &password).await?;
+    storage.unlock(wallet.seed());
+
+    let keys = WalletKeys::new(wallet.seed(), KeyCodec::default())?;
+    let mut peer = BsPeer::new(
+        keys.key_manager()?,
+        StartConfig {
+            base_path: Some(data_dir),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|e| format!("Failed to start peer: {e}"))?;
+    drop(wallet);
+
+    plog::load_or_generate(&mut peer, &storage).await?;
+    let plog = peer
+        .plog()
+        .ok_or("No plog yet, attach the one of a recovered wallet with `vaiber recover` first")?;
+    println!("VLAD: {}", plog.vlad);
+    if let Err(e) = keys.sync(&plog) {
+        tracing::warn!("{}", e);
+    }
+
+    // Everyone we followed before, plus whatever the config adds
+    let mut follow_list = follow::load_follow_list(&storage).await;
+    for vlad in &config.follow {
+        let vlad = Vlad::try_from_str(vlad).map_err(|e| format!("Invalid VLAD {vlad}: {e}"))?;
+        follow_list.entry(vlad.clone()).or_insert_with(|| {
+            tracing::info!("Following {}", vlad);
+            FollowedPeer::default()
+        });
+    }
+    follow::save_changes(&storage, &FollowList::new(), &follow_list).await?;
+    let follow_list = Arc::new(Mutex::new(follow_list));
+
+    let peer_events = peer.events.take().ok_or("Peer has no event stream")?;
+    let (updates, mut received) = futures::channel::mpsc::unbounded::<NodeUpdate>();
+
+    let followed = follow_list.lock().unwrap().keys().cloned().collect();
+    let heads = follow_list.clone();
+    let refresh = events::refresh_followed(
+        &peer,
+        followed,
+        move |vlad| heads.lock().unwrap().get(vlad)?.head.clone(),
+        {
+            let updates = updates.clone();
+            move |update| {
+                let _ = updates.unbounded_send(update);
+            }
+        },
+    );
+
+    let current = follow_list.clone();
+    let handle_events = events::run(
+        peer.clone(),
+        peer_events,
+        move || current.lock().unwrap().clone(),
+        move |update| {
+            let _ = updates.unbounded_send(update);
+        },
+    );
+
+    // Persist whatever gets resolved
+    let persist = async {
+        while let Some(update) = received.next().await {
+            match update {
+                NodeUpdate::Resolved(vlad, followed) => {
+                    tracing::info!("Resolved plog for VLAD: {}", vlad);
+                    if let Err(e) = follow::save_followed(&storage, &vlad, &followed).await {
+                        tracing::error!("Failed to save followed peer {}: {}", vlad, e);
+                    }
+                    follow_list.lock().unwrap().insert(vlad, followed);
+                }
+                NodeUpdate::Listening(address) => println!("Listening on: {address}"),
+                // Approving a recovery is left to the apps, where someone checks the request
+                NodeUpdate::Connected(_)
+                | NodeUpdate::Disconnected(_)
+                | NodeUpdate::Recovery(..) => {}
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = async { tokio::join!(refresh, handle_events, persist) } => {}
+        _ = tokio::signal::ctrl_c() => tracing::info!("Shutting down"),
+    }
+    Ok(())
+}
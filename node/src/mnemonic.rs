@@ -0,0 +1,103 @@
+//! The wallet seed as BIP-39 words, a paper backup that needs neither username nor password.
+//!
+//! The words encode the seed itself, not a seed derived from them, so they turn back into
+//! exactly the seed seed-keeper encrypts. A 32 byte seed gives 24 words.
+use bip39::{Language, Mnemonic};
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore as _;
+use zeroize::Zeroizing;
+
+/// Errors turning words into a seed or back.
+#[derive(Debug, thiserror::Error)]
+pub enum MnemonicError {
+    /// The words are not a valid mnemonic, or the seed has no mnemonic
+    #[error("Invalid recovery words: {0}")]
+    Invalid(String),
+}
+
+/// The words for `seed`.
+pub fn to_words(seed: &[u8]) -> Result<Zeroizing<Vec<String>>, MnemonicError> {
+    let mnemonic = Mnemonic::from_entropy_in(Language::English, seed)
+        .map_err(|e| MnemonicError::Invalid(e.to_string()))?;
+    Ok(Zeroizing::new(
+        mnemonic
+            .to_string()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+    ))
+}
+
+/// The seed for `words`, ignoring case and extra whitespace.
+pub fn to_seed(words: &str) -> Result<Zeroizing<Vec<u8>>, MnemonicError> {
+    let normalized = Zeroizing::new(
+        words
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, &normalized)
+        .map_err(|e| MnemonicError::Invalid(e.to_string()))?;
+    Ok(Zeroizing::new(mnemonic.to_entropy()))
+}
+
+/// `count` distinct random word positions out of `word_count`, in order, to ask back after the
+/// words were written down.
+pub fn quiz_positions(word_count: usize, count: usize) -> Vec<usize> {
+    let mut positions = Vec::with_capacity(count);
+    while positions.len() < count.min(word_count) {
+        let position = OsRng.next_u32() as usize % word_count;
+        if !positions.contains(&position) {
+            positions.push(position);
+        }
+    }
+    positions.sort_unstable();
+    positions
+}
+
+/// Reads the recovery words from `VAIBER_WORDS`, or prompts for them without echoing.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_words() -> std::io::Result<String> {
+    match std::env::var("VAIBER_WORDS") {
+        Ok(words) => Ok(words),
+        Err(_) => rpassword::prompt_password("Recovery words: "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_turn_back_into_the_seed() {
+        let seed = [7u8; 32];
+        let words = to_words(&seed).unwrap();
+        assert_eq!(words.len(), 24);
+        assert_eq!(to_seed(&words.join(" ")).unwrap().as_slice(), seed);
+
+        // Case and whitespace don't matter
+        let typed = format!("  {}\n", words.join("   ").to_uppercase());
+        assert_eq!(to_seed(&typed).unwrap().as_slice(), seed);
+    }
+
+    #[test]
+    fn words_with_a_bad_checksum_are_invalid() {
+        // The BIP-39 words of an all zero seed, whose last word carries the checksum
+        let words = to_words(&[0u8; 32]).unwrap();
+        assert_eq!(
+            words.join(" "),
+            format!("{} art", ["abandon"; 23].join(" "))
+        );
+
+        let bad_checksum = ["abandon"; 24].join(" ");
+        assert!(matches!(
+            to_seed(&bad_checksum),
+            Err(MnemonicError::Invalid(_))
+        ));
+        assert!(matches!(
+            to_seed("not recovery words"),
+            Err(MnemonicError::Invalid(_))
+        ));
+    }
+}
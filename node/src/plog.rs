@@ -0,0 +1,689 @@
+//! Our own plog: loading it from storage, or generating and saving a new one.
+use crate::events;
+use crate::keys::{KeyCodec, KeyError, KeyMan, WalletKeys, key_paths};
+use crate::prerotation;
+use crate::recovery::{self, Approval, GuardianState, Guardians, RecoveryError};
+use crate::storage::{StorageError, StorageProvider};
+use bs::params::anykey::PubkeyParams;
+use bs::update::OpParams;
+use bs_peer::peer::DefaultBsPeer;
+use bs_peer::utils::create_default_scripts;
+use multicid::Vlad;
+use provenance_log::key::key_paths::ValidatedKeyParams as _;
+use provenance_log::{Log, Script};
+
+/// Where our plog is stored.
+pub const PLOG_STORAGE_KEY: &str = "VLAD_STORAGE_KEY";
+
+/// Where the VLAD of a wallet recovered from words, or created to recover it through its
+/// guardians, is kept until its plog is attached or recovered.
+pub const PENDING_ATTACH_KEY: &str = "VAIBER_PENDING_ATTACH";
+
+/// Errors loading our stored plog or generating a new one. Nothing in storage is changed by
+/// them.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    /// Neither the stored plog nor its backup can be read
+    #[error("The stored plog and its backup are unreadable: {0}")]
+    Unreadable(String),
+
+    /// The stored plog could not be loaded
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    /// The peer did not accept the stored plog, or could not generate a new one
+    #[error("Failed to load plog: {0}")]
+    Peer(#[from] bs_peer::Error),
+}
+
+/// Loads the stored plog into `peer`, falling back on the backup if the stored one is
+/// unreadable. Returns whether there was a plog to load.
+pub async fn load(
+    peer: &mut DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+) -> Result<bool, LoadError> {
+    tracing::info!("Loading existing Plog from storage...");
+    let reason = match storage.try_load(PLOG_STORAGE_KEY).await {
+        Ok(None) => return Ok(false),
+        Ok(Some(plog_data)) => match Log::try_from(plog_data.as_slice()) {
+            Ok(plog) => {
+                tracing::info!("Plog loaded from storage successfully.");
+                peer.load(plog).await?;
+                return Ok(true);
+            }
+            Err(e) => e.to_string(),
+        },
+        Err(StorageError::Corrupted { reason, .. }) => reason,
+        Err(e) => return Err(e.into()),
+    };
+
+    // A write interrupted by a crash, fall back to the previous Plog
+    tracing::error!("Stored Plog is unreadable, trying backup: {}", reason);
+    let Some(plog) = storage
+        .load_backup(PLOG_STORAGE_KEY)
+        .await
+        .ok()
+        .and_then(|data| Log::try_from(data.as_slice()).ok())
+    else {
+        return Err(LoadError::Unreadable(reason));
+    };
+    tracing::warn!("Plog restored from backup.");
+    // Later updates replace the stored plog, so it has to be readable again
+    let plog_bytes: Vec<u8> = plog.clone().into();
+    if let Err(e) = storage.save(PLOG_STORAGE_KEY, &plog_bytes).await {
+        tracing::error!("Failed to save restored Plog: {}", e);
+    }
+    peer.load(plog).await?;
+    Ok(true)
+}
+
+/// Generates a new plog with the default lock and unlock scripts, and saves it.
+pub async fn generate(
+    peer: &mut DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+) -> Result<(), LoadError> {
+    let (lock_script, unlock_script) = create_default_scripts();
+    peer.generate(&lock_script, &unlock_script).await?;
+    save(peer, storage).await?;
+    Ok(())
+}
+
+/// Loads the stored plog, or generates and saves a new one if there is none yet.
+///
+/// A stored plog that can't be read is an error, never replaced by a new one. Neither is the
+/// plog of a wallet recovered from words: while it is [pending_attach], nothing is generated.
+pub async fn load_or_generate(
+    peer: &mut DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+) -> Result<(), LoadError> {
+    if !load(peer, storage).await? && pending_attach(storage).await?.is_none() {
+        generate(peer, storage).await?;
+    }
+    Ok(())
+}
+
+/// The VLAD whose plog a wallet recovered from words still has to [attach], or a wallet
+/// created for it still has to [recover], if any.
+pub async fn pending_attach(storage: &StorageProvider) -> Result<Option<Vlad>, StorageError> {
+    let Some(data) = storage.try_load(PENDING_ATTACH_KEY).await? else {
+        return Ok(None);
+    };
+    let corrupted = |reason: String| StorageError::Corrupted {
+        key: PENDING_ATTACH_KEY.to_string(),
+        reason,
+    };
+    let vlad = String::from_utf8(data).map_err(|e| corrupted(e.to_string()))?;
+    Vlad::try_from_str(&vlad)
+        .map(Some)
+        .map_err(|e| corrupted(e.to_string()))
+}
+
+/// Records that the plog of `vlad` is to be [attach]ed or [recover]ed, until it is.
+pub async fn set_pending_attach(
+    storage: &StorageProvider,
+    vlad: &Vlad,
+) -> Result<(), StorageError> {
+    storage
+        .save(PENDING_ATTACH_KEY, vlad.to_string().as_bytes())
+        .await
+}
+
+/// Errors adding an entry to our plog.
+///
+/// When [update] returns one of these, neither the plog nor the key manager have changed.
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    /// There is no plog to update yet
+    #[error("No plog to update, the peer is not initialized")]
+    NotInitialized,
+
+    /// The entry could not be created or appended
+    #[error("Failed to update plog: {0}")]
+    Update(#[from] bs_peer::Error),
+
+    /// The signing key could not be found, derived or switched
+    #[error(transparent)]
+    Keys(#[from] KeyError),
+
+    /// The stored plog was changed by someone else since it was loaded
+    #[error("The plog was changed elsewhere, load it again before updating")]
+    Conflict,
+
+    /// The new plog could not be saved
+    #[error(transparent)]
+    Storage(StorageError),
+
+    /// The plog of a VLAD could not be found on the network
+    #[error("Could not find the plog of {0}")]
+    Unresolved(Vlad),
+
+    /// The guardians could not be changed, or the plog recovered
+    #[error(transparent)]
+    Recovery(#[from] RecoveryError),
+}
+
+impl From<StorageError> for UpdateError {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::Conflict(_) => UpdateError::Conflict,
+            e => UpdateError::Storage(e),
+        }
+    }
+}
+
+/// Appends an entry with `ops` to our plog and saves it.
+///
+/// With `rotate_key`, the entry reveals the key the plog committed to as the new `/pubkey`,
+/// and commits to a key of that codec for the rotation after it, see [crate::prerotation].
+/// Once the entry is saved the key manager signs with the revealed key from then on. Any
+/// entry on a plog without a commitment yet adds one.
+///
+/// The update is all or nothing: if appending, saving or switching keys fails, the previous
+/// plog is put back in `peer` and in storage, and the key manager keeps signing with the
+/// previous key.
+pub async fn update(
+    peer: &mut DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+    keys: &WalletKeys,
+    mut ops: Vec<OpParams>,
+    rotate_key: Option<KeyCodec>,
+) -> Result<(), UpdateError> {
+    let Some(previous) = peer.plog().map(|plog| plog.clone()) else {
+        return Err(UpdateError::NotInitialized);
+    };
+    let previous_bytes: Vec<u8> = previous.clone().into();
+
+    // The entry has to be signed with the key the plog currently names
+    keys.sync(&previous)?;
+
+    // The stored plog, which the new one replaces unless someone else changed it meanwhile
+    let stored = storage.try_load(PLOG_STORAGE_KEY).await?;
+    if let Some(stored_plog) = stored.as_deref().and_then(|data| Log::try_from(data).ok()) {
+        if stored_plog.head != previous.head {
+            return Err(UpdateError::Conflict);
+        }
+    }
+
+    // Everything that can fail is prepared before the plog changes
+    if let Some(codec) = rotate_key {
+        codec.ensure_available()?;
+    }
+    let committed = prerotation::current_commitment(&previous)?;
+    let rotation = rotate_key
+        .map(|codec| prerotation::committed_key(keys, committed.as_deref(), codec))
+        .transpose()?;
+    if let Some(rotation) = &rotation {
+        // Reveal the key committed to as the new /pubkey
+        ops.push(OpParams::UseKey {
+            key: PubkeyParams::KEY_PATH.into(),
+            mk: rotation.public_key.clone(),
+        });
+    }
+
+    // Commit to the key after the one we sign with, unless that is already done
+    let next = match (&rotation, rotate_key) {
+        (Some(rotation), Some(codec)) => Some((rotation.generation + 1, codec)),
+        _ if committed.is_none() => Some((keys.index() + 1, keys.signing_codec())),
+        _ => None,
+    };
+    if let Some((generation, codec)) = next {
+        let next_key = keys.public_key(&key_paths::SIGNING.into(), generation, codec)?;
+        ops.push(prerotation::commit_op(&next_key)?);
+    }
+
+    // The locks this entry installs count the guardians it leaves in place
+    let mut guardians = GuardianState::from_plog(&previous)?;
+    for op in &ops {
+        guardians.apply_params(op);
+    }
+    let unlock = if rotation.is_some() {
+        prerotation::rotation_unlock()
+    } else {
+        default_unlock()
+    };
+    let config = entry_config(ops, unlock, &guardians.guardians());
+    if let Err(e) = peer.update(config).await {
+        restore(peer, previous).await;
+        return Err(e.into());
+    }
+
+    let Some(new_bytes) = peer.plog().map(|plog| Vec::<u8>::from(plog.clone())) else {
+        restore(peer, previous).await;
+        return Err(UpdateError::NotInitialized);
+    };
+    if let Err(e) = storage
+        .compare_and_swap(PLOG_STORAGE_KEY, stored.as_deref(), Some(&new_bytes))
+        .await
+    {
+        restore(peer, previous).await;
+        return Err(e.into());
+    }
+
+    // The new public key is in the stored plog, so sign with the new key from now on
+    if let Some(rotation) = rotation {
+        if let Err(e) = keys.switch_to(rotation) {
+            // Put the previous plog back, so it names the key we still sign with
+            if let Err(e) = storage
+                .compare_and_swap(PLOG_STORAGE_KEY, Some(&new_bytes), Some(&previous_bytes))
+                .await
+            {
+                tracing::error!("Failed to restore the stored Plog: {}", e);
+            }
+            restore(peer, previous).await;
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes the plog of `vlad`, as found in the DHT, our own again after the wallet was recovered
+/// from its words, see [crate::wallet::recover].
+///
+/// Fails with [KeyError::NotDerived] unless `keys` can sign for the plog, and with
+/// [UpdateError::Conflict] if there is a stored plog already. Once attached, the VLAD is no
+/// longer [pending_attach].
+pub async fn attach(
+    peer: &mut DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+    keys: &WalletKeys,
+    vlad: &Vlad,
+) -> Result<(), UpdateError> {
+    let plog = events::resolve_plog(peer, vlad)
+        .await
+        .ok_or_else(|| UpdateError::Unresolved(vlad.clone()))?;
+    keys.sync(&plog)?;
+    let plog_bytes: Vec<u8> = plog.clone().into();
+    storage
+        .compare_and_swap(PLOG_STORAGE_KEY, None, Some(&plog_bytes))
+        .await?;
+    peer.load(plog).await?;
+    // The stored plog keeps a new one from being generated from now on
+    if let Err(e) = storage.delete(PENDING_ATTACH_KEY).await {
+        tracing::error!("Failed to clear the pending VLAD: {}", e);
+    }
+    Ok(())
+}
+
+/// Takes over the plog of `vlad`, as found in the DHT, with the new wallet `keys`, through the
+/// `approvals` of its guardians, see [crate::recovery].
+///
+/// Fails with [RecoveryError::NotEnoughApprovals] unless enough of the approvals verify, and
+/// like [attach] with [UpdateError::Conflict] if there is a stored plog already. Once the
+/// recovery entry is stored, the VLAD is no longer [pending_attach].
+pub async fn recover(
+    peer: &mut DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+    keys: &WalletKeys,
+    vlad: &Vlad,
+    approvals: &[Approval],
+) -> Result<(), UpdateError> {
+    let plog = events::resolve_plog(peer, vlad)
+        .await
+        .ok_or_else(|| UpdateError::Unresolved(vlad.clone()))?;
+    let guardians = Guardians::from_plog(&plog)?;
+    let (ops, unlock) = recovery::recovery_entry(&guardians, keys, approvals)?;
+
+    // Signed with the new key, which the key manager already has as /pubkey
+    peer.load(plog).await?;
+    peer.update(entry_config(ops, unlock, &guardians)).await?;
+    let recovered = peer
+        .plog()
+        .map(|plog| plog.clone())
+        .ok_or(UpdateError::NotInitialized)?;
+    keys.sync(&recovered)?;
+    let plog_bytes: Vec<u8> = recovered.into();
+    storage
+        .compare_and_swap(PLOG_STORAGE_KEY, None, Some(&plog_bytes))
+        .await?;
+    // The stored plog keeps a new one from being generated from now on
+    if let Err(e) = storage.delete(PENDING_ATTACH_KEY).await {
+        tracing::error!("Failed to clear the pending VLAD: {}", e);
+    }
+    Ok(())
+}
+
+/// The unlock script of an entry signed with the current `/pubkey` key.
+fn default_unlock() -> Script {
+    let (_, unlock_script) = create_default_scripts();
+    Script::Code(provenance_log::Key::default(), unlock_script)
+}
+
+/// The config for an entry with `ops` and `unlock`, signed with the current `/pubkey` key.
+///
+/// Every entry (re)installs the locks that enforce the commitment, so plogs from before them
+/// get them with their next entry. They count the `guardians` in force after the entry. An
+/// entry that rotates unlocks them by revealing the key, a recovery with the approvals.
+fn entry_config(ops: Vec<OpParams>, unlock: Script, guardians: &Guardians) -> bs::update::Config {
+    bs::update::Config::builder()
+        .unlock(unlock)
+        .entry_signing_key(PubkeyParams::KEY_PATH.into())
+        .add_entry_lock_scripts(prerotation::lock_scripts(guardians))
+        .additional_ops(ops)
+        .build()
+}
+
+/// Puts `previous` back as the peer's plog after a failed update.
+async fn restore(peer: &mut DefaultBsPeer<KeyMan>, previous: Log) {
+    let changed = peer.plog().is_some_and(|plog| plog.head != previous.head);
+    if changed {
+        if let Err(e) = peer.load(previous).await {
+            tracing::error!("Failed to restore the previous Plog: {}", e);
+        }
+    }
+}
+
+/// Saves the peer's current plog, if it has one.
+pub async fn save(
+    peer: &DefaultBsPeer<KeyMan>,
+    storage: &StorageProvider,
+) -> Result<(), StorageError> {
+    let Some(plog) = peer.plog() else {
+        return Ok(());
+    };
+    let plog_bytes: Vec<u8> = plog.clone().into();
+    storage.save(PLOG_STORAGE_KEY, &plog_bytes).await
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use crate::storage::memory::MemoryStorage;
+    use crate::test_util;
+
+    /// A peer with a saved plog, and storage whose writes can be made to fail.
+    async fn faulty_plog() -> (
+        tempfile::TempDir,
+        MemoryStorage,
+        StorageProvider,
+        WalletKeys,
+        DefaultBsPeer<KeyMan>,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = MemoryStorage::default();
+        let storage = StorageProvider::new(memory.clone());
+        let keys = WalletKeys::new(&[2; 32], KeyCodec::default()).unwrap();
+        let mut peer = test_util::peer(&keys, &dir.path().join("peer")).await;
+        load_or_generate(&mut peer, &storage).await.unwrap();
+        (dir, memory, storage, keys, peer)
+    }
+
+    /// Tries to rotate the key of `peer` in a failing update, and checks that nothing changed.
+    async fn assert_failed_update_changes_nothing(
+        peer: &mut DefaultBsPeer<KeyMan>,
+        storage: &StorageProvider,
+        keys: &WalletKeys,
+    ) -> UpdateError {
+        let head = peer.plog().map(|plog| plog.head.clone()).unwrap();
+        let stored = storage.load(PLOG_STORAGE_KEY).await.unwrap();
+        let signing_key = keys.signing_public_key().unwrap();
+
+        let op = OpParams::UseStr {
+            key: "/name".into(),
+            s: "changed".to_string(),
+        };
+        let err = update(peer, storage, keys, vec![op], Some(KeyCodec::default()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(peer.plog().map(|plog| plog.head.clone()), Some(head));
+        assert_eq!(storage.load(PLOG_STORAGE_KEY).await.unwrap(), stored);
+        assert_eq!(keys.index(), 0);
+        assert_eq!(keys.signing_public_key().unwrap(), signing_key);
+        err
+    }
+
+    #[tokio::test]
+    async fn nothing_is_generated_while_a_recovered_plog_is_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageProvider::new_encrypted(MemoryStorage::default());
+        let words = crate::mnemonic::to_words(&[3; 32]).unwrap().join(" ");
+        let vlad = test_util::vlad(1);
+        let wallet = crate::wallet::recover(&storage, &words, &vlad, "recovered", "a password")
+            .await
+            .unwrap();
+        assert_eq!(pending_attach(&storage).await.unwrap(), Some(vlad));
+
+        // As after a restart before the plog was attached
+        let keys = WalletKeys::new(wallet.seed(), KeyCodec::default()).unwrap();
+        let mut peer = test_util::peer(&keys, dir.path()).await;
+        load_or_generate(&mut peer, &storage).await.unwrap();
+        assert!(peer.plog().is_none());
+        assert_eq!(storage.try_load(PLOG_STORAGE_KEY).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_new_plog_that_fails_to_save_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        let storage = StorageProvider::new(FileStorage::with_dir(data_dir.clone()).unwrap());
+        // A directory in the way of the temporary file the plog is written to first
+        std::fs::create_dir(data_dir.join(format!(".{PLOG_STORAGE_KEY}.tmp"))).unwrap();
+
+        let keys = WalletKeys::new(&[4; 32], KeyCodec::default()).unwrap();
+        let mut peer = test_util::peer(&keys, &dir.path().join("peer")).await;
+        let err = load_or_generate(&mut peer, &storage).await.unwrap_err();
+        assert!(matches!(err, LoadError::Storage(_)));
+    }
+
+    #[tokio::test]
+    async fn an_update_that_fails_to_save_changes_nothing() {
+        let (_dir, memory, storage, keys, mut peer) = faulty_plog().await;
+        memory.before_next_swap(|_| Err(StorageError::QuotaExceeded));
+
+        let err = assert_failed_update_changes_nothing(&mut peer, &storage, &keys).await;
+        assert!(matches!(
+            err,
+            UpdateError::Storage(StorageError::QuotaExceeded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn an_update_racing_another_write_changes_nothing() {
+        let (_dir, memory, storage, keys, mut peer) = faulty_plog().await;
+        memory.before_next_swap(|_| Err(StorageError::Conflict(PLOG_STORAGE_KEY.to_string())));
+
+        let err = assert_failed_update_changes_nothing(&mut peer, &storage, &keys).await;
+        assert!(matches!(err, UpdateError::Conflict));
+    }
+
+    #[tokio::test]
+    async fn an_update_whose_new_key_cant_be_stored_changes_nothing() {
+        let (_dir, memory, storage, keys, mut peer) = faulty_plog().await;
+        // The new plog is saved, then switching to its key fails
+        let broken = keys.clone();
+        memory.before_next_swap(move |_| {
+            broken.break_key_store();
+            Ok(())
+        });
+
+        let err = assert_failed_update_changes_nothing(&mut peer, &storage, &keys).await;
+        assert!(matches!(err, UpdateError::Keys(KeyError::Locked)));
+    }
+
+    #[tokio::test]
+    async fn the_locks_only_let_rotations_to_the_committed_key_through() {
+        let (_dir, _memory, storage, keys, mut peer) = faulty_plog().await;
+        // Commits to the next key and locks it, then rotates to it
+        update(&mut peer, &storage, &keys, vec![], None)
+            .await
+            .unwrap();
+        update(
+            &mut peer,
+            &storage,
+            &keys,
+            vec![],
+            Some(KeyCodec::default()),
+        )
+        .await
+        .unwrap();
+        let plog = peer.plog().map(|plog| plog.clone()).unwrap();
+        assert!(plog.verify().all(|verified| verified.is_ok()));
+
+        // Someone holding the current key, but not the next one, repoints the commitment
+        let theirs = WalletKeys::new(&[9; 32], KeyCodec::default()).unwrap();
+        let repoint = vec![prerotation::commit_op(&theirs.signing_public_key().unwrap()).unwrap()];
+        let head = plog.head.clone();
+        // The lock on the commitment wants the preimage of the current one, so the entry
+        // is refused when it is appended
+        let config = entry_config(repoint, default_unlock(), &Guardians::default());
+        assert!(peer.update(config).await.is_err());
+        assert_eq!(peer.plog().map(|plog| plog.head.clone()), Some(head));
+    }
+
+    #[tokio::test]
+    async fn guardians_only_change_with_a_rotation_and_can_recover_the_plog() {
+        let (dir, _memory, storage, keys, mut peer) = faulty_plog().await;
+        // Installs the locks
+        update(&mut peer, &storage, &keys, vec![], None)
+            .await
+            .unwrap();
+        let vlad = peer.plog().map(|plog| plog.vlad.clone()).unwrap();
+        let guardian = WalletKeys::new(&[6; 32], KeyCodec::default()).unwrap();
+        let guardians = Guardians {
+            guardians: vec![recovery::Guardian {
+                vlad: test_util::vlad(6),
+                key: guardian
+                    .public_key(&recovery::guardian_key_path(&vlad), 0, KeyCodec::default())
+                    .unwrap(),
+            }],
+            threshold: 1,
+        };
+        let changes = guardians.changes(&Guardians::default()).unwrap();
+        assert!(
+            update(&mut peer, &storage, &keys, changes.clone(), None)
+                .await
+                .is_err()
+        );
+        update(
+            &mut peer,
+            &storage,
+            &keys,
+            changes,
+            Some(KeyCodec::default()),
+        )
+        .await
+        .unwrap();
+        let plog = peer.plog().map(|plog| plog.clone()).unwrap();
+        assert_eq!(Guardians::from_plog(&plog).unwrap(), guardians);
+
+        // A new wallet, approved by someone who is not the guardian, is refused
+        let new_keys = WalletKeys::new(&[8; 32], KeyCodec::default()).unwrap();
+        let mut new_peer = test_util::peer(&new_keys, &dir.path().join("new")).await;
+        new_peer.load(plog.clone()).await.unwrap();
+        let recovery::RecoveryMessage::Request {
+            new_pubkey,
+            commitment,
+        } = recovery::request(&new_keys).unwrap()
+        else {
+            panic!("not a request");
+        };
+        let approval = |approver: &WalletKeys| match recovery::approve(
+            approver,
+            &test_util::vlad(6),
+            &vlad,
+            &new_pubkey,
+            &commitment,
+        ) {
+            Ok(recovery::RecoveryMessage::Approval(approval)) => approval,
+            other => panic!("not an approval: {other:?}"),
+        };
+        let forged = approval(&WalletKeys::new(&[7; 32], KeyCodec::default()).unwrap());
+        let mut forged_guardians = guardians.clone();
+        forged_guardians.guardians[0].key = WalletKeys::new(&[7; 32], KeyCodec::default())
+            .unwrap()
+            .public_key(&recovery::guardian_key_path(&vlad), 0, KeyCodec::default())
+            .unwrap();
+        let (ops, unlock) =
+            recovery::recovery_entry(&forged_guardians, &new_keys, &[forged]).unwrap();
+        let config = entry_config(ops, unlock, &guardians);
+        assert!(new_peer.update(config).await.is_err());
+        assert_eq!(
+            new_peer.plog().map(|plog| plog.head.clone()),
+            Some(plog.head.clone())
+        );
+
+        // Approved by the guardian, it verifies and the new wallet signs for the plog
+        let (ops, unlock) =
+            recovery::recovery_entry(&guardians, &new_keys, &[approval(&guardian)]).unwrap();
+        new_peer
+            .update(entry_config(ops, unlock, &guardians))
+            .await
+            .unwrap();
+        let recovered = new_peer.plog().map(|plog| plog.clone()).unwrap();
+        assert!(recovered.verify().all(|verified| verified.is_ok()));
+        assert_eq!(
+            prerotation::check_rotations(&recovered).last(),
+            Some(&(
+                recovered.entries.len() - 1,
+                prerotation::RotationCheck::Recovered(1)
+            ))
+        );
+        new_keys.sync(&recovered).unwrap();
+        assert_eq!(new_keys.index(), 0);
+        assert!(matches!(keys.sync(&recovered), Err(KeyError::NotDerived)));
+    }
+
+    #[tokio::test]
+    async fn an_update_of_a_plog_changed_elsewhere_is_refused() {
+        let (dir, _memory, storage, keys, mut peer) = faulty_plog().await;
+        // Another app on the same storage adds an entry first
+        let mut other = test_util::peer(&keys, &dir.path().join("other")).await;
+        load(&mut other, &storage).await.unwrap();
+        update(&mut other, &storage, &keys, vec![], None)
+            .await
+            .unwrap();
+        let stored = storage.load(PLOG_STORAGE_KEY).await.unwrap();
+
+        let head = peer.plog().map(|plog| plog.head.clone());
+        assert!(matches!(
+            update(&mut peer, &storage, &keys, vec![], None).await,
+            Err(UpdateError::Conflict)
+        ));
+        assert_eq!(peer.plog().map(|plog| plog.head.clone()), head);
+        assert_eq!(storage.load(PLOG_STORAGE_KEY).await.unwrap(), stored);
+    }
+
+    async fn stored_plog() -> (tempfile::TempDir, StorageProvider, WalletKeys, Log) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageProvider::new(FileStorage::with_dir(dir.path().join("data")).unwrap());
+        let keys = WalletKeys::new(&[1; 32], KeyCodec::default()).unwrap();
+        let mut peer = test_util::peer(&keys, &dir.path().join("peer")).await;
+        load_or_generate(&mut peer, &storage).await.unwrap();
+        let plog = peer.plog().map(|plog| plog.clone()).unwrap();
+        (dir, storage, keys, plog)
+    }
+
+    #[tokio::test]
+    async fn a_torn_plog_is_restored_from_its_backup() {
+        let (dir, storage, keys, plog) = stored_plog().await;
+        let plog_bytes: Vec<u8> = plog.clone().into();
+        // The next write keeps this one as its backup, and is torn by a crash
+        storage.save(PLOG_STORAGE_KEY, &plog_bytes).await.unwrap();
+        storage
+            .save(PLOG_STORAGE_KEY, &plog_bytes[..plog_bytes.len() / 2])
+            .await
+            .unwrap();
+
+        let mut peer = test_util::peer(&keys, &dir.path().join("restarted")).await;
+        assert!(load(&mut peer, &storage).await.unwrap());
+        assert_eq!(peer.plog().map(|p| p.head.clone()), Some(plog.head));
+        assert_eq!(storage.load(PLOG_STORAGE_KEY).await.unwrap(), plog_bytes);
+    }
+
+    #[tokio::test]
+    async fn an_unreadable_plog_is_never_replaced() {
+        let (dir, storage, keys, _) = stored_plog().await;
+        storage.delete(PLOG_STORAGE_KEY).await.unwrap();
+        storage.save(PLOG_STORAGE_KEY, b"not a plog").await.unwrap();
+
+        let mut peer = test_util::peer(&keys, &dir.path().join("restarted")).await;
+        assert!(matches!(
+            load_or_generate(&mut peer, &storage).await,
+            Err(LoadError::Unreadable(_))
+        ));
+        assert!(peer.plog().is_none());
+        assert_eq!(storage.load(PLOG_STORAGE_KEY).await.unwrap(), b"not a plog");
+    }
+}
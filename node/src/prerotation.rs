@@ -0,0 +1,451 @@
+//! Pre-rotation: the plog commits to the hash of the next signing key before it is used.
+//!
+//! The commitment is kept under [COMMITMENT_PATH] as the SHA2-256 multihash of the next
+//! public key. A rotation reveals the key committed to, and commits to the one after it in
+//! the same entry. Someone who only holds a leaked signing key does not know the committed
+//! key, so a rotation to their own key shows up as one that breaks the commitment. So does
+//! replacing or deleting the commitment without rotating, which is the only other way around
+//! it.
+//!
+//! Every entry we add locks `/pubkey` and the commitment with [lock_scripts], so an entry
+//! changing either only verifies if it is signed with the current key and the new `/pubkey`
+//! hashes to the commitment, see [rotation_unlock], or if enough guardians approved it, see
+//! [crate::recovery]. Plogs from before those locks, and any entry that drops them, are only
+//! caught by [check_rotations], for the apps to warn about.
+use crate::keys::{DerivedKey, KeyCodec, KeyError, WalletKeys, key_paths};
+use crate::recovery::{self, GuardianState, Guardians};
+use bs::update::OpParams;
+use multicodec::Codec;
+use multikey::Multikey;
+use provenance_log::Script;
+use provenance_log::{Key, Log, Op, Value};
+
+/// Where the hash of the next signing key is committed to.
+pub const COMMITMENT_PATH: &str = "/vaiber/next-pubkey";
+
+/// The lock scripts guarding `/pubkey` and [COMMITMENT_PATH], by path: an entry changing
+/// either has to be signed with the current `/pubkey`, and reveal a key hashing to the
+/// commitment in force before it.
+///
+/// With `guardians`, the ones in force after the entry, an entry approved by enough of them
+/// gets through instead, see [recovery::approval_lock]. The guardians, the threshold and the
+/// approvals are locked the same way, so they only change with a rotation or a recovery.
+pub(crate) fn lock_scripts(guardians: &Guardians) -> Vec<(Key, Script)> {
+    let rotation = format!(
+        r#"check_preimage("{COMMITMENT_PATH}") && check_signature("{}", "/entry/")"#,
+        key_paths::SIGNING
+    );
+    let lock = match recovery::approval_lock(guardians) {
+        Some((count, approved)) => format!("{count}({rotation}) || {approved}"),
+        None => rotation,
+    };
+    [
+        key_paths::SIGNING,
+        COMMITMENT_PATH,
+        recovery::GUARDIANS_PREFIX,
+        recovery::THRESHOLD_PATH,
+        recovery::APPROVALS_PREFIX,
+    ]
+    .into_iter()
+    .map(|path| (Key::from(path), Script::Code(Key::from(path), lock.clone())))
+    .collect()
+}
+
+/// The unlock script of an entry that rotates the key: the entry and its proof for the
+/// signature, with the `/pubkey` it reveals on top as the preimage of the commitment.
+pub(crate) fn rotation_unlock() -> Script {
+    let unlock = format!(
+        r#"push("/entry/"); push("/entry/proof"); push("{}");"#,
+        key_paths::SIGNING
+    );
+    Script::Code(Key::default(), unlock)
+}
+
+/// How a change of `/pubkey`, or of the commitment, relates to the commitment before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationCheck {
+    /// The new key is the one committed to
+    Committed,
+    /// There was no commitment to check against
+    Uncommitted,
+    /// The new key is not the one committed to, or the commitment was replaced or deleted
+    /// without rotating
+    Mismatch,
+    /// The key was replaced by a recovery carrying this many approvals that verify against
+    /// the guardians named before it, see [crate::recovery]
+    Recovered(usize),
+}
+
+/// The commitment to `public_key`, the bytes of its SHA2-256 multihash.
+pub fn commitment(public_key: &Multikey) -> Result<Vec<u8>, KeyError> {
+    let key_bytes: Vec<u8> = public_key.clone().into();
+    let hash = multihash::Builder::new_from_bytes(Codec::Sha2256, key_bytes.as_slice())
+        .and_then(|builder| builder.try_build())
+        .map_err(|e| KeyError::Derive(e.to_string()))?;
+    Ok(hash.into())
+}
+
+/// Checks every rotation in `plog`, and every change of its commitment, giving the index of
+/// each such entry with its result.
+///
+/// The first `/pubkey` is not a rotation, so it is never checked, and neither is the first
+/// commitment. A new key that is not the one committed to is [RotationCheck::Recovered] if
+/// the entry carries approvals from at least the threshold of guardians.
+pub fn check_rotations(plog: &Log) -> Vec<(usize, RotationCheck)> {
+    let mut checker = RotationChecker::default();
+    plog.verify()
+        .enumerate()
+        .filter_map(|(idx, v)| v.ok().map(|v| (idx, v)))
+        .filter_map(|(idx, (_count, entry, _kvp))| {
+            let ops = entry.ops().collect::<Vec<_>>();
+            checker.check(&ops).map(|check| (idx, check))
+        })
+        .collect()
+}
+
+/// Follows the `/pubkey`, the commitment and the guardians through the entries of a plog,
+/// oldest first.
+#[derive(Default)]
+struct RotationChecker {
+    has_pubkey: bool,
+    /// The commitment from the entries so far
+    committed: Option<Vec<u8>>,
+    /// The guardians from the entries so far
+    guardians: GuardianState,
+}
+
+impl RotationChecker {
+    /// Checks the next entry, with `ops`, if it rotates the key or changes a commitment.
+    fn check(&mut self, ops: &[&Op]) -> Option<RotationCheck> {
+        let pubkey_path = Key::from(key_paths::SIGNING);
+        let commitment_path = Key::from(COMMITMENT_PATH);
+
+        let revealed = ops.iter().find_map(|op| match op {
+            Op::Update(key, Value::Data(data)) if key == &pubkey_path => Some(data),
+            _ => None,
+        });
+        let mut commitment_changed = false;
+        let mut next_commitment = self.committed.clone();
+        for op in ops {
+            match op {
+                Op::Update(key, Value::Data(data)) if key == &commitment_path => {
+                    commitment_changed = true;
+                    next_commitment = Some(data.clone());
+                }
+                Op::Delete(key) if key == &commitment_path => {
+                    commitment_changed = true;
+                    next_commitment = None;
+                }
+                _ => {}
+            }
+        }
+
+        // The commitment and guardians in force for this entry are the ones from the entries
+        // before it
+        let check = match revealed {
+            Some(data) if self.has_pubkey => Some(self.check_revealed(data, ops)),
+            // Only a rotation may replace or drop a commitment
+            None if commitment_changed && self.committed.is_some() => Some(RotationCheck::Mismatch),
+            _ => None,
+        };
+        self.has_pubkey |= revealed.is_some();
+        self.committed = next_commitment;
+        for op in ops {
+            self.guardians.apply(op);
+        }
+        check
+    }
+
+    /// How the key revealed as `data`, in the entry with `ops`, relates to the commitment.
+    fn check_revealed(&self, data: &[u8], ops: &[&Op]) -> RotationCheck {
+        let guardians = self.guardians.guardians();
+        let approved = recovery::approved_by(&guardians, data, ops);
+        if !guardians.is_empty() && approved >= guardians.threshold {
+            return RotationCheck::Recovered(approved);
+        }
+        match (&self.committed, Multikey::try_from(data)) {
+            (None, _) => RotationCheck::Uncommitted,
+            (Some(expected), Ok(revealed)) => match commitment(&revealed) {
+                Ok(actual) if &actual == expected => RotationCheck::Committed,
+                _ => RotationCheck::Mismatch,
+            },
+            (Some(_), Err(_)) => RotationCheck::Mismatch,
+        }
+    }
+}
+
+/// The commitment currently in force in `plog`, if there is one.
+///
+/// Fails with [KeyError::Unverified] if an entry does not verify, since leaving it out could
+/// make an older commitment look current.
+pub fn current_commitment(plog: &Log) -> Result<Option<Vec<u8>>, KeyError> {
+    let commitment_path = Key::from(COMMITMENT_PATH);
+    let mut committed = None;
+    for verified in plog.verify() {
+        let (_count, entry, _kvp) = verified.map_err(|e| KeyError::Unverified(e.to_string()))?;
+        for op in entry.ops() {
+            match op {
+                Op::Update(key, Value::Data(data)) if key == &commitment_path => {
+                    committed = Some(data.clone());
+                }
+                Op::Delete(key) if key == &commitment_path => committed = None,
+                _ => {}
+            }
+        }
+    }
+    Ok(committed)
+}
+
+/// The op committing to `public_key` as the next signing key.
+pub(crate) fn commit_op(public_key: &Multikey) -> Result<OpParams, KeyError> {
+    Ok(OpParams::UseBin {
+        key: COMMITMENT_PATH.into(),
+        data: commitment(public_key)?,
+    })
+}
+
+/// The key to rotate to: the next generation of the signing key matching `committed`, or of
+/// `codec` if nothing was committed to yet.
+///
+/// With a commitment, `codec` is ignored: the key revealed is the one committed to, whatever
+/// its codec. The codec picked for a rotation only applies to the key committed to next,
+/// which the rotation after it reveals.
+pub(crate) fn committed_key(
+    keys: &WalletKeys,
+    committed: Option<&[u8]>,
+    codec: KeyCodec,
+) -> Result<DerivedKey, KeyError> {
+    let signing = Key::from(key_paths::SIGNING);
+    let generation = keys.index() + 1;
+    let Some(committed) = committed else {
+        return keys.derive(&signing, generation, codec);
+    };
+
+    // The commitment does not say which codec the key uses, so try each
+    for candidate in KeyCodec::ALL {
+        let Ok(key) = keys.derive(&signing, generation, candidate) else {
+            continue;
+        };
+        if commitment(&key.public_key)? == committed {
+            return Ok(key);
+        }
+    }
+    Err(KeyError::NotCommitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recovery::{APPROVALS_PREFIX, GUARDIANS_PREFIX, RecoveryMessage, THRESHOLD_PATH};
+    use crate::test_util::vlad;
+    use multicid::Vlad;
+
+    fn signing_key(keys: &WalletKeys, generation: u64) -> Multikey {
+        keys.public_key(&key_paths::SIGNING.into(), generation, KeyCodec::Ed25519)
+            .unwrap()
+    }
+
+    fn reveal(public_key: &Multikey) -> Op {
+        Op::Update(
+            key_paths::SIGNING.into(),
+            Value::Data(public_key.clone().into()),
+        )
+    }
+
+    fn commit(public_key: &Multikey) -> Op {
+        Op::Update(
+            COMMITMENT_PATH.into(),
+            Value::Data(commitment(public_key).unwrap()),
+        )
+    }
+
+    fn other_op() -> Op {
+        Op::Update("/profile/name".into(), Value::Str("alice".to_string()))
+    }
+
+    /// The checks for entries with `entries` as their ops, by entry index.
+    fn check(entries: &[Vec<Op>]) -> Vec<(usize, RotationCheck)> {
+        let mut checker = RotationChecker::default();
+        entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, ops)| {
+                let ops = ops.iter().collect::<Vec<_>>();
+                checker.check(&ops).map(|check| (idx, check))
+            })
+            .collect()
+    }
+
+    fn keys() -> WalletKeys {
+        WalletKeys::new(&[7; 32], KeyCodec::Ed25519).unwrap()
+    }
+
+    #[test]
+    fn rotating_to_the_committed_key_is_committed() {
+        let keys = keys();
+        let (first, second, third) = (
+            signing_key(&keys, 0),
+            signing_key(&keys, 1),
+            signing_key(&keys, 2),
+        );
+        let checks = check(&[
+            vec![reveal(&first), commit(&second)],
+            vec![other_op()],
+            vec![reveal(&second), commit(&third)],
+        ]);
+        assert_eq!(checks, vec![(2, RotationCheck::Committed)]);
+    }
+
+    #[test]
+    fn rotating_to_another_key_is_a_mismatch() {
+        let keys = keys();
+        let checks = check(&[
+            vec![
+                reveal(&signing_key(&keys, 0)),
+                commit(&signing_key(&keys, 1)),
+            ],
+            vec![reveal(&signing_key(&keys, 5))],
+        ]);
+        assert_eq!(checks, vec![(1, RotationCheck::Mismatch)]);
+    }
+
+    #[test]
+    fn rotating_without_a_commitment_is_uncommitted() {
+        let keys = keys();
+        let checks = check(&[
+            vec![reveal(&signing_key(&keys, 0))],
+            vec![reveal(&signing_key(&keys, 1))],
+        ]);
+        assert_eq!(checks, vec![(1, RotationCheck::Uncommitted)]);
+    }
+
+    #[test]
+    fn the_first_commitment_is_not_checked() {
+        let keys = keys();
+        let checks = check(&[
+            vec![reveal(&signing_key(&keys, 0))],
+            vec![commit(&signing_key(&keys, 1))],
+        ]);
+        assert!(checks.is_empty());
+    }
+
+    #[test]
+    fn replacing_the_commitment_without_rotating_is_a_mismatch() {
+        let keys = keys();
+        let leaked = signing_key(&keys, 9);
+        let checks = check(&[
+            vec![
+                reveal(&signing_key(&keys, 0)),
+                commit(&signing_key(&keys, 1)),
+            ],
+            vec![commit(&leaked)],
+            vec![reveal(&leaked)],
+        ]);
+        // The rotation matches the replaced commitment, but the replacement was flagged
+        assert_eq!(
+            checks,
+            vec![(1, RotationCheck::Mismatch), (2, RotationCheck::Committed)]
+        );
+    }
+
+    #[test]
+    fn deleting_the_commitment_without_rotating_is_a_mismatch() {
+        let keys = keys();
+        let checks = check(&[
+            vec![
+                reveal(&signing_key(&keys, 0)),
+                commit(&signing_key(&keys, 1)),
+            ],
+            vec![Op::Delete(COMMITMENT_PATH.into())],
+            vec![reveal(&signing_key(&keys, 9))],
+        ]);
+        assert_eq!(
+            checks,
+            vec![
+                (1, RotationCheck::Mismatch),
+                (2, RotationCheck::Uncommitted)
+            ]
+        );
+    }
+
+    /// The ops of an entry naming the wallet made from `fill` as the only guardian of
+    /// `guardee`, and the entry recovering it to `new`, approved by the wallet made from
+    /// `approver`.
+    fn recovery(fill: u8, approver: u8, guardee: &Vlad, new: &WalletKeys) -> [Vec<Op>; 2] {
+        let guardian_key = WalletKeys::new(&[fill; 32], KeyCodec::Ed25519)
+            .unwrap()
+            .public_key(
+                &recovery::guardian_key_path(guardee),
+                0,
+                KeyCodec::default(),
+            )
+            .unwrap();
+        let setup = vec![
+            Op::Update(
+                format!("{GUARDIANS_PREFIX}0/vlad").as_str().into(),
+                Value::Str(vlad(fill).to_string()),
+            ),
+            Op::Update(
+                format!("{GUARDIANS_PREFIX}0/key").as_str().into(),
+                Value::Data(guardian_key.into()),
+            ),
+            Op::Update(THRESHOLD_PATH.into(), Value::Str("1".to_string())),
+        ];
+
+        let new_pubkey: Vec<u8> = signing_key(new, 0).into();
+        let next = commitment(&signing_key(new, 1)).unwrap();
+        let approver = WalletKeys::new(&[approver; 32], KeyCodec::Ed25519).unwrap();
+        let Ok(RecoveryMessage::Approval(approval)) =
+            recovery::approve(&approver, &vlad(fill), guardee, &new_pubkey, &next)
+        else {
+            panic!("not an approval");
+        };
+        let recover = vec![
+            Op::Update(key_paths::SIGNING.into(), Value::Data(new_pubkey)),
+            Op::Update(COMMITMENT_PATH.into(), Value::Data(next)),
+            Op::Update(
+                format!("{APPROVALS_PREFIX}0/pubkey").as_str().into(),
+                Value::Data(approval.pubkey_signature),
+            ),
+            Op::Update(
+                format!("{APPROVALS_PREFIX}0/commitment").as_str().into(),
+                Value::Data(approval.commitment_signature),
+            ),
+        ];
+        [setup, recover]
+    }
+
+    #[test]
+    fn a_recovery_approved_by_the_guardians_is_recovered() {
+        let keys = keys();
+        let guardee = vlad(9);
+        let new = WalletKeys::new(&[5; 32], KeyCodec::Ed25519).unwrap();
+        let [setup, recover] = recovery(1, 1, &guardee, &new);
+        let checks = check(&[
+            vec![
+                reveal(&signing_key(&keys, 0)),
+                commit(&signing_key(&keys, 1)),
+            ],
+            setup,
+            recover,
+        ]);
+        assert_eq!(checks, vec![(2, RotationCheck::Recovered(1))]);
+    }
+
+    #[test]
+    fn a_recovery_approved_by_anyone_else_is_a_mismatch() {
+        let keys = keys();
+        let guardee = vlad(9);
+        let new = WalletKeys::new(&[5; 32], KeyCodec::Ed25519).unwrap();
+        let [setup, recover] = recovery(1, 2, &guardee, &new);
+        let checks = check(&[
+            vec![
+                reveal(&signing_key(&keys, 0)),
+                commit(&signing_key(&keys, 1)),
+            ],
+            setup,
+            recover,
+        ]);
+        assert_eq!(checks, vec![(2, RotationCheck::Mismatch)]);
+    }
+}
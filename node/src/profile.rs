@@ -0,0 +1,124 @@
+//! Named profiles, each with its own storage directory and therefore its own identity.
+//!
+//! The default profile uses the platform data directory, e.g. `~/.local/share/vaiber`.
+//! A named profile lives next to it with the name appended, e.g. `~/.local/share/vaiber-alice`,
+//! so several nodes can run side by side on one machine.
+use crate::validate_key;
+use directories::ProjectDirs;
+use std::path::PathBuf;
+
+/// Errors picking or locating a [Profile].
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    /// Profile names must be a single valid storage key segment
+    #[error("Invalid profile name: {0:?}")]
+    InvalidName(String),
+
+    /// The platform has no data directory we could use
+    #[error("Failed to get project directories")]
+    NoDataDir,
+
+    /// From<std::io::Error>
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Command line options selecting a [Profile], shared by every binary.
+#[derive(clap::Args, Debug, Default)]
+pub struct ProfileArgs {
+    /// Name of the profile to use
+    #[arg(long, env = "VAIBER_PROFILE")]
+    profile: Option<String>,
+
+    /// Use this data directory instead of a profile
+    #[arg(long, env = "VAIBER_DATA_DIR", conflicts_with = "profile")]
+    data_dir: Option<PathBuf>,
+}
+
+impl ProfileArgs {
+    /// The profile picked on the command line or in the environment, or `default`.
+    pub fn profile_or(self, default: Profile) -> Result<Profile, ProfileError> {
+        match (self.profile, self.data_dir) {
+            (_, Some(dir)) => Ok(Profile::Custom(dir)),
+            (Some(name), None) => Profile::named(name),
+            (None, None) => Ok(default),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Profile {
+    /// The platform data directory
+    Default,
+    /// A named profile next to the default one
+    Named(String),
+    /// An explicit `--data-dir`
+    Custom(PathBuf),
+}
+
+impl Profile {
+    /// A named profile. Names are a single storage key segment, e.g. `alice` or `node-2`.
+    pub fn named(name: impl Into<String>) -> Result<Self, ProfileError> {
+        let name = name.into();
+        if name.contains('/') || validate_key(&name).is_err() {
+            return Err(ProfileError::InvalidName(name));
+        }
+        Ok(Profile::Named(name))
+    }
+
+    /// Short name to show to the user.
+    pub fn name(&self) -> String {
+        match self {
+            Profile::Default => "default".to_string(),
+            Profile::Named(name) => name.clone(),
+            Profile::Custom(dir) => dir.display().to_string(),
+        }
+    }
+
+    /// The directory this profile stores its wallet data and node state in.
+    pub fn data_dir(&self) -> Result<PathBuf, ProfileError> {
+        match self {
+            Profile::Default => default_data_dir(),
+            Profile::Named(name) => {
+                let default = default_data_dir()?;
+                let mut dir_name = default.file_name().unwrap_or_default().to_owned();
+                dir_name.push(format!("-{name}"));
+                Ok(default.with_file_name(dir_name))
+            }
+            Profile::Custom(dir) => Ok(dir.clone()),
+        }
+    }
+
+    /// The default profile and every named profile that has been used on this machine.
+    pub fn list() -> Result<Vec<Profile>, ProfileError> {
+        let default = default_data_dir()?;
+        let mut prefix = default.file_name().unwrap_or_default().to_owned();
+        prefix.push("-");
+        let prefix = prefix.to_string_lossy().into_owned();
+
+        let mut profiles = vec![Profile::Default];
+        if let Some(parent) = default.parent() {
+            let mut named = std::fs::read_dir(parent)?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| {
+                    let name = entry
+                        .file_name()
+                        .to_str()?
+                        .strip_prefix(&prefix)?
+                        .to_string();
+                    Profile::named(name).ok()
+                })
+                .collect::<Vec<_>>();
+            named.sort_by_key(Profile::name);
+            profiles.extend(named);
+        }
+        Ok(profiles)
+    }
+}
+
+fn default_data_dir() -> Result<PathBuf, ProfileError> {
+    let project_dirs =
+        ProjectDirs::from("io", "peerpiper", "vaiber").ok_or(ProfileError::NoDataDir)?;
+    Ok(project_dirs.data_dir().to_path_buf())
+}
@@ -0,0 +1,857 @@
+//! Social recovery: guardians can together move our plog to a new signing key.
+//!
+//! A guardian is someone we follow who offered to guard us. They derive a key for guarding us
+//! alone from their own seed, at [guardian_key_path], and send its public key in a
+//! [RecoveryMessage::Offer] on our [topic], signed with the current signing key of their plog.
+//! Saving the guardians puts the VLAD and key of each below [GUARDIANS_PREFIX] in our plog,
+//! and how many of them have to agree under [THRESHOLD_PATH].
+//!
+//! Someone who lost their password creates a new wallet for their old VLAD, see
+//! [crate::wallet::create_for_recovery], and sends a [RecoveryMessage::Request] with the new
+//! wallet's signing key and its commitment to the key after it. Each guardian who made sure,
+//! out of band, that the request is genuine signs both with their key for that VLAD. Once
+//! enough approvals are in, [crate::plog::recover] adds the entry installing the new `/pubkey`
+//! and commitment, with the approvals below [APPROVALS_PREFIX].
+//!
+//! The lock scripts on `/pubkey` and the commitment, see [crate::prerotation::lock_scripts],
+//! accept such an entry in place of a rotation when approvals from at least the threshold of
+//! guardians verify against their keys, see [approval_lock] and [recovery_unlock]. The same
+//! locks guard the guardians and the threshold, so only a rotation can change them.
+use crate::keys::{self, KeyCodec, KeyError, WalletKeys, key_paths};
+use crate::prerotation;
+use bs::update::OpParams;
+use data_encoding::BASE32_NOPAD;
+use multicid::Vlad;
+use multikey::{Multikey, Views as _};
+use multisig::Multisig;
+use provenance_log::{Key, Log, Op, Script, Value};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::collections::BTreeMap;
+
+/// Each guardian is stored below this, under its index: its VLAD at `<index>/vlad`, and the
+/// key it guards us with at `<index>/key`.
+pub const GUARDIANS_PREFIX: &str = "/vaiber/guardians/";
+/// How many guardians have to approve a recovery.
+pub const THRESHOLD_PATH: &str = "/vaiber/guardian-threshold";
+/// A recovery entry keeps each approval below this, under the index of the guardian.
+pub const APPROVALS_PREFIX: &str = "/vaiber/recovery-approvals/";
+/// PubSub topics for recovery messages are this followed by the VLAD being recovered.
+pub const TOPIC_PREFIX: &str = "recovery/";
+
+/// The keys we guard others with are derived below this, one per VLAD we guard.
+const GUARDIAN_KEYS_PREFIX: &str = "/vaiber/guardian/";
+
+/// Signed along with the VLAD guarded and the key, so an offer cannot be taken for anything else.
+const OFFER_CONTEXT: &[u8] = b"vaiber/recovery/offer/v1";
+
+/// Errors setting up guardians or recovering a plog.
+#[derive(Debug, thiserror::Error)]
+pub enum RecoveryError {
+    /// The threshold has to be between 1 and the number of guardians
+    #[error("The threshold must be between 1 and {guardians}, not {threshold}")]
+    InvalidThreshold { threshold: usize, guardians: usize },
+
+    /// The same VLAD is named as a guardian twice
+    #[error("{0} is already a guardian")]
+    DuplicateGuardian(Vlad),
+
+    /// The plog does not name any guardians
+    #[error("This plog has no guardians, so it cannot be recovered")]
+    NoGuardians,
+
+    /// Fewer guardians than the threshold approved
+    #[error("{approved} of the {threshold} guardian approvals needed")]
+    NotEnoughApprovals { approved: usize, threshold: usize },
+
+    /// The offer was not signed by the guardian's current signing key
+    #[error("Invalid offer from {0}")]
+    InvalidOffer(Vlad),
+
+    /// The approval is not from a guardian, or does not verify against their key
+    #[error("Invalid approval from {0}")]
+    InvalidApproval(String),
+
+    /// A recovery message could not be read
+    #[error("Invalid recovery message: {0}")]
+    InvalidMessage(String),
+
+    /// A recovery message could not be sent
+    #[error("Failed to send recovery message: {0}")]
+    Network(String),
+
+    /// A key could not be derived, or a message signed
+    #[error(transparent)]
+    Keys(#[from] KeyError),
+}
+
+/// Someone who guards a plog.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Guardian {
+    pub vlad: Vlad,
+    /// The key they derived to approve recoveries of this plog with
+    pub key: Multikey,
+}
+
+/// The guardians a plog names, in the order of their index, and how many of them have to
+/// approve a recovery.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Guardians {
+    pub guardians: Vec<Guardian>,
+    pub threshold: usize,
+}
+
+impl Guardians {
+    /// The guardians currently named in `plog`, none if it has not set any.
+    ///
+    /// Fails with [KeyError::Unverified] if an entry does not verify, since leaving it out
+    /// could make former guardians look current.
+    pub fn from_plog(plog: &Log) -> Result<Self, KeyError> {
+        Ok(GuardianState::from_plog(plog)?.guardians())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guardians.is_empty()
+    }
+
+    /// The index of the guardian `vlad`, if it is one.
+    pub fn position(&self, vlad: &Vlad) -> Option<usize> {
+        self.guardians
+            .iter()
+            .position(|guardian| &guardian.vlad == vlad)
+    }
+
+    /// The ops that turn `current` into these guardians.
+    pub fn changes(&self, current: &Guardians) -> Result<Vec<OpParams>, RecoveryError> {
+        if !self.is_empty() && !(1..=self.guardians.len()).contains(&self.threshold) {
+            return Err(RecoveryError::InvalidThreshold {
+                threshold: self.threshold,
+                guardians: self.guardians.len(),
+            });
+        }
+        for (index, guardian) in self.guardians.iter().enumerate() {
+            if self.position(&guardian.vlad) != Some(index) {
+                return Err(RecoveryError::DuplicateGuardian(guardian.vlad.clone()));
+            }
+        }
+
+        let mut ops = Vec::new();
+        for (index, guardian) in self.guardians.iter().enumerate() {
+            let previous = current.guardians.get(index);
+            if previous.map(|previous| &previous.vlad) != Some(&guardian.vlad) {
+                ops.push(OpParams::UseStr {
+                    key: guardian_path(index, "vlad"),
+                    s: guardian.vlad.to_string(),
+                });
+            }
+            if previous.map(|previous| &previous.key) != Some(&guardian.key) {
+                ops.push(OpParams::UseKey {
+                    key: guardian_path(index, "key"),
+                    mk: guardian.key.clone(),
+                });
+            }
+        }
+        for index in self.guardians.len()..current.guardians.len() {
+            ops.push(OpParams::Delete {
+                key: guardian_path(index, "vlad"),
+            });
+            ops.push(OpParams::Delete {
+                key: guardian_path(index, "key"),
+            });
+        }
+        if self.is_empty() {
+            if current.threshold != 0 {
+                ops.push(OpParams::Delete {
+                    key: THRESHOLD_PATH.into(),
+                });
+            }
+        } else if self.threshold != current.threshold {
+            ops.push(OpParams::UseStr {
+                key: THRESHOLD_PATH.into(),
+                s: self.threshold.to_string(),
+            });
+        }
+        Ok(ops)
+    }
+}
+
+/// The guardians of a plog as its entries are applied one op at a time, keyed by the index
+/// they are stored under.
+#[derive(Default)]
+pub(crate) struct GuardianState {
+    vlads: BTreeMap<usize, Vlad>,
+    keys: BTreeMap<usize, Multikey>,
+    threshold: usize,
+}
+
+impl GuardianState {
+    /// The state after every entry of `plog`, see [Guardians::from_plog].
+    pub(crate) fn from_plog(plog: &Log) -> Result<Self, KeyError> {
+        let mut state = GuardianState::default();
+        for verified in plog.verify() {
+            let (_count, entry, _kvp) =
+                verified.map_err(|e| KeyError::Unverified(e.to_string()))?;
+            for op in entry.ops() {
+                state.apply(op);
+            }
+        }
+        Ok(state)
+    }
+
+    pub(crate) fn apply(&mut self, op: &Op) {
+        match op {
+            Op::Update(key, value) => self.set(key, Some(value)),
+            Op::Delete(key) => self.set(key, None),
+            _ => {}
+        }
+    }
+
+    /// Applies an op of an entry that is yet to be created.
+    pub(crate) fn apply_params(&mut self, op: &OpParams) {
+        match op {
+            OpParams::UseStr { key, s } => self.set(key, Some(&Value::Str(s.clone()))),
+            OpParams::UseBin { key, data } => self.set(key, Some(&Value::Data(data.clone()))),
+            OpParams::UseKey { key, mk } => self.set(key, Some(&Value::Data(mk.clone().into()))),
+            OpParams::Delete { key } => self.set(key, None),
+            _ => {}
+        }
+    }
+
+    /// Sets `key` to `value`, or deletes it with None, if it belongs to the guardians.
+    fn set(&mut self, key: &Key, value: Option<&Value>) {
+        let key = key.to_string();
+        if key == THRESHOLD_PATH {
+            self.threshold = match value {
+                Some(Value::Str(s)) => s.parse().unwrap_or(0),
+                _ => 0,
+            };
+            return;
+        }
+        let Some((index, field)) = key
+            .strip_prefix(GUARDIANS_PREFIX)
+            .and_then(|rest| rest.split_once('/'))
+        else {
+            return;
+        };
+        let Ok(index) = index.parse::<usize>() else {
+            return;
+        };
+        match (field, value) {
+            ("vlad", Some(Value::Str(s))) => match Vlad::try_from_str(s) {
+                Ok(vlad) => {
+                    self.vlads.insert(index, vlad);
+                }
+                Err(_) => {
+                    self.vlads.remove(&index);
+                }
+            },
+            ("vlad", _) => {
+                self.vlads.remove(&index);
+            }
+            ("key", Some(Value::Data(data))) => match Multikey::try_from(data.as_slice()) {
+                Ok(key) => {
+                    self.keys.insert(index, key);
+                }
+                Err(_) => {
+                    self.keys.remove(&index);
+                }
+            },
+            ("key", _) => {
+                self.keys.remove(&index);
+            }
+            _ => {}
+        }
+    }
+
+    /// The guardians from index 0 up to the first one missing its VLAD or key. The
+    /// [Guardians::changes] never leave a gap, and the lock scripts only count those.
+    pub(crate) fn guardians(&self) -> Guardians {
+        let guardians = (0..)
+            .map_while(|index| {
+                Some(Guardian {
+                    vlad: self.vlads.get(&index)?.clone(),
+                    key: self.keys.get(&index)?.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let threshold = if guardians.is_empty() {
+            0
+        } else {
+            self.threshold
+        };
+        Guardians {
+            guardians,
+            threshold,
+        }
+    }
+}
+
+fn guardian_path(index: usize, field: &str) -> Key {
+    Key::from(format!("{GUARDIANS_PREFIX}{index}/{field}").as_str())
+}
+
+fn approval_path(index: usize, field: &str) -> Key {
+    Key::from(format!("{APPROVALS_PREFIX}{index}/{field}").as_str())
+}
+
+/// The path of the key we guard `guardee` with: the base32 SHA-256 digest of their VLAD below
+/// [GUARDIAN_KEYS_PREFIX], since a VLAD is longer than a key segment may be.
+///
+/// Each plog we guard gets a key of its own, so an approval for one can never be replayed on
+/// another.
+pub fn guardian_key_path(guardee: &Vlad) -> Key {
+    let vlad_bytes: Vec<u8> = guardee.clone().into();
+    let digest = Sha256::digest(&vlad_bytes);
+    Key::from(format!("{GUARDIAN_KEYS_PREFIX}{}", BASE32_NOPAD.encode(&digest)).as_str())
+}
+
+/// The PubSub topic recovery messages for `vlad` are sent on.
+pub fn topic(vlad: &Vlad) -> String {
+    format!("{TOPIC_PREFIX}{vlad}")
+}
+
+/// A guardian's signatures over the new `/pubkey` and commitment of a recovery, made with the
+/// key they guard the plog with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Approval {
+    pub guardian: String,
+    pub new_pubkey: Vec<u8>,
+    pub pubkey_signature: Vec<u8>,
+    pub commitment_signature: Vec<u8>,
+}
+
+/// A message on a recovery [topic].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecoveryMessage {
+    /// `guardian` offers to guard the VLAD of the topic with `key`
+    Offer {
+        guardian: String,
+        key: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    /// The owner of the VLAD asks to recover it with a new signing key, committing to the key
+    /// after it
+    Request {
+        new_pubkey: Vec<u8>,
+        commitment: Vec<u8>,
+    },
+    /// A guardian approves a request
+    Approval(Approval),
+}
+
+impl RecoveryMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).expect("Serializing to a Vec does not fail");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RecoveryError> {
+        ciborium::from_reader(bytes).map_err(|e| RecoveryError::InvalidMessage(e.to_string()))
+    }
+}
+
+/// What a guardian signs to offer guarding `guardee` with `key`.
+fn offer_payload(guardee: &Vlad, key: &[u8]) -> Vec<u8> {
+    let vlad_bytes: Vec<u8> = guardee.clone().into();
+    [OFFER_CONTEXT, vlad_bytes.as_slice(), key].concat()
+}
+
+/// Whether `signature` by `key` verifies over `message`.
+fn verifies(key: &Multikey, message: &[u8], signature: &[u8]) -> bool {
+    let Ok(signature) = Multisig::try_from(signature) else {
+        return false;
+    };
+    key.verify_view()
+        .and_then(|view| view.verify(&signature, Some(message)))
+        .is_ok()
+}
+
+/// Offers to guard `guardee`, as the `guardian` whose wallet `keys` is.
+pub fn offer(
+    keys: &WalletKeys,
+    guardian: &Vlad,
+    guardee: &Vlad,
+) -> Result<RecoveryMessage, RecoveryError> {
+    let key: Vec<u8> = keys
+        .public_key(&guardian_key_path(guardee), 0, KeyCodec::default())?
+        .into();
+    let signature = keys.sign(
+        &key_paths::SIGNING.into(),
+        keys.index(),
+        keys.signing_codec(),
+        &offer_payload(guardee, &key),
+    )?;
+    Ok(RecoveryMessage::Offer {
+        guardian: guardian.to_string(),
+        key,
+        signature,
+    })
+}
+
+/// The guardian an offer to guard `guardee` with `key` makes, if `signature` verifies against
+/// the current signing key of `guardian_plog`.
+pub fn verify_offer(
+    guardian_plog: &Log,
+    guardee: &Vlad,
+    key: &[u8],
+    signature: &[u8],
+) -> Result<Guardian, RecoveryError> {
+    let invalid = || RecoveryError::InvalidOffer(guardian_plog.vlad.clone());
+    let signing_key = keys::pubkey_history(guardian_plog)?
+        .pop()
+        .ok_or_else(invalid)?;
+    if !verifies(&signing_key, &offer_payload(guardee, key), signature) {
+        return Err(invalid());
+    }
+    Ok(Guardian {
+        vlad: guardian_plog.vlad.clone(),
+        key: Multikey::try_from(key).map_err(|_| invalid())?,
+    })
+}
+
+/// The current signing key of the new wallet `keys`, and its commitment to the next one.
+fn new_keys(keys: &WalletKeys) -> Result<(Multikey, Vec<u8>), KeyError> {
+    let new_pubkey = keys.signing_public_key()?;
+    let next = keys.public_key(
+        &key_paths::SIGNING.into(),
+        keys.index() + 1,
+        keys.signing_codec(),
+    )?;
+    Ok((new_pubkey, prerotation::commitment(&next)?))
+}
+
+/// Asks the guardians to recover a plog with the new wallet `keys`.
+pub fn request(keys: &WalletKeys) -> Result<RecoveryMessage, RecoveryError> {
+    let (new_pubkey, commitment) = new_keys(keys)?;
+    Ok(RecoveryMessage::Request {
+        new_pubkey: new_pubkey.into(),
+        commitment,
+    })
+}
+
+/// Approves recovering `guardee` with `new_pubkey` and `commitment`, as the `guardian` whose
+/// wallet `keys` is.
+pub fn approve(
+    keys: &WalletKeys,
+    guardian: &Vlad,
+    guardee: &Vlad,
+    new_pubkey: &[u8],
+    commitment: &[u8],
+) -> Result<RecoveryMessage, RecoveryError> {
+    let path = guardian_key_path(guardee);
+    let sign = |message: &[u8]| keys.sign(&path, 0, KeyCodec::default(), message);
+    Ok(RecoveryMessage::Approval(Approval {
+        guardian: guardian.to_string(),
+        new_pubkey: new_pubkey.to_vec(),
+        pubkey_signature: sign(new_pubkey)?,
+        commitment_signature: sign(commitment)?,
+    }))
+}
+
+/// The index of the guardian whose `approval` of `new_pubkey` and `commitment` verifies.
+pub fn verify_approval(
+    guardians: &Guardians,
+    approval: &Approval,
+    new_pubkey: &[u8],
+    commitment: &[u8],
+) -> Result<usize, RecoveryError> {
+    let invalid = || RecoveryError::InvalidApproval(approval.guardian.clone());
+    let vlad = Vlad::try_from_str(&approval.guardian).map_err(|_| invalid())?;
+    let index = guardians.position(&vlad).ok_or_else(invalid)?;
+    let key = &guardians.guardians[index].key;
+    if approval.new_pubkey == new_pubkey
+        && verifies(key, new_pubkey, &approval.pubkey_signature)
+        && verifies(key, commitment, &approval.commitment_signature)
+    {
+        Ok(index)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// The ops and unlock script of the entry that recovers a plog with `guardians` to the new
+/// wallet `keys`.
+///
+/// Approvals that don't verify are left out, and each guardian counts once. Fails with
+/// [RecoveryError::NotEnoughApprovals] unless the rest meet the threshold.
+pub(crate) fn recovery_entry(
+    guardians: &Guardians,
+    keys: &WalletKeys,
+    approvals: &[Approval],
+) -> Result<(Vec<OpParams>, Script), RecoveryError> {
+    if guardians.is_empty() {
+        return Err(RecoveryError::NoGuardians);
+    }
+    let (new_pubkey, commitment) = new_keys(keys)?;
+    let new_pubkey_bytes: Vec<u8> = new_pubkey.clone().into();
+    let by_index = approvals
+        .iter()
+        .filter_map(|approval| {
+            let index =
+                verify_approval(guardians, approval, &new_pubkey_bytes, &commitment).ok()?;
+            Some((index, approval))
+        })
+        .collect::<BTreeMap<_, _>>();
+    if by_index.len() < guardians.threshold {
+        return Err(RecoveryError::NotEnoughApprovals {
+            approved: by_index.len(),
+            threshold: guardians.threshold,
+        });
+    }
+
+    let mut ops = vec![
+        OpParams::UseKey {
+            key: key_paths::SIGNING.into(),
+            mk: new_pubkey,
+        },
+        OpParams::UseBin {
+            key: prerotation::COMMITMENT_PATH.into(),
+            data: commitment,
+        },
+    ];
+    for (index, approval) in &by_index {
+        ops.push(OpParams::UseBin {
+            key: approval_path(*index, "pubkey"),
+            data: approval.pubkey_signature.clone(),
+        });
+        ops.push(OpParams::UseBin {
+            key: approval_path(*index, "commitment"),
+            data: approval.commitment_signature.clone(),
+        });
+    }
+    let approved = by_index.keys().copied().collect::<Vec<_>>();
+    Ok((ops, recovery_unlock(&approved)))
+}
+
+/// The part of the lock scripts that accepts a recovery, None without guardians: statements
+/// counting the guardians whose approvals of the new `/pubkey` and commitment are on the
+/// stack, in the order of their index, and the condition that there are enough of them.
+///
+/// An approval is checked against the guardian's key in force before the entry, and the
+/// values it signs as the entry leaves them. Signatures are only popped once they verify, so
+/// guardians who did not approve are skipped.
+pub(crate) fn approval_lock(guardians: &Guardians) -> Option<(String, String)> {
+    if guardians.is_empty() {
+        return None;
+    }
+    let mut count = String::from("let approved = 0;\n");
+    for index in 0..guardians.guardians.len() {
+        let key = guardian_path(index, "key");
+        count.push_str(&format!(
+            r#"if check_signature("{key}", "{}") && check_signature("{key}", "{}") {{ approved += 1; }}"#,
+            key_paths::SIGNING,
+            prerotation::COMMITMENT_PATH,
+        ));
+        count.push('\n');
+    }
+    Some((count, format!("approved >= {}", guardians.threshold)))
+}
+
+/// The unlock script of a recovery entry approved by the guardians at `approved`, in
+/// ascending order: each approval of the commitment with the one of `/pubkey` on top, and
+/// the lowest index on top of all, as [approval_lock] checks them.
+pub(crate) fn recovery_unlock(approved: &[usize]) -> Script {
+    let unlock = approved
+        .iter()
+        .rev()
+        .map(|&index| {
+            format!(
+                r#"push("{}"); push("{}");"#,
+                approval_path(index, "commitment"),
+                approval_path(index, "pubkey")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    Script::Code(Key::default(), unlock)
+}
+
+/// How many guardians in `guardians` approved the entry with `ops`, revealing `new_pubkey`.
+///
+/// Only approvals below [APPROVALS_PREFIX] whose signatures verify over `new_pubkey` and the
+/// commitment the entry sets count.
+pub(crate) fn approved_by(guardians: &Guardians, new_pubkey: &[u8], ops: &[&Op]) -> usize {
+    let commitment_path = Key::from(prerotation::COMMITMENT_PATH);
+    let Some(commitment) = ops.iter().find_map(|op| match op {
+        Op::Update(key, Value::Data(data)) if key == &commitment_path => Some(data),
+        _ => None,
+    }) else {
+        return 0;
+    };
+    (0..guardians.guardians.len())
+        .filter(|&index| {
+            let signature = |field| {
+                let path = approval_path(index, field);
+                ops.iter().find_map(|op| match op {
+                    Op::Update(key, Value::Data(data)) if key == &path => Some(data),
+                    _ => None,
+                })
+            };
+            let key = &guardians.guardians[index].key;
+            signature("pubkey").is_some_and(|signature| verifies(key, new_pubkey, signature))
+                && signature("commitment")
+                    .is_some_and(|signature| verifies(key, commitment, signature))
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::vlad;
+
+    fn keys(fill: u8) -> WalletKeys {
+        WalletKeys::new(&[fill; 32], KeyCodec::Ed25519).unwrap()
+    }
+
+    /// Guardians with the wallets made from `fills`, guarding `guardee`.
+    fn guardians(fills: &[u8], guardee: &Vlad, threshold: usize) -> Guardians {
+        Guardians {
+            guardians: fills
+                .iter()
+                .map(|&fill| Guardian {
+                    vlad: vlad(fill),
+                    key: keys(fill)
+                        .public_key(&guardian_key_path(guardee), 0, KeyCodec::default())
+                        .unwrap(),
+                })
+                .collect(),
+            threshold,
+        }
+    }
+
+    fn paths(ops: &[OpParams]) -> Vec<String> {
+        ops.iter()
+            .map(|op| match op {
+                OpParams::UseStr { key, .. }
+                | OpParams::UseBin { key, .. }
+                | OpParams::UseKey { key, .. }
+                | OpParams::Delete { key } => key.to_string(),
+                _ => panic!("unexpected op"),
+            })
+            .collect()
+    }
+
+    /// Approvals of recovering `guardee` with `new_keys`, from the wallets made from `fills`.
+    fn approvals(fills: &[u8], guardee: &Vlad, new_keys: &WalletKeys) -> Vec<Approval> {
+        let RecoveryMessage::Request {
+            new_pubkey,
+            commitment,
+        } = request(new_keys).unwrap()
+        else {
+            panic!("not a request");
+        };
+        fills
+            .iter()
+            .map(|&fill| {
+                match approve(&keys(fill), &vlad(fill), guardee, &new_pubkey, &commitment) {
+                    Ok(RecoveryMessage::Approval(approval)) => approval,
+                    other => panic!("not an approval: {other:?}"),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn changes_replace_guardians_and_drop_the_rest() {
+        let guardee = vlad(9);
+        let current = guardians(&[1, 2, 3], &guardee, 2);
+        let ops = guardians(&[1, 4], &guardee, 1).changes(&current).unwrap();
+        assert_eq!(
+            paths(&ops),
+            vec![
+                format!("{GUARDIANS_PREFIX}1/vlad"),
+                format!("{GUARDIANS_PREFIX}1/key"),
+                format!("{GUARDIANS_PREFIX}2/vlad"),
+                format!("{GUARDIANS_PREFIX}2/key"),
+                THRESHOLD_PATH.to_string(),
+            ]
+        );
+        assert!(current.changes(&current).unwrap().is_empty());
+    }
+
+    #[test]
+    fn removing_every_guardian_removes_the_threshold() {
+        let guardee = vlad(9);
+        let ops = Guardians::default()
+            .changes(&guardians(&[1], &guardee, 1))
+            .unwrap();
+        assert_eq!(
+            paths(&ops),
+            vec![
+                format!("{GUARDIANS_PREFIX}0/vlad"),
+                format!("{GUARDIANS_PREFIX}0/key"),
+                THRESHOLD_PATH.to_string(),
+            ]
+        );
+        assert!(matches!(ops[2], OpParams::Delete { .. }));
+    }
+
+    #[test]
+    fn changes_refuse_bad_thresholds_and_duplicates() {
+        let guardee = vlad(9);
+        for threshold in [0, 3] {
+            assert!(matches!(
+                guardians(&[1, 2], &guardee, threshold).changes(&Guardians::default()),
+                Err(RecoveryError::InvalidThreshold { .. })
+            ));
+        }
+        assert!(matches!(
+            guardians(&[1, 1], &guardee, 1).changes(&Guardians::default()),
+            Err(RecoveryError::DuplicateGuardian(_))
+        ));
+    }
+
+    #[test]
+    fn the_state_follows_the_changes() {
+        let guardee = vlad(9);
+        let first = guardians(&[1, 2, 3], &guardee, 2);
+        let second = guardians(&[3], &guardee, 1);
+        let mut state = GuardianState::default();
+        for op in first.changes(&Guardians::default()).unwrap() {
+            state.apply_params(&op);
+        }
+        assert_eq!(state.guardians(), first);
+        for op in second.changes(&first).unwrap() {
+            state.apply_params(&op);
+        }
+        assert_eq!(state.guardians(), second);
+    }
+
+    #[test]
+    fn guardians_after_a_gap_are_ignored() {
+        let guardee = vlad(9);
+        let mut state = GuardianState::default();
+        for op in guardians(&[1, 2], &guardee, 2)
+            .changes(&Guardians::default())
+            .unwrap()
+        {
+            state.apply_params(&op);
+        }
+        state.apply(&Op::Delete(guardian_path(0, "key")));
+        assert_eq!(state.guardians(), Guardians::default());
+    }
+
+    #[test]
+    fn offers_verify_against_the_guardians_own_key() {
+        let guardee = vlad(9);
+        let guardian = keys(1);
+        let RecoveryMessage::Offer { key, signature, .. } =
+            offer(&guardian, &vlad(1), &guardee).unwrap()
+        else {
+            panic!("not an offer");
+        };
+        let expected = guardian
+            .public_key(&guardian_key_path(&guardee), 0, KeyCodec::default())
+            .unwrap();
+        assert_eq!(key, Vec::<u8>::from(expected));
+        let payload = offer_payload(&guardee, &key);
+        assert!(verifies(
+            &guardian.signing_public_key().unwrap(),
+            &payload,
+            &signature
+        ));
+        // Not an offer to guard anyone else
+        assert!(!verifies(
+            &guardian.signing_public_key().unwrap(),
+            &offer_payload(&vlad(8), &key),
+            &signature
+        ));
+    }
+
+    #[test]
+    fn recovery_entries_carry_the_verified_approvals() {
+        let guardee = vlad(9);
+        let new_keys = keys(5);
+        let guardians = guardians(&[1, 2, 3], &guardee, 2);
+        let mut approvals = approvals(&[3, 1], &guardee, &new_keys);
+        // Someone who is not a guardian, and a guardian approving twice
+        approvals.extend(self::approvals(&[4], &guardee, &new_keys));
+        approvals.push(approvals[0].clone());
+
+        let (ops, unlock) = recovery_entry(&guardians, &new_keys, &approvals).unwrap();
+        assert_eq!(
+            paths(&ops),
+            vec![
+                key_paths::SIGNING.to_string(),
+                prerotation::COMMITMENT_PATH.to_string(),
+                format!("{APPROVALS_PREFIX}0/pubkey"),
+                format!("{APPROVALS_PREFIX}0/commitment"),
+                format!("{APPROVALS_PREFIX}2/pubkey"),
+                format!("{APPROVALS_PREFIX}2/commitment"),
+            ]
+        );
+        let Script::Code(_, unlock) = unlock else {
+            panic!("not a code script");
+        };
+        assert_eq!(
+            unlock,
+            format!(
+                r#"push("{APPROVALS_PREFIX}2/commitment"); push("{APPROVALS_PREFIX}2/pubkey"); push("{APPROVALS_PREFIX}0/commitment"); push("{APPROVALS_PREFIX}0/pubkey");"#
+            )
+        );
+    }
+
+    #[test]
+    fn recovery_needs_the_threshold_of_approvals() {
+        let guardee = vlad(9);
+        let new_keys = keys(5);
+        let guardians = guardians(&[1, 2, 3], &guardee, 2);
+        let approvals = approvals(&[2], &guardee, &new_keys);
+        assert!(matches!(
+            recovery_entry(&guardians, &new_keys, &approvals),
+            Err(RecoveryError::NotEnoughApprovals {
+                approved: 1,
+                threshold: 2
+            })
+        ));
+        assert!(matches!(
+            recovery_entry(&Guardians::default(), &new_keys, &approvals),
+            Err(RecoveryError::NoGuardians)
+        ));
+    }
+
+    #[test]
+    fn approvals_only_count_for_the_request_and_plog_they_sign() {
+        let guardee = vlad(9);
+        let new_keys = keys(5);
+        let guardians = guardians(&[1, 2], &guardee, 1);
+        // Approvals of another plog, made with the key guarding that one
+        let elsewhere = approvals(&[1], &vlad(8), &new_keys);
+        assert!(matches!(
+            recovery_entry(&guardians, &new_keys, &elsewhere),
+            Err(RecoveryError::NotEnoughApprovals { approved: 0, .. })
+        ));
+        // Approvals of someone else's request
+        let theirs = approvals(&[1], &guardee, &keys(6));
+        assert!(matches!(
+            recovery_entry(&guardians, &new_keys, &theirs),
+            Err(RecoveryError::NotEnoughApprovals { approved: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn the_approval_lock_counts_each_guardian() {
+        let guardee = vlad(9);
+        assert!(approval_lock(&Guardians::default()).is_none());
+        let (count, condition) = approval_lock(&guardians(&[1, 2], &guardee, 2)).unwrap();
+        for index in 0..2 {
+            assert!(count.contains(&format!(
+                r#"check_signature("{GUARDIANS_PREFIX}{index}/key", "{}")"#,
+                key_paths::SIGNING
+            )));
+        }
+        assert_eq!(condition, "approved >= 2");
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let message = request(&keys(5)).unwrap();
+        assert_eq!(
+            RecoveryMessage::from_bytes(&message.to_bytes()).unwrap(),
+            message
+        );
+        assert!(RecoveryMessage::from_bytes(b"not cbor").is_err());
+    }
+}
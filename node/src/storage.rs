@@ -0,0 +1,287 @@
+//! This module defines the `WalletStorage` trait for managing wallet data.
+use std::sync::Arc;
+
+mod encrypted;
+pub use encrypted::EncryptedStorage;
+
+mod scoped;
+use scoped::ScopedStorage;
+
+#[cfg(test)]
+pub(crate) mod memory;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file;
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileStorage;
+
+/// Where the wallet's password encrypted seed is stored.
+pub const SEED_STORAGE_KEY: &str = "SEED_KEEPER_ENCRYPTED_SEED";
+
+/// Keys stored as-is by [StorageProvider::new_encrypted], since they are read before unlocking.
+const PASSTHROUGH_KEYS: &[&str] = &[SEED_STORAGE_KEY, crate::identity::REGISTRY_KEY];
+
+/// Namespaces where [StorageProvider::scoped] storages of other identities live, which the
+/// encryption of the storage around them leaves alone.
+const NESTED_NAMESPACES: &[&str] = &[crate::identity::NAMESPACE, crate::identity::DEV_NAMESPACE];
+
+/// Errors returned by a [WalletStorage] implementation.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum StorageError {
+    /// Nothing is stored under the key
+    #[error("No data stored under {0}")]
+    NotFound(String),
+
+    /// The stored data could not be read back
+    #[error("Data under {key} is corrupted: {reason}")]
+    Corrupted { key: String, reason: String },
+
+    /// The backend has run out of space
+    #[error("Storage quota exceeded")]
+    QuotaExceeded,
+
+    /// The data is encrypted and the wallet is locked
+    #[error("Storage is locked")]
+    Locked,
+
+    /// The key is not a valid storage key, see [validate_key]
+    #[error("Invalid storage key {key:?}: {reason}")]
+    InvalidKey { key: String, reason: &'static str },
+
+    /// A compare-and-swap found a different value than expected
+    #[error("Data under {0} was changed concurrently")]
+    Conflict(String),
+
+    /// Any other backend failure
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+/// Longest allowed key segment, which keeps file based backends within common name limits
+const MAX_SEGMENT_LEN: usize = 128;
+
+/// Checks that `key` is a valid storage key.
+///
+/// Keys are namespaced with `/`, e.g. `peers/<vlad>`, and file based backends map each
+/// namespace to a subdirectory. Every segment must be non-empty, made of ASCII letters,
+/// digits, `-`, `_` and `.`, and must not start with `.`. That rules out `..` and absolute
+/// paths, and leaves dot-files free for the backends' own bookkeeping.
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    let invalid = |reason| {
+        Err(StorageError::InvalidKey {
+            key: key.to_string(),
+            reason,
+        })
+    };
+
+    for segment in key.split('/') {
+        if segment.is_empty() {
+            return invalid("empty segment");
+        }
+        if segment.len() > MAX_SEGMENT_LEN {
+            return invalid("segment too long");
+        }
+        if segment.starts_with('.') {
+            return invalid("segment starts with '.'");
+        }
+        if !segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return invalid("segment contains characters other than A-Z, a-z, 0-9, '-', '_', '.'");
+        }
+    }
+    Ok(())
+}
+
+/// Async key/value storage for wallet data.
+///
+/// Keys passed in have been checked with [validate_key] by the [StorageProvider].
+/// There is deliberately no `exists`; call [WalletStorage::load] and handle
+/// [StorageError::NotFound] instead, so there is no window between checking and loading.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait WalletStorage: Send + Sync {
+    /// Stores `data` under `key`, replacing any previous value.
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Loads the value under `key`, or [StorageError::NotFound].
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Loads the value `key` held before the last write, for backends that keep one.
+    async fn load_backup(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        Err(StorageError::NotFound(key.to_string()))
+    }
+
+    /// Removes the value under `key`. Deleting a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Lists every stored key starting with `prefix`.
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError>;
+
+    /// Atomically replaces the value under `key` with `new`, if it is currently `expected`.
+    ///
+    /// `None` as `expected` means the key must not exist yet, and `None` as `new` deletes it.
+    /// Returns [StorageError::Conflict] if the current value differs.
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError>;
+}
+
+// A storage provider context that wraps any storage implementation
+#[derive(Clone)]
+pub struct StorageProvider {
+    inner: Arc<dyn WalletStorage>,
+    /// Set when the storage encrypts at rest, so the wallet can unlock and lock it
+    encryption: Option<EncryptedStorage>,
+    /// The storage below any encryption, for [StorageProvider::scoped]
+    plain: Arc<dyn WalletStorage>,
+}
+
+impl StorageProvider {
+    pub fn new<S: WalletStorage + 'static>(storage: S) -> Self {
+        let plain: Arc<dyn WalletStorage> = Arc::new(storage);
+        Self {
+            inner: plain.clone(),
+            encryption: None,
+            plain,
+        }
+    }
+
+    /// Wraps `storage` in an [EncryptedStorage], keyed from the wallet seed once unlocked.
+    ///
+    /// The encrypted seed itself is left as-is, since it is needed before unlocking.
+    pub fn new_encrypted<S: WalletStorage + 'static>(storage: S) -> Self {
+        let plain: Arc<dyn WalletStorage> = Arc::new(storage);
+        let encrypted =
+            EncryptedStorage::with_inner(plain.clone(), PASSTHROUGH_KEYS, NESTED_NAMESPACES);
+        Self {
+            inner: Arc::new(encrypted.clone()),
+            encryption: Some(encrypted),
+            plain,
+        }
+    }
+
+    /// The same backend with every key inside `namespace`, e.g. `identities/alice`.
+    ///
+    /// If this storage encrypts at rest, so does the scoped one, but it is locked and unlocked
+    /// on its own, with the seed of whoever's data it holds.
+    pub fn scoped(&self, namespace: &str) -> Result<Self, StorageError> {
+        validate_key(namespace)?;
+        let scoped = ScopedStorage::new(self.plain.clone(), namespace);
+        Ok(match self.encryption {
+            Some(_) => Self::new_encrypted(scoped),
+            None => Self::new(scoped),
+        })
+    }
+
+    /// Makes the wallet `seed` available for encryption at rest, if enabled.
+    pub fn unlock(&self, seed: &[u8]) {
+        if let Some(encryption) = &self.encryption {
+            encryption.unlock(seed);
+        }
+    }
+
+    /// Forgets the at-rest key, if encryption is enabled.
+    pub fn lock(&self) {
+        if let Some(encryption) = &self.encryption {
+            encryption.lock();
+        }
+    }
+
+    pub async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        validate_key(key)?;
+        self.inner.save(key, data).await
+    }
+
+    pub async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        validate_key(key)?;
+        self.inner.load(key).await
+    }
+
+    /// Like [StorageProvider::load], but a missing key is `Ok(None)` instead of an error.
+    pub async fn try_load(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match self.load(key).await {
+            Ok(data) => Ok(Some(data)),
+            Err(StorageError::NotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Loads the previous value of `key`, to fall back on when the current one is unreadable.
+    pub async fn load_backup(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        validate_key(key)?;
+        self.inner.load_backup(key).await
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        self.inner.delete(key).await
+    }
+
+    /// Deletes every key starting with `prefix`, or every key at all for an empty `prefix`.
+    pub async fn delete_all(&self, prefix: &str) -> Result<(), StorageError> {
+        for key in self.list_keys(prefix).await? {
+            self.delete(&key).await?;
+        }
+        Ok(())
+    }
+
+    /// Lists keys starting with `prefix`, e.g. `peers/` for every key in that namespace.
+    pub async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        self.inner.list_keys(prefix).await
+    }
+
+    pub async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        validate_key(key)?;
+        self.inner.compare_and_swap(key, expected, new).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_namespaced_keys() {
+        for key in [
+            "VLAD_STORAGE_KEY",
+            "peers/abc-DEF_1.2",
+            "identities/work/settings/auto-lock",
+        ] {
+            assert_eq!(validate_key(key), Ok(()), "{key}");
+        }
+        assert_eq!(validate_key(&"a".repeat(MAX_SEGMENT_LEN)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_unsafe_keys() {
+        let too_long = format!("peers/{}", "a".repeat(MAX_SEGMENT_LEN + 1));
+        for key in [
+            "",
+            "/absolute",
+            "trailing/",
+            "a//b",
+            "../escape",
+            "peers/..",
+            ".hidden",
+            "with space",
+            "back\\slash",
+            "ünicode",
+            too_long.as_str(),
+        ] {
+            assert!(
+                matches!(validate_key(key), Err(StorageError::InvalidKey { .. })),
+                "{key}"
+            );
+        }
+    }
+}
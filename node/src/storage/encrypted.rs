@@ -0,0 +1,450 @@
+//! Encryption at rest for anything written through [WalletStorage].
+//!
+//! Values are sealed with ChaCha20-Poly1305 under a key derived (HKDF-SHA256) from the
+//! unlocked [seed_keeper_core::credentials::Wallet] seed. The storage key is used as
+//! associated data, so a sealed value can't be moved to another key unnoticed.
+//!
+//! Values stored in plaintext before encryption at rest existed are sealed by a one-off pass
+//! the first time the storage is used while unlocked, which then writes [MIGRATED_KEY]. From
+//! then on a value that isn't sealed is [StorageError::Corrupted], so plaintext swapped in on
+//! disk is never taken for ours. The pass leaves alone the namespaces other storages keep in
+//! the same backend, such as other identities, which are sealed with their own seed.
+use super::{StorageError, WalletStorage};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use std::sync::{Arc, RwLock};
+use zeroize::Zeroizing;
+
+/// Marks a sealed value, followed by the format version.
+const MAGIC: &[u8; 4] = b"VBAR";
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + NONCE_LEN;
+
+/// HKDF info, so the at-rest key never collides with other keys derived from the seed.
+const KDF_INFO: &[u8] = b"vaiber/storage/at-rest/v1";
+
+/// Written, sealed, once every plaintext value has been sealed.
+const MIGRATED_KEY: &str = "VAIBER_AT_REST_MIGRATED";
+const MIGRATED_VALUE: &[u8] = b"v1";
+
+/// A [WalletStorage] wrapper that encrypts values before they reach the inner storage.
+///
+/// Until [EncryptedStorage::unlock] is called (and after [EncryptedStorage::lock]) every
+/// read and write is refused, except for the keys passed as `passthrough`, which are stored
+/// as-is. Plaintext entries left over from before encryption are sealed by a migration pass
+/// before the first read or write while unlocked.
+#[derive(Clone)]
+pub struct EncryptedStorage {
+    inner: Arc<dyn WalletStorage>,
+    cipher: Arc<RwLock<Option<ChaCha20Poly1305>>>,
+    passthrough: &'static [&'static str],
+    /// Namespaces of other storages in the same backend, which the migration pass skips
+    nested: &'static [&'static str],
+    /// Whether [MIGRATED_KEY] was found or written since the last unlock
+    migrated: Arc<RwLock<bool>>,
+}
+
+impl EncryptedStorage {
+    /// Wraps `storage`, leaving the `passthrough` keys unencrypted.
+    pub fn new<S: WalletStorage + 'static>(
+        storage: S,
+        passthrough: &'static [&'static str],
+    ) -> Self {
+        Self::with_inner(Arc::new(storage), passthrough, &[])
+    }
+
+    /// Like [EncryptedStorage::new], for storage that is shared already, and holds other
+    /// storages below the `nested` namespaces.
+    pub(crate) fn with_inner(
+        inner: Arc<dyn WalletStorage>,
+        passthrough: &'static [&'static str],
+        nested: &'static [&'static str],
+    ) -> Self {
+        Self {
+            inner,
+            cipher: Arc::new(RwLock::new(None)),
+            passthrough,
+            nested,
+            migrated: Arc::new(RwLock::new(false)),
+        }
+    }
+
+    /// Derives the at-rest key from the wallet `seed` and starts encrypting.
+    pub fn unlock(&self, seed: &[u8]) {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, seed)
+            .expand(KDF_INFO, key.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+        *self.cipher.write().unwrap() = Some(cipher);
+        *self.migrated.write().unwrap() = false;
+    }
+
+    /// Forgets the at-rest key. Reads and writes fail until the next [EncryptedStorage::unlock].
+    pub fn lock(&self) {
+        *self.cipher.write().unwrap() = None;
+    }
+
+    /// Whether the at-rest key is currently available.
+    pub fn is_unlocked(&self) -> bool {
+        self.cipher.read().unwrap().is_some()
+    }
+
+    fn is_passthrough(&self, key: &str) -> bool {
+        self.passthrough.contains(&key)
+    }
+
+    /// Whether `key` belongs to a storage nested below one of our namespaces.
+    fn is_nested(&self, key: &str) -> bool {
+        self.nested.iter().any(|namespace| {
+            key.strip_prefix(namespace)
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Runs the migration pass, unless [MIGRATED_KEY] says it already ran.
+    async fn ensure_migrated(&self) -> Result<(), StorageError> {
+        if *self.migrated.read().unwrap() {
+            return Ok(());
+        }
+        match self.inner.load(MIGRATED_KEY).await {
+            Ok(marker) if self.open_stored(MIGRATED_KEY, &marker).is_ok() => {}
+            // A marker we didn't seal is no marker, and sealing again only touches plaintext
+            Ok(_) | Err(StorageError::NotFound(_)) => self.migrate().await?,
+            Err(err) => return Err(err),
+        }
+        *self.migrated.write().unwrap() = true;
+        Ok(())
+    }
+
+    /// Seals every value still stored in plaintext, then writes [MIGRATED_KEY].
+    async fn migrate(&self) -> Result<(), StorageError> {
+        for key in self.inner.list_keys("").await? {
+            if self.is_passthrough(&key) || self.is_nested(&key) || key == MIGRATED_KEY {
+                continue;
+            }
+            let stored = match self.inner.load(&key).await {
+                Ok(stored) => stored,
+                Err(StorageError::NotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            if is_sealed(&stored) {
+                continue;
+            }
+            // If someone else replaced it in the meantime, their value is sealed already
+            let sealed = self.seal(&key, &stored)?;
+            match self
+                .inner
+                .compare_and_swap(&key, Some(&stored), Some(&sealed))
+                .await
+            {
+                Ok(()) | Err(StorageError::Conflict(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        tracing::info!("Stored data is now encrypted at rest");
+        let marker = self.seal(MIGRATED_KEY, MIGRATED_VALUE)?;
+        self.inner.save(MIGRATED_KEY, &marker).await
+    }
+
+    fn seal(&self, key: &str, data: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let guard = self.cipher.read().unwrap();
+        let cipher = guard.as_ref().ok_or(StorageError::Locked)?;
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: data,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|err| StorageError::Backend(format!("Failed to encrypt {key}: {err}")))?;
+
+        let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.push(VERSION);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn open(&self, key: &str, sealed: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let guard = self.cipher.read().unwrap();
+        let cipher = guard.as_ref().ok_or(StorageError::Locked)?;
+
+        if sealed.len() < HEADER_LEN {
+            return Err(StorageError::Corrupted {
+                key: key.to_string(),
+                reason: "too short to be encrypted".to_string(),
+            });
+        }
+        if sealed[MAGIC.len()] != VERSION {
+            return Err(StorageError::Corrupted {
+                key: key.to_string(),
+                reason: format!("unsupported encryption version {}", sealed[MAGIC.len()]),
+            });
+        }
+
+        let nonce = Nonce::from_slice(&sealed[MAGIC.len() + 1..HEADER_LEN]);
+        cipher
+            .decrypt(
+                nonce,
+                Payload {
+                    msg: &sealed[HEADER_LEN..],
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|err| StorageError::Corrupted {
+                key: key.to_string(),
+                reason: format!("failed to decrypt: {err}"),
+            })
+    }
+
+    /// Decrypts a stored value, which has to be sealed once the migration pass ran.
+    fn open_stored(&self, key: &str, stored: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if !is_sealed(stored) {
+            return Err(StorageError::Corrupted {
+                key: key.to_string(),
+                reason: "stored unencrypted".to_string(),
+            });
+        }
+        self.open(key, stored)
+    }
+}
+
+fn is_sealed(data: &[u8]) -> bool {
+    data.len() >= HEADER_LEN && data.starts_with(MAGIC)
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl WalletStorage for EncryptedStorage {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        if self.is_passthrough(key) {
+            return self.inner.save(key, data).await;
+        }
+        if !self.is_unlocked() {
+            return Err(StorageError::Locked);
+        }
+        self.ensure_migrated().await?;
+        let sealed = self.seal(key, data)?;
+        self.inner.save(key, &sealed).await
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        if self.is_passthrough(key) {
+            return self.inner.load(key).await;
+        }
+        if !self.is_unlocked() {
+            return Err(StorageError::Locked);
+        }
+        self.ensure_migrated().await?;
+
+        let stored = self.inner.load(key).await?;
+        self.open_stored(key, &stored)
+    }
+
+    async fn load_backup(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        if self.is_passthrough(key) {
+            return self.inner.load_backup(key).await;
+        }
+        if !self.is_unlocked() {
+            return Err(StorageError::Locked);
+        }
+        self.ensure_migrated().await?;
+        let stored = self.inner.load_backup(key).await?;
+        self.open_stored(key, &stored)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.inner.delete(key).await
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        self.inner.list_keys(prefix).await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        if self.is_passthrough(key) {
+            return self.inner.compare_and_swap(key, expected, new).await;
+        }
+        if !self.is_unlocked() {
+            return Err(StorageError::Locked);
+        }
+        self.ensure_migrated().await?;
+
+        // Sealing is randomised, so compare plaintexts and swap against the exact stored bytes
+        let stored = match self.inner.load(key).await {
+            Ok(stored) => Some(stored),
+            Err(StorageError::NotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
+        let current = stored
+            .as_deref()
+            .map(|stored| self.open_stored(key, stored))
+            .transpose()?;
+        if current.as_deref() != expected {
+            return Err(StorageError::Conflict(key.to_string()));
+        }
+
+        let sealed = new.map(|data| self.seal(key, data)).transpose()?;
+        self.inner
+            .compare_and_swap(key, stored.as_deref(), sealed.as_deref())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    const SEED: [u8; 32] = [7; 32];
+
+    fn unlocked(seed: &[u8]) -> EncryptedStorage {
+        let storage = EncryptedStorage::new(MemoryStorage::default(), &[]);
+        storage.unlock(seed);
+        storage
+    }
+
+    #[test]
+    fn open_returns_what_was_sealed() {
+        let storage = unlocked(&SEED);
+        let sealed = storage.seal("peers/abc", b"followed").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_ne!(&sealed[HEADER_LEN..], b"followed");
+        assert_eq!(storage.open("peers/abc", &sealed).unwrap(), b"followed");
+    }
+
+    #[test]
+    fn sealing_is_randomised() {
+        let storage = unlocked(&SEED);
+        let first = storage.seal("key", b"same").unwrap();
+        let second = storage.seal("key", b"same").unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn open_rejects_another_key_or_seed() {
+        let sealed = unlocked(&SEED).seal("peers/abc", b"followed").unwrap();
+        assert!(matches!(
+            unlocked(&SEED).open("peers/other", &sealed),
+            Err(StorageError::Corrupted { .. })
+        ));
+        assert!(matches!(
+            unlocked(&[8; 32]).open("peers/abc", &sealed),
+            Err(StorageError::Corrupted { .. })
+        ));
+    }
+
+    #[test]
+    fn open_rejects_tampering_and_unknown_versions() {
+        let storage = unlocked(&SEED);
+        let sealed = storage.seal("key", b"value").unwrap();
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            storage.open("key", &tampered),
+            Err(StorageError::Corrupted { .. })
+        ));
+
+        let mut future = sealed;
+        future[MAGIC.len()] = VERSION + 1;
+        assert!(matches!(
+            storage.open("key", &future),
+            Err(StorageError::Corrupted { .. })
+        ));
+    }
+
+    #[test]
+    fn locked_storage_refuses_to_seal_or_open() {
+        let storage = unlocked(&SEED);
+        let sealed = storage.seal("key", b"value").unwrap();
+        storage.lock();
+        assert_eq!(storage.seal("key", b"value"), Err(StorageError::Locked));
+        assert_eq!(storage.open("key", &sealed), Err(StorageError::Locked));
+    }
+
+    #[tokio::test]
+    async fn plaintext_from_before_encryption_is_sealed_on_first_use() {
+        let plain = MemoryStorage::default();
+        plain.save("key", b"old value").await.unwrap();
+        plain.save("peers/abc", b"followed").await.unwrap();
+        plain.save("SEED", b"passthrough").await.unwrap();
+        let storage = EncryptedStorage::new(plain.clone(), &["SEED"]);
+        storage.unlock(&SEED);
+
+        assert_eq!(storage.load("key").await.unwrap(), b"old value");
+        assert!(is_sealed(&plain.load("key").await.unwrap()));
+        assert!(is_sealed(&plain.load("peers/abc").await.unwrap()));
+        assert_eq!(plain.load("SEED").await.unwrap(), b"passthrough");
+        assert!(is_sealed(&plain.load(MIGRATED_KEY).await.unwrap()));
+        assert_eq!(storage.load("peers/abc").await.unwrap(), b"followed");
+    }
+
+    #[tokio::test]
+    async fn plaintext_after_the_migration_is_corrupted() {
+        let plain = MemoryStorage::default();
+        let storage = EncryptedStorage::new(plain.clone(), &[]);
+        storage.unlock(&SEED);
+        storage.save("key", b"ours").await.unwrap();
+
+        // Swapped in on disk, and still refused after unlocking again
+        plain.save("key", b"theirs").await.unwrap();
+        storage.lock();
+        storage.unlock(&SEED);
+        assert!(matches!(
+            storage.load("key").await,
+            Err(StorageError::Corrupted { .. })
+        ));
+        assert_eq!(
+            storage
+                .compare_and_swap("key", Some(b"theirs".as_slice()), Some(b"new".as_slice()))
+                .await,
+            Err(StorageError::Corrupted {
+                key: "key".to_string(),
+                reason: "stored unencrypted".to_string(),
+            })
+        );
+        assert_eq!(plain.load("key").await.unwrap(), b"theirs");
+    }
+
+    #[tokio::test]
+    async fn an_unreadable_marker_runs_the_migration_again() {
+        for marker in [b"".as_slice(), b"VBAR", b"v1"] {
+            let plain = MemoryStorage::default();
+            plain.save(MIGRATED_KEY, marker).await.unwrap();
+            plain.save("key", b"old value").await.unwrap();
+            let storage = EncryptedStorage::new(plain.clone(), &[]);
+            storage.unlock(&SEED);
+
+            assert_eq!(storage.load("key").await.unwrap(), b"old value");
+            assert!(is_sealed(&plain.load("key").await.unwrap()));
+            let marker = plain.load(MIGRATED_KEY).await.unwrap();
+            assert_eq!(storage.open(MIGRATED_KEY, &marker).unwrap(), MIGRATED_VALUE);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_migration_leaves_nested_storages_alone() {
+        let plain = MemoryStorage::default();
+        plain.save("identities/alice/key", b"hers").await.unwrap();
+        plain.save("identities-list", b"ours").await.unwrap();
+        let storage = EncryptedStorage::with_inner(Arc::new(plain.clone()), &[], &["identities"]);
+        storage.unlock(&SEED);
+
+        assert_eq!(storage.load("identities-list").await.unwrap(), b"ours");
+        assert_eq!(plain.load("identities/alice/key").await.unwrap(), b"hers");
+    }
+}
@@ -0,0 +1,297 @@
+//! File based storage for native platforms.
+//!
+//! Every write goes to a temporary file that is fsynced and then renamed over the target,
+//! so a crash leaves either the old or the new value, never a truncated one. The previous
+//! value is kept alongside as a single rolling backup.
+//!
+//! Keys are validated with [validate_key] and namespaces map to subdirectories, so
+//! `peers/<vlad>` lives at `<data_dir>/peers/<vlad>`. Temporary files and backups are
+//! dot-files, which valid keys can never name.
+use super::{StorageError, WalletStorage, validate_key};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt as _;
+use tokio::sync::Mutex;
+
+/// Suffix of the file a new value is written to before being renamed into place
+const TMP_SUFFIX: &str = ".tmp";
+/// Suffix of the rolling backup of the previous value
+const BACKUP_SUFFIX: &str = ".bak";
+
+#[derive(Clone, Default)]
+pub struct FileStorage {
+    /// The [PathBuf] where the wallet data will be stored
+    data_dir: PathBuf,
+    /// Serialises writes, so compare-and-swap can't interleave with another write
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl FileStorage {
+    /// Creates a new instance of `FileStorage` in `data_dir`, creating it if needed.
+    ///
+    /// Use a separate directory per identity.
+    pub fn with_dir(data_dir: PathBuf) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&data_dir)?;
+
+        Ok(Self {
+            data_dir,
+            write_lock: Arc::default(),
+        })
+    }
+
+    /// Returns the directory where the wallet data is stored.
+    pub fn dir(&self) -> PathBuf {
+        self.data_dir.clone()
+    }
+
+    /// Maps a validated key onto its file, one subdirectory per namespace.
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(key
+            .split('/')
+            .fold(self.data_dir.clone(), |path, segment| path.join(segment)))
+    }
+
+    fn backup_path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        Ok(hidden_sibling(&self.path(key)?, BACKUP_SUFFIX))
+    }
+
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        read_file(key, &self.path(key)?).await
+    }
+
+    /// Backs up the current value, if any, then atomically replaces it with `data`.
+    ///
+    /// Callers must hold `write_lock`.
+    async fn write(
+        &self,
+        key: &str,
+        current: Option<&[u8]>,
+        data: &[u8],
+    ) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|err| io_error(key, err))?;
+        }
+        if let Some(current) = current {
+            write_atomic(&self.backup_path(key)?, current)
+                .await
+                .map_err(|err| io_error(key, err))?;
+        }
+        write_atomic(&path, data)
+            .await
+            .map_err(|err| io_error(key, err))
+    }
+
+    /// Removes the value and its backup. Callers must hold `write_lock`.
+    async fn remove(&self, key: &str) -> Result<(), StorageError> {
+        for path in [self.path(key)?, self.backup_path(key)?] {
+            match tokio::fs::remove_file(path).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(io_error(key, err));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn read_file(key: &str, path: &Path) -> Result<Option<Vec<u8>>, StorageError> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(io_error(key, err)),
+    }
+}
+
+/// `dir/name` becomes `dir/.name<suffix>`.
+fn hidden_sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Writes `data` to a temporary sibling of `path`, fsyncs it, then renames it into place.
+async fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = hidden_sibling(path, TMP_SUFFIX);
+
+    let mut file = tokio::fs::File::create(&tmp).await?;
+    file.write_all(data).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    tokio::fs::rename(&tmp, path).await?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        tokio::fs::File::open(dir).await?.sync_all().await?;
+    }
+    Ok(())
+}
+
+/// Maps I/O failures onto the matching [StorageError].
+fn io_error(key: &str, err: std::io::Error) -> StorageError {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => StorageError::NotFound(key.to_string()),
+        std::io::ErrorKind::StorageFull => StorageError::QuotaExceeded,
+        _ => StorageError::Backend(format!("{key}: {err}")),
+    }
+}
+
+#[async_trait::async_trait]
+impl WalletStorage for FileStorage {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().await;
+        let current = self.read(key).await?;
+        self.write(key, current.as_deref(), data).await
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.read(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn load_backup(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        read_file(key, &self.backup_path(key)?)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().await;
+        self.remove(key).await
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let mut keys = Vec::new();
+        // Walk the namespaces as (directory, key prefix) pairs
+        let mut pending = vec![(self.data_dir.clone(), String::new())];
+        while let Some((dir, namespace)) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir)
+                .await
+                .map_err(|err| io_error(prefix, err))?;
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|err| io_error(prefix, err))?
+            {
+                // Skips our dot-files, and anything else that couldn't be a valid key
+                let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                let key = format!("{namespace}{name}");
+                if validate_key(&key).is_err() {
+                    continue;
+                }
+                let Ok(file_type) = entry.file_type().await else {
+                    continue;
+                };
+                if file_type.is_dir() {
+                    // Only descend into namespaces that could contain a match
+                    let nested = format!("{key}/");
+                    if nested.starts_with(prefix) || prefix.starts_with(&nested) {
+                        pending.push((entry.path(), nested));
+                    }
+                } else if file_type.is_file() && key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().await;
+        let current = self.read(key).await?;
+        if current.as_deref() != expected {
+            return Err(StorageError::Conflict(key.to_string()));
+        }
+        match new {
+            Some(data) => self.write(key, current.as_deref(), data).await,
+            None => self.remove(key).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageProvider;
+
+    fn storage() -> (tempfile::TempDir, FileStorage) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::with_dir(dir.path().to_path_buf()).unwrap();
+        (dir, storage)
+    }
+
+    #[tokio::test]
+    async fn save_renames_a_temporary_file_into_place() {
+        let (dir, storage) = storage();
+        storage.save("peers/abc", b"followed").await.unwrap();
+
+        let path = dir.path().join("peers").join("abc");
+        assert_eq!(std::fs::read(&path).unwrap(), b"followed");
+        assert!(!hidden_sibling(&path, TMP_SUFFIX).exists());
+
+        // A write that crashed before its rename leaves the value and the key list alone
+        std::fs::write(hidden_sibling(&path, TMP_SUFFIX), b"torn").unwrap();
+        assert_eq!(storage.load("peers/abc").await.unwrap(), b"followed");
+        assert_eq!(storage.list_keys("").await.unwrap(), vec!["peers/abc"]);
+    }
+
+    #[tokio::test]
+    async fn each_write_backs_up_the_value_before_it() {
+        let (_dir, storage) = storage();
+        storage.save("key", b"first").await.unwrap();
+        assert_eq!(
+            storage.load_backup("key").await,
+            Err(StorageError::NotFound("key".to_string()))
+        );
+
+        storage.save("key", b"second").await.unwrap();
+        assert_eq!(storage.load_backup("key").await.unwrap(), b"first");
+        storage
+            .compare_and_swap("key", Some(b"second".as_slice()), Some(b"third".as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(storage.load("key").await.unwrap(), b"third");
+        assert_eq!(storage.load_backup("key").await.unwrap(), b"second");
+
+        storage.delete("key").await.unwrap();
+        assert!(storage.load_backup("key").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_torn_value_falls_back_on_its_backup() {
+        let (dir, file) = storage();
+        let storage = StorageProvider::new_encrypted(file);
+        storage.unlock(&[7; 32]);
+        storage.save("VLAD_STORAGE_KEY", b"first").await.unwrap();
+        storage.save("VLAD_STORAGE_KEY", b"second").await.unwrap();
+
+        let path = dir.path().join("VLAD_STORAGE_KEY");
+        let mut torn = std::fs::read(&path).unwrap();
+        torn.truncate(torn.len() - 4);
+        std::fs::write(&path, torn).unwrap();
+
+        assert!(matches!(
+            storage.load("VLAD_STORAGE_KEY").await,
+            Err(StorageError::Corrupted { .. })
+        ));
+        assert_eq!(
+            storage.load_backup("VLAD_STORAGE_KEY").await.unwrap(),
+            b"first"
+        );
+    }
+}
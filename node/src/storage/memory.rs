@@ -0,0 +1,81 @@
+//! An in-memory [WalletStorage] for tests.
+use super::{StorageError, WalletStorage};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Runs on the stored values right before a compare-and-swap, which fails with its error.
+type SwapHook = Box<dyn FnOnce(&mut HashMap<String, Vec<u8>>) -> Result<(), StorageError> + Send>;
+
+#[derive(Clone, Default)]
+pub(crate) struct MemoryStorage {
+    values: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    before_swap: Arc<Mutex<Option<SwapHook>>>,
+}
+
+impl MemoryStorage {
+    /// Runs `hook` before the next compare-and-swap, to make it fail or to race it with
+    /// another write.
+    pub(crate) fn before_next_swap(
+        &self,
+        hook: impl FnOnce(&mut HashMap<String, Vec<u8>>) -> Result<(), StorageError> + Send + 'static,
+    ) {
+        *self.before_swap.lock().unwrap() = Some(Box::new(hook));
+    }
+}
+
+#[async_trait::async_trait]
+impl WalletStorage for MemoryStorage {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.values
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.values.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .values
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        let hook = self.before_swap.lock().unwrap().take();
+        let mut values = self.values.lock().unwrap();
+        if let Some(hook) = hook {
+            hook(&mut values)?;
+        }
+        if values.get(key).map(Vec::as_slice) != expected {
+            return Err(StorageError::Conflict(key.to_string()));
+        }
+        match new {
+            Some(data) => values.insert(key.to_string(), data.to_vec()),
+            None => values.remove(key),
+        };
+        Ok(())
+    }
+}
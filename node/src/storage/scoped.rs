@@ -0,0 +1,127 @@
+//! A namespace inside another [WalletStorage], so several identities can share one backend.
+//!
+//! Every key is stored below `<namespace>/`, and [WalletStorage::list_keys] strips it again,
+//! so code using the scoped storage sees the same keys it would see on its own backend.
+use super::{StorageError, WalletStorage};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub(crate) struct ScopedStorage {
+    inner: Arc<dyn WalletStorage>,
+    /// The namespace followed by `/`
+    prefix: String,
+}
+
+impl ScopedStorage {
+    /// Scopes `inner` to `namespace`, which has to be a valid storage key.
+    pub(crate) fn new(inner: Arc<dyn WalletStorage>, namespace: &str) -> Self {
+        Self {
+            inner,
+            prefix: format!("{namespace}/"),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl WalletStorage for ScopedStorage {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.inner.save(&self.key(key), data).await
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.inner.load(&self.key(key)).await
+    }
+
+    async fn load_backup(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.inner.load_backup(&self.key(key)).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.inner.delete(&self.key(key)).await
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .inner
+            .list_keys(&self.key(prefix))
+            .await?
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+            .collect())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        self.inner
+            .compare_and_swap(&self.key(key), expected, new)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::memory::MemoryStorage;
+    use crate::storage::{SEED_STORAGE_KEY, StorageProvider, WalletStorage as _};
+
+    #[tokio::test]
+    async fn keys_are_stored_below_the_namespace() {
+        let memory = MemoryStorage::default();
+        let root = StorageProvider::new(memory.clone());
+        let alice = root.scoped("identities/alice").unwrap();
+        let bob = root.scoped("identities/bob").unwrap();
+
+        alice.save("peers/abc", b"followed").await.unwrap();
+        assert_eq!(
+            memory.load("identities/alice/peers/abc").await.unwrap(),
+            b"followed"
+        );
+        assert_eq!(alice.load("peers/abc").await.unwrap(), b"followed");
+        assert_eq!(alice.list_keys("peers/").await.unwrap(), vec!["peers/abc"]);
+        assert!(bob.try_load("peers/abc").await.unwrap().is_none());
+        assert!(bob.list_keys("").await.unwrap().is_empty());
+
+        alice
+            .compare_and_swap("peers/abc", Some(b"followed".as_slice()), None)
+            .await
+            .unwrap();
+        assert!(memory.list_keys("").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn passthrough_keys_stay_readable_before_unlocking() {
+        let memory = MemoryStorage::default();
+        let root = StorageProvider::new_encrypted(memory.clone());
+        let alice = root.scoped("identities/alice").unwrap();
+        alice.unlock(&[7; 32]);
+        alice
+            .save(SEED_STORAGE_KEY, b"encrypted seed")
+            .await
+            .unwrap();
+        alice.save("VLAD_STORAGE_KEY", b"plog").await.unwrap();
+
+        // The seed is kept as it is below the namespace, everything else is sealed
+        let seed_key = format!("identities/alice/{SEED_STORAGE_KEY}");
+        assert_eq!(memory.load(&seed_key).await.unwrap(), b"encrypted seed");
+        let sealed = memory
+            .load("identities/alice/VLAD_STORAGE_KEY")
+            .await
+            .unwrap();
+        assert_ne!(sealed, b"plog");
+
+        alice.lock();
+        assert_eq!(
+            alice.load(SEED_STORAGE_KEY).await.unwrap(),
+            b"encrypted seed"
+        );
+        assert!(alice.load("VLAD_STORAGE_KEY").await.is_err());
+    }
+}
@@ -0,0 +1,36 @@
+//! Values for tests that would otherwise have to come from a plog, and peers to make plogs with.
+use multicid::Vlad;
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    crate::keys::{KeyMan, WalletKeys},
+    bs_peer::BsPeer,
+    bs_peer::peer::DefaultBsPeer,
+    bs_peer::platform::StartConfig,
+    std::path::Path,
+};
+
+/// A VLAD like the ones our plogs get: a 64 byte signature as nonce, and a CIDv1 of a
+/// SHA3-512 digest. Its hex alone is longer than a key segment may be.
+pub(crate) fn vlad(fill: u8) -> Vlad {
+    let mut bytes = vec![0x87, 0x24]; // vlad
+    bytes.extend([0x3b, 0x40]); // nonce, 64 bytes
+    bytes.extend([fill; 64]);
+    bytes.extend([0x01, 0x71]); // CIDv1, dag-cbor
+    bytes.extend([0x31, 0x14, 0x40]); // multihash, sha3-512, 64 bytes
+    bytes.extend([fill.wrapping_add(1); 64]);
+    Vlad::try_from(bytes.as_slice()).expect("a valid VLAD")
+}
+
+/// A peer signing with `keys`, keeping its node state in `dir`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn peer(keys: &WalletKeys, dir: &Path) -> DefaultBsPeer<KeyMan> {
+    BsPeer::new(
+        keys.key_manager().expect("unlocked keys"),
+        StartConfig {
+            base_path: Some(dir.to_path_buf()),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("a running peer")
+}
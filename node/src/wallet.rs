@@ -0,0 +1,308 @@
+//! Opening the password encrypted wallet seed kept in storage.
+use crate::mnemonic::{self, MnemonicError};
+use crate::plog;
+use crate::storage::{SEED_STORAGE_KEY, StorageError, StorageProvider};
+use multicid::Vlad;
+use seed_keeper_core::credentials::{Credentials, MinString, Wallet};
+use zeroize::Zeroizing;
+
+/// Errors opening or creating a wallet.
+#[derive(Debug, thiserror::Error)]
+pub enum WalletError {
+    /// There is no wallet in storage yet
+    #[error("No wallet found, create one first")]
+    NotFound,
+
+    /// There is already a wallet in storage
+    #[error("A wallet already exists")]
+    AlreadyExists,
+
+    /// The stored wallet changed while its credentials were being changed
+    #[error("The wallet was changed elsewhere, try again")]
+    Conflict,
+
+    /// The username or password were rejected
+    #[error("Invalid credentials: {0}")]
+    Credentials(String),
+
+    /// The recovery words could not be read
+    #[error(transparent)]
+    Mnemonic(#[from] MnemonicError),
+
+    /// From<StorageError>
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+/// Opens the wallet in `storage` with the given credentials.
+pub async fn open(
+    storage: &StorageProvider,
+    username: &str,
+    password: &str,
+) -> Result<Wallet, WalletError> {
+    let encrypted_seed = storage
+        .try_load(SEED_STORAGE_KEY)
+        .await?
+        .ok_or(WalletError::NotFound)?;
+    wallet(username, password, Some(encrypted_seed))
+}
+
+/// Creates a new wallet and stores its encrypted seed, unless `storage` already has one.
+pub async fn create(
+    storage: &StorageProvider,
+    username: &str,
+    password: &str,
+) -> Result<Wallet, WalletError> {
+    let wallet = wallet(username, password, None)?;
+    store(storage, wallet).await
+}
+
+/// Rebuilds the wallet from its recovery `words`, encrypts it under a new `username` and
+/// `password`, and stores it, unless `storage` already has a wallet. Leaves `storage` unlocked
+/// with the recovered wallet.
+///
+/// Only the wallet comes back, the plog of `vlad` is attached again with [plog::attach]. Until
+/// then `vlad` is [plog::pending_attach], which is stored before the wallet, so no new plog is
+/// ever generated for it.
+pub async fn recover(
+    storage: &StorageProvider,
+    words: &str,
+    vlad: &Vlad,
+    username: &str,
+    password: &str,
+) -> Result<Wallet, WalletError> {
+    let seed = mnemonic::to_seed(words)?;
+    let wallet = from_seed(username, password, &seed)?;
+    if storage.try_load(SEED_STORAGE_KEY).await?.is_some() {
+        return Err(WalletError::AlreadyExists);
+    }
+    storage.unlock(wallet.seed());
+    plog::set_pending_attach(storage, vlad).await?;
+    store(storage, wallet).await
+}
+
+/// Creates a new wallet to take over the plog of `vlad` through its guardians, and stores it,
+/// unless `storage` already has a wallet. Leaves `storage` unlocked with the new wallet.
+///
+/// The plog is recovered with [plog::recover] once enough guardians approved. Until then
+/// `vlad` is [plog::pending_attach], as after [recover], so no new plog is generated.
+pub async fn create_for_recovery(
+    storage: &StorageProvider,
+    vlad: &Vlad,
+    username: &str,
+    password: &str,
+) -> Result<Wallet, WalletError> {
+    let wallet = wallet(username, password, None)?;
+    if storage.try_load(SEED_STORAGE_KEY).await?.is_some() {
+        return Err(WalletError::AlreadyExists);
+    }
+    storage.unlock(wallet.seed());
+    plog::set_pending_attach(storage, vlad).await?;
+    store(storage, wallet).await
+}
+
+/// Encrypts the seed of the wallet in `storage` under a new username and password, after
+/// opening it with the old ones, and replaces the stored seed in one step.
+///
+/// The seed stays the same, so the plog, the keys and the data encrypted at rest are untouched.
+pub async fn change_credentials(
+    storage: &StorageProvider,
+    old_username: &str,
+    old_password: &str,
+    new_username: &str,
+    new_password: &str,
+) -> Result<Wallet, WalletError> {
+    let stored = storage
+        .try_load(SEED_STORAGE_KEY)
+        .await?
+        .ok_or(WalletError::NotFound)?;
+    let old = wallet(old_username, old_password, Some(stored.clone()))?;
+    let new = from_seed(new_username, new_password, old.seed())?;
+    let encrypted_seed = new
+        .encrypted_seed()
+        .map_err(|e| WalletError::Credentials(format!("Error encrypting seed: {e}")))?;
+    match storage
+        .compare_and_swap(SEED_STORAGE_KEY, Some(&stored), Some(&encrypted_seed))
+        .await
+    {
+        Ok(()) => Ok(new),
+        Err(StorageError::Conflict(_)) => Err(WalletError::Conflict),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The wallet holding `seed`, encrypted under `username` and `password`.
+fn from_seed(username: &str, password: &str, seed: &[u8]) -> Result<Wallet, WalletError> {
+    // seed-keeper derives the wrapping key from the password, salted with the username
+    let key = seed_keeper_core::derive_key(password, username)
+        .map_err(|e| WalletError::Credentials(e.to_string()))?;
+    let encrypted_seed = seed_keeper_core::wrap::encrypt(key, Zeroizing::new(seed.to_vec()))
+        .map_err(|e| WalletError::Credentials(format!("Error encrypting seed: {e}")))?;
+    let wallet = wallet(username, password, Some(encrypted_seed))?;
+    // Any other seed would be a different identity, so make sure it survived the round trip
+    if wallet.seed() != seed {
+        return Err(WalletError::Credentials(
+            "The seed could not be encrypted again".to_string(),
+        ));
+    }
+    Ok(wallet)
+}
+
+/// Stores the encrypted seed of `wallet`, unless `storage` already has a wallet.
+async fn store(storage: &StorageProvider, wallet: Wallet) -> Result<Wallet, WalletError> {
+    let seed = wallet
+        .encrypted_seed()
+        .map_err(|e| WalletError::Credentials(format!("Error encrypting seed: {e}")))?;
+    match storage
+        .compare_and_swap(SEED_STORAGE_KEY, None, Some(&seed))
+        .await
+    {
+        Ok(()) => Ok(wallet),
+        Err(StorageError::Conflict(_)) => Err(WalletError::AlreadyExists),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Opens the wallet in `storage`, or creates one if there is none yet.
+pub async fn open_or_create(
+    storage: &StorageProvider,
+    username: &str,
+    password: &str,
+) -> Result<Wallet, WalletError> {
+    match open(storage, username, password).await {
+        Err(WalletError::NotFound) => create(storage, username, password).await,
+        result => result,
+    }
+}
+
+pub(crate) fn wallet(
+    username: &str,
+    password: &str,
+    encrypted_seed: Option<Vec<u8>>,
+) -> Result<Wallet, WalletError> {
+    let credentials = Credentials {
+        username: MinString::new(username)
+            .map_err(|e| WalletError::Credentials(format!("Invalid username: {e}")))?,
+        password: MinString::new(password)
+            .map_err(|e| WalletError::Credentials(format!("Invalid password: {e}")))?,
+        encrypted_seed,
+    };
+    Wallet::new(credentials).map_err(|e| WalletError::Credentials(e.to_string()))
+}
+
+/// Reads the credentials from `VAIBER_USERNAME` and `VAIBER_PASSWORD`, prompting on the
+/// terminal for whichever is not set.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_credentials() -> std::io::Result<(String, String)> {
+    let username = read_username("VAIBER_USERNAME", "Username: ")?;
+    let password = match std::env::var("VAIBER_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => rpassword::prompt_password("Password: ")?,
+    };
+    Ok((username, password))
+}
+
+/// Reads the new credentials from `VAIBER_NEW_USERNAME` and `VAIBER_NEW_PASSWORD`, prompting
+/// for whichever is not set. A prompted password has to be typed twice.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_new_credentials() -> std::io::Result<(String, String)> {
+    let username = read_username("VAIBER_NEW_USERNAME", "New username: ")?;
+    let password = match std::env::var("VAIBER_NEW_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            let password = rpassword::prompt_password("New password: ")?;
+            if rpassword::prompt_password("Repeat new password: ")? != password {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "The passwords do not match",
+                ));
+            }
+            password
+        }
+    };
+    Ok((username, password))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_username(var: &str, prompt: &str) -> std::io::Result<String> {
+    use std::io::Write as _;
+
+    match std::env::var(var) {
+        Ok(username) => Ok(username),
+        Err(_) => {
+            print!("{prompt}");
+            std::io::stdout().flush()?;
+            let mut username = String::new();
+            std::io::stdin().read_line(&mut username)?;
+            Ok(username.trim().to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn changing_credentials_keeps_the_seed_under_the_new_ones_only() {
+        let storage = StorageProvider::new(MemoryStorage::default());
+        let created = create(&storage, "username", "password").await.unwrap();
+
+        let changed = change_credentials(
+            &storage,
+            "username",
+            "password",
+            "new username",
+            "new password",
+        )
+        .await
+        .unwrap();
+        assert_eq!(changed.seed(), created.seed());
+
+        let opened = open(&storage, "new username", "new password")
+            .await
+            .unwrap();
+        assert_eq!(opened.seed(), created.seed());
+        assert!(open(&storage, "username", "password").await.is_err());
+        assert!(
+            change_credentials(&storage, "username", "password", "another name", "password")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn a_concurrent_change_of_credentials_is_a_conflict() {
+        let memory = MemoryStorage::default();
+        let storage = StorageProvider::new(memory.clone());
+        let created = create(&storage, "username", "password").await.unwrap();
+
+        // Someone else changes the credentials between our load and our write
+        let theirs = from_seed("their username", "their password", created.seed()).unwrap();
+        let their_seed = theirs.encrypted_seed().unwrap();
+        let racing_write = their_seed.clone();
+        memory.before_next_swap(move |values| {
+            values.insert(SEED_STORAGE_KEY.to_string(), racing_write);
+            Ok(())
+        });
+
+        assert!(matches!(
+            change_credentials(
+                &storage,
+                "username",
+                "password",
+                "new username",
+                "new password"
+            )
+            .await,
+            Err(WalletError::Conflict)
+        ));
+        assert_eq!(storage.load(SEED_STORAGE_KEY).await.unwrap(), their_seed);
+        assert!(
+            open(&storage, "new username", "new password")
+                .await
+                .is_err()
+        );
+    }
+}
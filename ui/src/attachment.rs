@@ -0,0 +1,183 @@
+//! Previews of data values in a Plog, with a way to save them.
+//!
+//! Attachments are stored in the entry itself, see [vaiber_node::blobs].
+use base64::Engine as _;
+use dioxus::logger::tracing;
+use dioxus::prelude::*;
+
+/// Longest text shown in a preview, the rest is only in the saved file.
+const PREVIEW_CHARS: usize = 2000;
+
+/// What an attachment looks like, from its content.
+#[derive(Clone, Debug, PartialEq)]
+enum AttachmentKind {
+    Image(&'static str),
+    Json(String),
+    Text(String),
+    Binary,
+}
+
+impl AttachmentKind {
+    fn detect(data: &[u8]) -> Self {
+        const IMAGES: &[(&[u8], &str)] = &[
+            (b"\x89PNG\r\n\x1a\n", "image/png"),
+            (b"\xff\xd8\xff", "image/jpeg"),
+            (b"GIF8", "image/gif"),
+        ];
+        if let Some((_, mime)) = IMAGES.iter().find(|(magic, _)| data.starts_with(magic)) {
+            return AttachmentKind::Image(mime);
+        }
+        if data.len() > 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            return AttachmentKind::Image("image/webp");
+        }
+        let Ok(text) = std::str::from_utf8(data) else {
+            return AttachmentKind::Binary;
+        };
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(json) => AttachmentKind::Json(
+                serde_json::to_string_pretty(&json).unwrap_or_else(|_| text.to_string()),
+            ),
+            Err(_) => AttachmentKind::Text(text.to_string()),
+        }
+    }
+
+    fn mime(&self) -> &'static str {
+        match self {
+            AttachmentKind::Image(mime) => mime,
+            AttachmentKind::Json(_) => "application/json",
+            AttachmentKind::Text(_) => "text/plain",
+            AttachmentKind::Binary => "application/octet-stream",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            AttachmentKind::Image(mime) => mime.trim_start_matches("image/"),
+            AttachmentKind::Json(_) => "json",
+            AttachmentKind::Text(_) => "txt",
+            AttachmentKind::Binary => "bin",
+        }
+    }
+}
+
+/// A data value under `key`, which is the attachment itself.
+#[component]
+pub(crate) fn AttachmentView(key: String, data: Vec<u8>) -> Element {
+    rsx! { AttachmentPreview { key, data } }
+}
+
+/// An image attachment, shown on its own, e.g. an avatar.
+#[component]
+pub(crate) fn AttachmentImage(data: Vec<u8>, class: String) -> Element {
+    let AttachmentKind::Image(mime) = AttachmentKind::detect(&data) else {
+        return rsx! {};
+    };
+    let data_url = format!(
+        "data:{mime};base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&data)
+    );
+    rsx! {
+        img { class, src: "{data_url}" }
+    }
+}
+
+/// Whether `data` is an image we can show.
+pub(crate) fn is_image(data: &[u8]) -> bool {
+    matches!(AttachmentKind::detect(data), AttachmentKind::Image(_))
+}
+
+/// Shows images, JSON and text inline, with a button to save the attachment as a file.
+#[component]
+fn AttachmentPreview(key: String, data: Vec<u8>) -> Element {
+    let kind = AttachmentKind::detect(&data);
+    let file_name = format!(
+        "{}.{}",
+        key.trim_matches('/').replace('/', "-"),
+        kind.extension()
+    );
+    let data_url = format!(
+        "data:{};base64,{}",
+        kind.mime(),
+        base64::engine::general_purpose::STANDARD.encode(&data)
+    );
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-1 w-full",
+            match &kind {
+                AttachmentKind::Image(_) => rsx! {
+                    img { class: "max-h-32 max-w-full rounded border object-contain self-start", src: "{data_url}" }
+                },
+                AttachmentKind::Json(text) | AttachmentKind::Text(text) => rsx! {
+                    pre {
+                        class: "max-h-32 overflow-auto p-1 bg-white border rounded text-gray-800 whitespace-pre-wrap break-all",
+                        {text.chars().take(PREVIEW_CHARS).collect::<String>()}
+                    }
+                },
+                AttachmentKind::Binary => rsx! {
+                    span { class: "font-mono text-gray-700", "{data.len()} bytes" }
+                },
+            }
+            SaveButton { file_name, data_url, data }
+        }
+    }
+}
+
+/// Downloads the data URL in the browser.
+#[cfg(target_arch = "wasm32")]
+#[component]
+pub(crate) fn SaveButton(file_name: String, data_url: String, data: Vec<u8>) -> Element {
+    rsx! {
+        a {
+            class: "self-start px-2 border rounded text-xs text-green-700 hover:bg-green-50",
+            href: "{data_url}",
+            download: "{file_name}",
+            "Save"
+        }
+    }
+}
+
+/// Asks where to save the file with a native dialog.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+#[component]
+pub(crate) fn SaveButton(file_name: String, data_url: String, data: Vec<u8>) -> Element {
+    let _ = data_url;
+    let handle_save = move |_| {
+        let file_name = file_name.clone();
+        let data = data.clone();
+        async move {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .set_file_name(&file_name)
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            if let Err(e) = std::fs::write(file.path(), &data) {
+                tracing::error!("Failed to save {}: {}", file_name, e);
+            }
+        }
+    };
+
+    rsx! {
+        button {
+            class: "self-start px-2 border rounded text-xs text-green-700 hover:bg-green-50",
+            r#type: "button",
+            onclick: handle_save,
+            "Save"
+        }
+    }
+}
+
+/// Mobile has no file dialog to save to, so there is only the preview.
+#[cfg(not(any(
+    target_arch = "wasm32",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "windows"
+)))]
+#[component]
+pub(crate) fn SaveButton(file_name: String, data_url: String, data: Vec<u8>) -> Element {
+    let _ = (file_name, data_url, data);
+    rsx! {}
+}
@@ -0,0 +1,154 @@
+//! Locking the unlocked wallet by itself, see [vaiber_node::autolock].
+//!
+//! Input, focus and visibility are watched from the page with a small script, which works the
+//! same in the desktop webview and in the browser.
+use crate::StorageProvider;
+use dioxus::logger::tracing;
+use dioxus::prelude::*;
+use vaiber_node::autolock::{self, AutoLockSettings, IdleTimer, LockReason};
+
+/// Reports input at most once a second, and losing focus or visibility, as `input` and `blur`.
+/// Listeners from an earlier mount are removed first.
+const LISTENERS: &str = r#"
+window.vaiberAutoLock?.abort();
+const controller = new AbortController();
+window.vaiberAutoLock = controller;
+const options = { signal: controller.signal, passive: true };
+let lastInput = 0;
+const input = () => {
+    const now = Date.now();
+    if (now - lastInput > 1000) {
+        lastInput = now;
+        dioxus.send("input");
+    }
+};
+for (const name of ["pointerdown", "pointermove", "keydown", "wheel", "touchstart"]) {
+    window.addEventListener(name, input, options);
+}
+window.addEventListener("blur", () => dioxus.send("blur"), options);
+document.addEventListener("visibilitychange", () => {
+    if (document.hidden) {
+        dioxus.send("blur");
+    }
+}, options);
+"#;
+
+/// Waits for `duration` on whichever timer the platform has.
+#[cfg(target_arch = "wasm32")]
+async fn sleep(duration: std::time::Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Waits for `duration` on whichever timer the platform has.
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(duration: std::time::Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Calls `on_lock` once the wallet should lock under `settings`, which it loads from storage.
+///
+/// Render it only while the wallet is unlocked.
+#[component]
+pub(crate) fn AutoLock(
+    settings: Signal<AutoLockSettings>,
+    on_lock: EventHandler<LockReason>,
+) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let mut timer = use_signal(IdleTimer::default);
+
+    use_future(move || {
+        let storage = storage.clone();
+        async move { settings.set(AutoLockSettings::load(&storage).await) }
+    });
+
+    use_future(move || async move {
+        let mut events = document::eval(LISTENERS);
+        while let Ok(event) = events.recv::<String>().await {
+            match event.as_str() {
+                "blur" if settings.peek().lock_on_blur => {
+                    on_lock.call(LockReason::Blurred);
+                    return;
+                }
+                "blur" => {}
+                _ => timer.write().touch(),
+            }
+        }
+    });
+
+    use_future(move || async move {
+        loop {
+            sleep(autolock::TICK).await;
+            let locking = timer.write().check(&settings.peek());
+            if let Some(reason) = locking {
+                on_lock.call(reason);
+                return;
+            }
+        }
+    });
+
+    use_drop(|| {
+        document::eval("window.vaiberAutoLock?.abort();");
+    });
+
+    rsx! {}
+}
+
+/// Edits and stores the auto-lock `settings` of the unlocked identity.
+#[component]
+pub(crate) fn AutoLockEditor(settings: Signal<AutoLockSettings>) -> Element {
+    let storage = use_context::<StorageProvider>();
+
+    let mut save = move |updated: AutoLockSettings| {
+        settings.set(updated);
+        let storage = storage.clone();
+        spawn(async move {
+            if let Err(e) = updated.save(&storage).await {
+                tracing::error!("Failed to save auto-lock settings: {}", e);
+            }
+        });
+    };
+    let mut save_blur = save.clone();
+    let mut save_suspend = save.clone();
+
+    let current = settings();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 text-xs w-64",
+            label {
+                class: "flex items-center justify-between gap-2",
+                "Lock after minutes without input"
+                input {
+                    class: "w-16 px-2 py-1 border rounded",
+                    r#type: "number",
+                    min: "0",
+                    value: "{current.idle_minutes}",
+                    onchange: move |e| {
+                        if let Ok(idle_minutes) = e.value().parse() {
+                            save(AutoLockSettings { idle_minutes, ..current });
+                        }
+                    },
+                }
+            }
+            label {
+                class: "flex items-center gap-2",
+                input {
+                    r#type: "checkbox",
+                    checked: current.lock_on_blur,
+                    onchange: move |e| save_blur(AutoLockSettings { lock_on_blur: e.checked(), ..current }),
+                }
+                "Lock when the window loses focus"
+            }
+            label {
+                class: "flex items-center gap-2",
+                input {
+                    r#type: "checkbox",
+                    checked: current.lock_on_suspend,
+                    onchange: move |e| save_suspend(AutoLockSettings { lock_on_suspend: e.checked(), ..current }),
+                }
+                "Lock when the system wakes from sleep"
+            }
+            p { class: "text-gray-500", "0 minutes never locks when idle." }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Exporting the whole identity to one encrypted backup file, and restoring it on the Create
+//! Wallet screen. The bundle itself is made by [vaiber_node::backup].
+use crate::attachment::SaveButton;
+use crate::wallet::{off_ui_thread, KeyCodec, WalletKeys};
+use crate::StorageProvider;
+use base64::Engine as _;
+use dioxus::prelude::*;
+use vaiber_node::backup;
+
+/// Asks for a passphrase and offers the backup as a file.
+#[component]
+pub(crate) fn BackupExport() -> Element {
+    let storage = use_context::<StorageProvider>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+
+    let mut passphrase = use_signal(String::new);
+    let mut confirm = use_signal(String::new);
+    let mut bundle = use_signal(|| None::<Vec<u8>>);
+    let mut error = use_signal(|| None::<String>);
+    let mut exporting = use_signal(|| false);
+
+    let handle_export = move |_| {
+        if passphrase() != confirm() {
+            error.set(Some("The passphrases do not match.".to_string()));
+            return;
+        }
+        let Some(keys) = key_manager.read().clone() else {
+            error.set(Some("Wallet is locked.".to_string()));
+            return;
+        };
+        let storage = storage.clone();
+        error.set(None);
+        exporting.set(true);
+        spawn(async move {
+            match backup::export(&storage, &keys, &passphrase()).await {
+                Ok(data) => {
+                    bundle.set(Some(data));
+                    passphrase.set(String::new());
+                    confirm.set(String::new());
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            exporting.set(false);
+        });
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 text-xs w-64",
+            p {
+                class: "text-gray-600",
+                "Everything needed to restore this identity, encrypted with a passphrase. Restoring also needs your username and password."
+            }
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "password",
+                autocomplete: "new-password",
+                placeholder: format!("Backup passphrase, {} characters or more", backup::MIN_PASSPHRASE_LEN),
+                value: "{passphrase}",
+                oninput: move |e| passphrase.set(e.value()),
+            }
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "password",
+                autocomplete: "new-password",
+                placeholder: "Repeat the passphrase",
+                value: "{confirm}",
+                oninput: move |e| confirm.set(e.value()),
+            }
+            button {
+                class: "py-1 px-2 bg-green-500 text-white rounded-md hover:bg-green-600 disabled:bg-gray-400 transition",
+                r#type: "button",
+                disabled: exporting() || passphrase().is_empty(),
+                onclick: handle_export,
+                if exporting() { "Encrypting..." } else { "Create backup" }
+            }
+            if let Some(data) = bundle() {
+                SaveButton {
+                    file_name: backup::FILE_NAME.to_string(),
+                    data_url: format!(
+                        "data:application/octet-stream;base64,{}",
+                        base64::engine::general_purpose::STANDARD.encode(&data),
+                    ),
+                    data,
+                }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500", "{err}" }
+            }
+        }
+    }
+}
+
+/// Restores a wallet from a backup file, for the Create Wallet screen.
+///
+/// Once restored, the wallet is unlocked and `on_restored` gets its encrypted seed.
+#[component]
+pub(crate) fn RestoreBackup(on_restored: EventHandler<Vec<u8>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let mut key_manager = use_context::<Signal<Option<WalletKeys>>>();
+
+    let mut file = use_signal(|| None::<Vec<u8>>);
+    let mut passphrase = use_signal(String::new);
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut restoring = use_signal(|| false);
+
+    let handle_restore = move |_| {
+        let Some(data) = file() else {
+            error.set(Some("Choose a backup file first.".to_string()));
+            return;
+        };
+        let storage = storage.clone();
+        error.set(None);
+        restoring.set(true);
+        spawn(async move {
+            let (passphrase, name, pass) = (passphrase(), username(), password());
+            let restored = off_ui_thread({
+                let storage = storage.clone();
+                async move {
+                    let opened = backup::open(&data, &passphrase)?;
+                    let wallet = backup::restore(&storage, &opened, &name, &pass).await?;
+                    Ok::<_, backup::BackupError>((opened, wallet))
+                }
+            })
+            .await;
+            match restored {
+                Ok((opened, wallet)) => {
+                    // The plog picks the signing key's codec, see WalletKeys::sync
+                    let keys = match WalletKeys::new(wallet.seed(), KeyCodec::default()) {
+                        Ok(keys) => keys,
+                        Err(e) => {
+                            error.set(Some(e.to_string()));
+                            restoring.set(false);
+                            return;
+                        }
+                    };
+                    if let Err(e) = opened.restore_keys(&keys) {
+                        // Signing with the wrong key generations is worse than not unlocking
+                        keys.lock();
+                        storage.lock();
+                        error.set(Some(format!("Failed to restore the keys: {e}")));
+                        restoring.set(false);
+                        return;
+                    }
+                    match wallet.encrypted_seed() {
+                        Ok(seed) => on_restored.call(seed),
+                        Err(e) => error.set(Some(format!("Error encrypting seed: {e}"))),
+                    }
+                    key_manager.set(Some(keys));
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            restoring.set(false);
+        });
+    };
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 mt-2 text-sm",
+            onsubmit: handle_restore,
+            input {
+                r#type: "file",
+                accept: ".vbak",
+                class: "text-xs",
+                onchange: move |e| async move {
+                    let Some(file_engine) = e.files() else {
+                        return;
+                    };
+                    let Some(name) = file_engine.files().into_iter().next() else {
+                        file.set(None);
+                        return;
+                    };
+                    match file_engine.read_file(&name).await {
+                        Some(data) => file.set(Some(data)),
+                        None => error.set(Some(format!("Failed to read {name}"))),
+                    }
+                },
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "password",
+                placeholder: "Backup passphrase",
+                value: "{passphrase}",
+                oninput: move |e| passphrase.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "text",
+                autocomplete: "username",
+                placeholder: "Username of the backed up wallet",
+                value: "{username}",
+                oninput: move |e| username.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "password",
+                autocomplete: "current-password",
+                placeholder: "Password of the backed up wallet",
+                value: "{password}",
+                oninput: move |e| password.set(e.value()),
+            }
+            button {
+                class: "w-full py-2 px-4 rounded-md bg-blue-500 text-white hover:bg-blue-600 disabled:bg-gray-400 transition",
+                r#type: "submit",
+                disabled: restoring() || file.read().is_none(),
+                if restoring() { "Restoring..." } else { "Restore Wallet" }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500 text-xs", "{err}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Picking, and adding, the identity to unlock on the login screen.
+//!
+//! [crate::WalletComponent] keeps the root storage and the active identity in an [Identities]
+//! context. Everything below it gets the active identity's own storage instead, see
+//! [vaiber_node::identity].
+use crate::StorageProvider;
+use dioxus::prelude::*;
+use vaiber_node::identity;
+
+/// The storage holding every identity, and the name of the active one.
+#[derive(Clone)]
+pub(crate) struct Identities {
+    pub root: StorageProvider,
+    pub active: Signal<String>,
+}
+
+/// Lists the identities to switch between, and adds new ones.
+#[component]
+pub(crate) fn IdentityPicker() -> Element {
+    let Identities { root, mut active } = use_context::<Identities>();
+
+    let mut new_name = use_signal(String::new);
+    let mut adding = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    let list_root = root.clone();
+    let mut names = use_resource(move || {
+        let root = list_root.clone();
+        async move { identity::list(&root).await }
+    });
+
+    let handle_add = move |_| {
+        let name = new_name().trim().to_string();
+        let root = root.clone();
+        error.set(None);
+        spawn(async move {
+            match identity::add(&root, &name).await {
+                Ok(()) => {
+                    new_name.set(String::new());
+                    adding.set(false);
+                    names.restart();
+                    active.set(name);
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let (names, list_error) = match &*names.read() {
+        Some(Ok(names)) => (names.clone(), None),
+        Some(Err(e)) => (vec![active()], Some(e.to_string())),
+        None => (vec![active()], None),
+    };
+
+    rsx! {
+        div { class: "space-y-2 mb-4",
+            label { class: "block text-sm font-medium text-gray-700", for: "identity", "Identity" }
+            div { class: "flex gap-2",
+                select {
+                    class: "flex-grow px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-green-500",
+                    id: "identity",
+                    onchange: move |evt| active.set(evt.value()),
+                    for name in names {
+                        option { value: "{name}", selected: name == active(), "{name}" }
+                    }
+                }
+                button {
+                    class: "px-3 py-2 border rounded-md text-sm hover:bg-gray-100",
+                    r#type: "button",
+                    onclick: move |_| adding.toggle(),
+                    "New"
+                }
+            }
+            if adding() {
+                div { class: "flex gap-2",
+                    input {
+                        class: "flex-grow px-3 py-2 border border-gray-300 rounded-md",
+                        r#type: "text",
+                        placeholder: "Name, e.g. work",
+                        value: "{new_name}",
+                        oninput: move |e| new_name.set(e.value()),
+                    }
+                    button {
+                        class: "px-3 py-2 rounded-md bg-green-500 text-white hover:bg-green-600 disabled:bg-gray-400 transition",
+                        r#type: "button",
+                        disabled: new_name().trim().is_empty(),
+                        onclick: handle_add,
+                        "Add"
+                    }
+                }
+            }
+            if let Some(err) = list_error.or(error()) {
+                p { class: "text-red-500 text-xs", "{err}" }
+            }
+        }
+    }
+}
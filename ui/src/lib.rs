@@ -1,7 +1,8 @@
 //! This crate contains all shared UI for the workspace.
 
-mod storage;
-pub use storage::{StorageProvider, WalletStorage};
+pub use vaiber_node::{
+    validate_key, EncryptedStorage, StorageError, StorageProvider, WalletStorage,
+};
 
 mod hero;
 pub use hero::Hero;
@@ -10,3 +11,19 @@ mod wallet;
 pub use wallet::WalletComponent;
 
 mod peer;
+
+mod attachment;
+
+mod profile;
+
+mod staged;
+
+mod backup;
+
+mod mnemonic;
+
+mod identity;
+
+mod autolock;
+
+mod recovery;
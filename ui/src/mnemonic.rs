@@ -0,0 +1,297 @@
+//! The wallet seed as recovery words: shown once after a wallet is created, with a quiz to make
+//! sure they were written down, and turned back into a wallet on the Create Wallet screen.
+//!
+//! Words only bring back the wallet. The Plog is found again by its VLAD, and attached once the
+//! peer is connected, see [vaiber_node::plog::attach].
+use crate::recovery::RequestRecovery;
+use crate::wallet::{off_ui_thread, KeyCodec, KeyMan, WalletKeys};
+use crate::StorageProvider;
+use bs_peer::peer::{DefaultBsPeer, ResolverExt as _};
+use dioxus::logger::tracing;
+use dioxus::prelude::*;
+use multicid::Vlad;
+use provenance_log::Log;
+use vaiber_node::{mnemonic, plog, wallet};
+use zeroize::Zeroizing;
+
+/// How many words the quiz asks back.
+const QUIZ_LEN: usize = 3;
+
+/// The VLAD whose Plog a wallet recovered from words, or made to be recovered by guardians,
+/// still has to be attached to, as stored by [vaiber_node::plog::set_pending_attach].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PendingAttach(pub Vlad);
+
+/// Shows freshly created recovery `words`, then asks a few of them back before `on_done`.
+#[component]
+pub(crate) fn MnemonicBackup(words: Zeroizing<Vec<String>>, on_done: EventHandler) -> Element {
+    let word_count = words.len();
+    let positions = use_hook(move || mnemonic::quiz_positions(word_count, QUIZ_LEN));
+    let mut quizzing = use_signal(|| false);
+    let mut answers = use_signal(|| vec![String::new(); QUIZ_LEN]);
+    let mut error = use_signal(|| None::<String>);
+
+    let check_positions = positions.clone();
+    let check_words = words.clone();
+    let handle_check = move |_| {
+        let correct = check_positions
+            .iter()
+            .zip(answers.read().iter())
+            .all(|(&position, answer)| answer.trim().eq_ignore_ascii_case(&check_words[position]));
+        if correct {
+            answers.set(vec![String::new(); QUIZ_LEN]);
+            on_done.call(());
+        } else {
+            error.set(Some(
+                "That does not match, check the words you wrote down.".to_string(),
+            ));
+        }
+    };
+
+    rsx! {
+        div {
+            class: "fixed inset-0 z-50 flex items-center justify-center bg-black/50",
+            div {
+                class: "max-w-lg w-full m-4 p-6 bg-white rounded-lg shadow-md flex flex-col gap-4",
+                h2 { class: "text-2xl font-bold", "Recovery words" }
+                if !quizzing() {
+                    p {
+                        class: "text-sm text-gray-600",
+                        "Write these words down in order and keep them offline. They bring back this wallet without your username and password, so anyone who sees them can use it. They are only shown once."
+                    }
+                    ol {
+                        class: "grid grid-cols-3 gap-2 font-mono text-sm",
+                        for (index, word) in words.iter().enumerate() {
+                            li {
+                                key: "{index}",
+                                class: "px-2 py-1 bg-gray-100 rounded",
+                                span { class: "text-gray-400 mr-1", "{index + 1}." }
+                                "{word}"
+                            }
+                        }
+                    }
+                    button {
+                        class: "py-2 px-4 rounded-md bg-green-500 text-white hover:bg-green-600 transition",
+                        r#type: "button",
+                        onclick: move |_| quizzing.set(true),
+                        "I wrote them down"
+                    }
+                } else {
+                    p {
+                        class: "text-sm text-gray-600",
+                        "Enter these words from your list to confirm."
+                    }
+                    for (slot, position) in positions.iter().copied().enumerate() {
+                        label {
+                            key: "{position}",
+                            class: "flex items-center gap-2 text-sm",
+                            span { class: "w-20 text-gray-700", "Word {position + 1}" }
+                            input {
+                                class: "flex-grow px-3 py-2 border border-gray-300 rounded-md font-mono",
+                                r#type: "text",
+                                autocomplete: "off",
+                                value: answers.read()[slot].clone(),
+                                oninput: move |e| answers.write()[slot] = e.value(),
+                            }
+                        }
+                    }
+                    div {
+                        class: "flex gap-2 justify-end",
+                        button {
+                            class: "px-3 py-2 border rounded hover:bg-gray-100",
+                            r#type: "button",
+                            onclick: move |_| {
+                                error.set(None);
+                                quizzing.set(false);
+                            },
+                            "Show the words again"
+                        }
+                        button {
+                            class: "px-3 py-2 bg-green-500 hover:bg-green-600 text-white rounded font-bold transition",
+                            r#type: "button",
+                            onclick: handle_check,
+                            "Confirm"
+                        }
+                    }
+                    if let Some(err) = error() {
+                        p { class: "text-red-500 text-xs", "{err}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rebuilds a wallet from its recovery words under a new username and password, for the Create
+/// Wallet screen.
+///
+/// Once recovered, the wallet is unlocked, `on_recovered` gets its encrypted seed, and the VLAD
+/// is left pending for [AttachVlad], in storage too so it survives a restart.
+#[component]
+pub(crate) fn RecoverFromWords(on_recovered: EventHandler<Vec<u8>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let mut key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut pending_attach = use_context::<Signal<Option<PendingAttach>>>();
+
+    let mut words = use_signal(String::new);
+    let mut vlad_input = use_signal(String::new);
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut recovering = use_signal(|| false);
+
+    let handle_recover = move |_| {
+        let Ok(vlad) = Vlad::try_from_str(vlad_input().trim()) else {
+            error.set(Some("VLAD invalid".to_string()));
+            return;
+        };
+        let storage = storage.clone();
+        error.set(None);
+        recovering.set(true);
+        spawn(async move {
+            let (typed, name, pass, target) = (words(), username(), password(), vlad.clone());
+            let recovered = off_ui_thread(async move {
+                wallet::recover(&storage, &typed, &target, &name, &pass).await
+            })
+            .await;
+            match recovered {
+                Ok(wallet) => {
+                    words.set(String::new());
+                    match wallet.encrypted_seed() {
+                        Ok(seed) => on_recovered.call(seed),
+                        Err(e) => error.set(Some(format!("Error encrypting seed: {e}"))),
+                    }
+                    pending_attach.set(Some(PendingAttach(vlad)));
+                    // The plog picks the signing key's codec, see WalletKeys::sync
+                    match WalletKeys::new(wallet.seed(), KeyCodec::default()) {
+                        Ok(keys) => key_manager.set(Some(keys)),
+                        Err(e) => error.set(Some(e.to_string())),
+                    }
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            recovering.set(false);
+        });
+    };
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 mt-2 text-sm",
+            onsubmit: handle_recover,
+            textarea {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md font-mono",
+                rows: "3",
+                autocomplete: "off",
+                placeholder: "Recovery words, separated by spaces",
+                value: "{words}",
+                oninput: move |e| words.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md font-mono text-xs",
+                r#type: "text",
+                placeholder: "VLAD of your Plog",
+                value: "{vlad_input}",
+                oninput: move |e| vlad_input.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "text",
+                autocomplete: "username",
+                placeholder: "New username",
+                value: "{username}",
+                oninput: move |e| username.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "password",
+                autocomplete: "new-password",
+                placeholder: "New password",
+                value: "{password}",
+                oninput: move |e| password.set(e.value()),
+            }
+            button {
+                class: "w-full py-2 px-4 rounded-md bg-blue-500 text-white hover:bg-blue-600 disabled:bg-gray-400 transition",
+                r#type: "submit",
+                disabled: recovering() || words().trim().is_empty(),
+                if recovering() { "Recovering..." } else { "Recover Wallet" }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500 text-xs", "{err}" }
+            }
+        }
+    }
+}
+
+/// Attaches the Plog of a wallet recovered from words, once we are connected to a peer that
+/// can find it, or recovers it through its guardians for a wallet made for that.
+#[component]
+pub(crate) fn AttachVlad(bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let mut plog_signal = use_context::<Signal<Option<Log>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut pending_attach = use_context::<Signal<Option<PendingAttach>>>();
+
+    let mut status = use_signal(|| None::<String>);
+    let mut attaching = use_signal(|| false);
+
+    let Some(PendingAttach(vlad)) = pending_attach() else {
+        return rsx! {};
+    };
+
+    let attach_vlad = vlad.clone();
+    let handle_attach = move |_| {
+        let Some(keys) = key_manager.read().clone() else {
+            status.set(Some("Wallet is locked.".to_string()));
+            return;
+        };
+        let Some(mut peer) = bs_peer_signal.peek().clone() else {
+            status.set(Some("Peer not initialized".to_string()));
+            return;
+        };
+        let storage = storage.clone();
+        let vlad = attach_vlad.clone();
+        attaching.set(true);
+        status.set(Some("Looking up the VLAD...".to_string()));
+        spawn(async move {
+            match plog::attach(&mut peer, &storage, &keys, &vlad).await {
+                Ok(()) => {
+                    if let Some(plog) = peer.plog() {
+                        plog_signal.set(Some(plog.clone()));
+                    }
+                    bs_peer_signal.set(Some(peer.clone()));
+                    pending_attach.set(None);
+                    status.set(None);
+                    if let Err(e) = peer.record_plog_to_dht().await {
+                        tracing::error!("Failed to publish Plog records: {}", e);
+                    }
+                }
+                Err(e) => status.set(Some(e.to_string())),
+            }
+            attaching.set(false);
+        });
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 bg-blue-50 border border-blue-300 rounded-lg p-4 shadow-sm text-xs",
+            h4 { class: "text-lg font-semibold text-blue-800", "Attach your Plog" }
+            p {
+                class: "text-gray-600",
+                "This wallet is waiting for the Plog of "
+                span { class: "font-mono break-all", "{vlad}" }
+                ". Connect to a peer below. If the wallet was recovered from its words, fetch the Plog to sign for it again."
+            }
+            button {
+                class: "py-2 px-4 rounded-md bg-blue-500 text-white hover:bg-blue-600 disabled:bg-gray-400 transition",
+                r#type: "button",
+                disabled: attaching(),
+                onclick: handle_attach,
+                if attaching() { "Attaching..." } else { "Attach Plog" }
+            }
+            if let Some(msg) = status() {
+                p { class: "text-gray-700", "{msg}" }
+            }
+            RequestRecovery { bs_peer_signal, vlad: vlad.clone() }
+        }
+    }
+}
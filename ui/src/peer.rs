@@ -1,45 +1,77 @@
 //! Peer component once a Wallet is available.
 //!
-//! The logic creates a default plog if one does not exist yet.
-use crate::wallet::KeyMan;
+//! The logic creates a default plog if one does not exist yet. The peer only runs while the
+//! wallet is unlocked: locking unmounts it, which cancels its tasks and drops it.
+use crate::attachment::AttachmentView;
+use crate::mnemonic::{AttachVlad, PendingAttach};
+use crate::profile::{current_values, ProfileCard, ProfileEditor};
+use crate::recovery::{GuardiansEditor, Guarding, RecoveryInbox};
+use crate::staged::{Change, StagedChanges, StagedChangesPanel};
+use crate::wallet::{KeyCodec, KeyMan, WalletKeys};
 use crate::StorageProvider;
-use bs::params::anykey::PubkeyParams;
 use bs::update::OpParams;
-use bs_peer::peer::{DefaultBsPeer, Libp2pEvent, PublicEvent, ResolvedPlog, ResolverExt as _};
+use bs_peer::peer::{DefaultBsPeer, ResolverExt as _};
 use bs_peer::platform::StartConfig;
-use bs_peer::utils::create_default_scripts;
 use bs_peer::BsPeer;
 use dioxus::logger::tracing;
 use dioxus::prelude::*;
-use libp2p::futures::StreamExt as _;
 use libp2p::Multiaddr;
 use multicid::Vlad;
 use multikey::{Multikey, Views};
 use provenance_log::key::key_paths::ValidatedKeyParams as _;
-use provenance_log::resolver::Resolver;
-use provenance_log::{Key as ProvenanceKey, Log, Script};
+use provenance_log::{Key as ProvenanceKey, Log};
 use std::collections::HashMap;
 use std::num::NonZero;
 use std::path::PathBuf;
-
-const VLAD_STORAGE_KEY: &str = "VLAD_STORAGE_KEY";
+use vaiber_node::blobs::{self, BlobError};
+use vaiber_node::follow::{self, FollowList, FollowedPeer};
+use vaiber_node::prerotation::{self, RotationCheck};
+use vaiber_node::recovery;
+use vaiber_node::{events, plog, NodeUpdate};
 
 #[component]
 pub fn Peer(platform_content: Element, base_path: Option<PathBuf>) -> Element {
     let storage = use_context::<StorageProvider>();
-    let (lock_script, unlock_script) = create_default_scripts();
 
-    let key_manager = use_context::<Signal<Option<KeyMan>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut pending_attach = use_context::<Signal<Option<PendingAttach>>>();
     let mut bs_peer_signal = use_signal(|| None::<DefaultBsPeer<KeyMan>>);
     let mut plog_signal = use_signal(|| None::<Log>);
+    // Why the stored plog could not be loaded, if it couldn't
+    let mut plog_error = use_signal(|| None::<String>);
     let mut peer_address = use_signal(|| None::<String>);
     let mut connected_peers = use_signal(Vec::<String>::new);
-    let mut ack_list = use_signal(Vec::<Vlad>::new);
-    let mut peer_list = use_signal(HashMap::<Vlad, Option<ResolvedPlog>>::new);
+    let mut peer_list = use_signal(FollowList::new);
+    // The follow list as last written to storage, None until it has been loaded
+    let mut stored_follow_list = use_signal(|| None::<FollowList>);
+    let staged = use_signal(StagedChanges::default);
+    let mut recovery_inbox = use_signal(RecoveryInbox::new);
 
     use_context_provider(move || peer_list);
     use_context_provider(|| connected_peers);
     use_context_provider(|| plog_signal);
+    use_context_provider(|| bs_peer_signal);
+    use_context_provider(|| staged);
+    use_context_provider(|| recovery_inbox);
+
+    // Persist changes to the follow list, once the stored one has been loaded
+    let follow_storage = storage.clone();
+    use_effect(move || {
+        let list = peer_list.read().clone();
+        let Some(previous) = stored_follow_list.peek().clone() else {
+            return;
+        };
+        if previous == list {
+            return;
+        }
+        stored_follow_list.set(Some(list.clone()));
+        let storage = follow_storage.clone();
+        spawn(async move {
+            if let Err(e) = follow::save_changes(&storage, &previous, &list).await {
+                tracing::error!("Failed to save follow list: {}", e);
+            }
+        });
+    });
 
     if key_manager().is_none() {
         return rsx! {
@@ -50,20 +82,23 @@ pub fn Peer(platform_content: Element, base_path: Option<PathBuf>) -> Element {
         };
     }
 
-    let km = key_manager.read().clone().unwrap();
-    let unlock_script_clone = unlock_script.clone();
-    let lock_script_clone = lock_script.clone();
+    let keys = key_manager.read().clone().unwrap();
     let storage_clone = storage.clone();
     let base_path_clone = base_path.clone();
     let bs_peer_resource = use_resource(move || {
-        let km = km.clone();
+        let keys = keys.clone();
         let storage = storage_clone.clone();
-        let lock_clone = lock_script_clone.clone();
-        let unlock_clone = unlock_script_clone.clone();
         let bath_path_clone = base_path_clone.clone();
         async move {
+            let key_manager = match keys.key_manager() {
+                Ok(key_manager) => key_manager,
+                Err(e) => {
+                    plog_error.set(Some(e.to_string()));
+                    return;
+                }
+            };
             let mut peer = BsPeer::new(
-                km,
+                key_manager,
                 StartConfig {
                     base_path: bath_path_clone,
                     ..Default::default()
@@ -72,191 +107,69 @@ pub fn Peer(platform_content: Element, base_path: Option<PathBuf>) -> Element {
             .await
             .unwrap();
 
-            let plog_loaded = if storage.exists(VLAD_STORAGE_KEY) && !cfg!(feature = "dev") {
-                tracing::info!("Loading existing Plog from storage...");
-                if let Ok(plog_data) = storage.load(VLAD_STORAGE_KEY) {
-                    tracing::info!("Plog loaded from storage successfully.");
-                    let plog = Log::try_from(plog_data.as_slice()).unwrap();
-                    peer.load(plog).await.is_ok()
-                } else {
-                    false
-                }
-            } else {
-                false
-            };
-
-            if !plog_loaded {
-                peer.generate(&lock_clone, &unlock_clone.clone())
-                    .await
-                    .unwrap_or_else(|e| tracing::error!("Failed to generate Plog: {}", e));
-                if let Some(plog_data) = peer.plog() {
-                    let plog_bytes: Vec<u8> = plog_data.into();
-                    storage
-                        .save(VLAD_STORAGE_KEY, &plog_bytes)
-                        .unwrap_or_else(|e| {
-                            tracing::error!("Failed to save Plog to storage: {}", e);
-                        });
-                };
+            // A wallet recovered from words gets no new plog, it attaches to its old one
+            if let Err(e) = plog::load_or_generate(&mut peer, &storage).await {
+                tracing::error!("{}", e);
+                plog_error.set(Some(e.to_string()));
             }
 
             if let Some(plog) = peer.plog() {
+                // Find the rotated key we sign with now
+                if let Err(e) = keys.sync(&plog) {
+                    tracing::error!("{}", e);
+                }
                 plog_signal.set(Some(plog.clone()));
             } else {
-                tracing::error!("Plog is not initialized.");
+                match plog::pending_attach(&storage).await {
+                    Ok(Some(vlad)) => pending_attach.set(Some(PendingAttach(vlad))),
+                    Ok(None) => tracing::error!("Plog is not initialized."),
+                    Err(e) => plog_error.set(Some(e.to_string())),
+                }
             }
 
-            let peer_clone = peer.clone();
-            let update_dht = move || {
-                let mut peer_clone_inner = peer_clone.clone();
-                async move {
-                    if let Err(e) = peer_clone_inner.record_plog_to_dht().await {
-                        tracing::error!("Failed to publish Plog records: {}", e);
-                    } else {
-                        tracing::info!("Plog records published to DHT successfully.");
+            let follow_list = follow::load_follow_list(&storage).await;
+            stored_follow_list.set(Some(follow_list.clone()));
+            peer_list.set(follow_list);
+
+            let on_update = move |update| match update {
+                NodeUpdate::Listening(address) => peer_address.set(Some(address)),
+                NodeUpdate::Connected(peer) => connected_peers.write().push(peer),
+                NodeUpdate::Disconnected(peer) => connected_peers.write().retain(|p| p != &peer),
+                NodeUpdate::Resolved(vlad, followed) => {
+                    peer_list.with_mut(|map| map.insert(vlad, followed));
+                }
+                NodeUpdate::Recovery(vlad, message) => {
+                    let received = (vlad, message);
+                    if !recovery_inbox.peek().contains(&received) {
+                        recovery_inbox.write().push(received);
                     }
                 }
             };
 
-            let mut peer_events = peer.events.take().unwrap();
-            let peer_clone = peer.clone();
-            spawn(async move {
-                while let Some(event) = peer_events.next().await {
-                    match event {
-                        PublicEvent::ListenAddr { address, .. } => {
-                            tracing::info!("Peer listening on: {}", address);
-                            peer_address.set(Some(address.to_string()));
-                        }
-                        PublicEvent::NewConnection { peer } => {
-                            tracing::info!("New connection established with peer: {}", peer);
-                            connected_peers.write().push(peer.to_string());
-                            update_dht().await;
-                        }
-                        PublicEvent::ConnectionClosed { peer, cause } => {
-                            tracing::info!(
-                                "Connection closed with peer: {}, cause: {:?}",
-                                peer,
-                                cause
-                            );
-                            connected_peers.write().retain(|p| p != &peer.to_string());
-                        }
-                        PublicEvent::Message { topic, data, .. } => {
-                            tracing::info!("Received message topic: {}, data: {:?}", topic, data);
-                            if let Ok(vlad) = Vlad::try_from_str(&topic) {
-                                if ack_list.read().contains(&vlad) {
-                                    return;
-                                }
-                                ack_list.write().push(vlad.clone());
+            let peer_events = peer.events.take().unwrap();
+            spawn(events::run(
+                peer.clone(),
+                peer_events,
+                move || peer_list.peek().clone(),
+                on_update,
+            ));
 
-                                if let Some(network_client) = peer_clone.network_client.as_ref() {
-                                    let Ok(head) = multicid::Cid::try_from(data.as_slice()) else {
-                                        tracing::warn!("Invalid VLAD: {}", vlad);
-                                        return;
-                                    };
-                                    // Retry logic with exponential backoff
-                                    let mut retries = 0;
-                                    loop {
-                                        match network_client.resolve_plog(&head).await {
-                                            Ok(plog) => {
-                                                tracing::info!("Resolved plog from PubSub for VLAD: {}", vlad);
-                                                peer_list.with_mut(|map| {
-                                                    map.insert(vlad.clone(), Some(plog))
-                                                });
-                                                break;
-                                            }
-                                            Err(e) => {
-                                                tracing::error!("Failed to resolve plog from PubSub for VLAD {}: {}", vlad, e);
-                                                if retries >= 3 {
-                                                    tracing::error!("Failed to resolve plog after 3 retries: {}", e);
-                                                    break;
-                                                }
-                                                retries += 1;
-                                                tokio::time::sleep(std::time::Duration::from_secs(
-                                                    2u64.pow(retries),
-                                                ))
-                                                .await;
-                                            }
-                                        }
-                                    }
-                                    // Send ACK back to the sender
-                                    let ack_topic = format!("ack/{}", topic);
-                                    if let Err(e) =
-                                        network_client.publish(data.clone(), ack_topic).await
-                                    {
-                                        tracing::error!("Failed to publish ACK: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                        PublicEvent::Swarm(Libp2pEvent::PutRecordRequest { source }) => {
-                            tracing::info!("Received PutRecordRequest from: {}", source);
-                            if let Some(network_client) = peer_clone.network_client.as_ref() {
-                                let peer_list_clone = peer_list.read().clone();
-                                for (vlad, plog) in peer_list_clone.iter() {
-                                    if plog.is_some() {
-                                        continue;
-                                    }
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    tokio::time::sleep(std::time::Duration::from_secs(4)).await;
-                                    let vlad_bytes: Vec<u8> = vlad.clone().into();
-                                    let cid_bytes = {
-                                        let mut retries = 0;
-                                        loop {
-                                            match network_client
-                                                .get_record(vlad_bytes.clone())
-                                                .await
-                                            {
-                                                Ok(bytes) => break Ok(bytes),
-                                                Err(e) => {
-                                                    if retries >= 3 {
-                                                        break Err(e);
-                                                    }
-                                                    retries += 1;
-                                                    tokio::time::sleep(
-                                                        std::time::Duration::from_secs(
-                                                            2u64.pow(retries),
-                                                        ),
-                                                    )
-                                                    .await;
-                                                }
-                                            }
-                                        }
-                                    };
-                                    let Ok(cid_bytes) = cid_bytes else {
-                                        continue;
-                                    };
-                                    let Ok(head) = multicid::Cid::try_from(cid_bytes.as_slice())
-                                    else {
-                                        continue;
-                                    };
-                                    let Ok(_head_bytes) = network_client.resolve(&head).await
-                                    else {
-                                        continue;
-                                    };
-                                    let vlad_clone = vlad.clone();
-                                    let network_client_clone = network_client.clone();
-                                    spawn(async move {
-                                        if let Ok(resolved_plog) =
-                                            network_client_clone.resolve_plog(&head).await
-                                        {
-                                            peer_list.with_mut(|map| {
-                                                map.insert(vlad_clone.clone(), Some(resolved_plog))
-                                            });
-                                        }
-                                    });
-                                }
-                            }
-                        }
-                        PublicEvent::Ack { topic, .. } => {
-                            tracing::info!("Received ACK for topic: {}", topic);
-                            if let Ok(vlad) = Vlad::try_from_str(&topic) {
-                                ack_list.write().retain(|v| v != &vlad);
-                            }
-                        }
-                        _ => {
-                            tracing::debug!("Received event: {:?}", event);
-                        }
-                    }
-                }
+            // Guardians offer and approve on our own recovery topic
+            if let Some(own) = peer.plog().map(|plog| plog.vlad.clone()) {
+                events::subscribe_recovery(&peer, &own).await;
+            }
+
+            // Re-subscribe to everyone we follow and refresh their heads in the background
+            let refresh_peer = peer.clone();
+            let followed: Vec<Vlad> = peer_list.peek().keys().cloned().collect();
+            spawn(async move {
+                events::refresh_followed(
+                    &refresh_peer,
+                    followed,
+                    |vlad| peer_list.peek().get(vlad)?.head.clone(),
+                    on_update,
+                )
+                .await;
             });
 
             bs_peer_signal.set(Some(peer));
@@ -276,7 +189,7 @@ pub fn Peer(platform_content: Element, base_path: Option<PathBuf>) -> Element {
                                                 MyPlogSection {
                             bs_peer_signal: bs_peer_signal,
                             plog_signal: plog_signal,
-                            unlock_script: unlock_script.clone(),
+                            plog_error: plog_error(),
                             peer_address: peer_address()
                         }
                     }
@@ -309,18 +222,29 @@ pub fn Peer(platform_content: Element, base_path: Option<PathBuf>) -> Element {
 fn MyPlogSection(
     bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>,
     plog_signal: Signal<Option<Log>>,
-    unlock_script: String,
+    plog_error: Option<String>,
     peer_address: Option<String>,
 ) -> Element {
     rsx! {
         div {
             class: "flex flex-col gap-6 bg-white border border-green-100 rounded-lg p-6 shadow-sm",
             h2 { class: "text-2xl font-bold text-green-800 mb-2", "My Plog Details" }
+            if let Some(err) = plog_error {
+                div { class: "p-2 bg-red-100 text-red-800 rounded text-xs", "{err}" }
+            } else if plog_signal.read().is_none() {
+                AttachVlad { bs_peer_signal }
+            }
             PlogControls { peer: bs_peer_signal }
-            AddOperationForm {
-                bs_peer_signal: bs_peer_signal,
-                unlock_script: unlock_script,
+            ProfileEditor {}
+            details {
+                summary { class: "cursor-pointer text-sm text-gray-600", "Guardians" }
+                GuardiansEditor { bs_peer_signal }
+            }
+            details {
+                summary { class: "cursor-pointer text-sm text-gray-600", "Advanced: raw keys" }
+                AddOperationForm {}
             }
+            StagedChangesPanel { bs_peer_signal }
             if let Some(addr) = peer_address {
                 div {
                     class: "mt-2 text-xs text-center",
@@ -357,6 +281,7 @@ fn PeerListSection(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
             class: "flex flex-col gap-6 bg-white border border-gray-100 rounded-lg p-6 shadow-sm",
             h2 { class: "text-2xl font-bold text-gray-800 mb-2", "Tracked Peers" }
             PeerList { peer }
+            Guarding { peer }
         }
     }
 }
@@ -366,6 +291,7 @@ fn PeerListSection(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
 #[component]
 pub fn PlogControls(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
     let plog_signal = use_context::<Signal<Option<provenance_log::Log>>>();
+    let mut staged = use_context::<Signal<StagedChanges>>();
 
     let vlad_resource = use_resource({
         move || async move {
@@ -377,6 +303,9 @@ pub fn PlogControls(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
         }
     });
 
+    // Stages deleting the key, to be signed with the other staged changes
+    let handle_remove = move |key: ProvenanceKey| staged.write().stage(&key, Change::Delete);
+
     rsx! {
         div {
             class: "flex flex-col gap-4 bg-white border border-green-100 rounded-lg p-4 shadow-sm",
@@ -390,7 +319,7 @@ pub fn PlogControls(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
                 }
             }
             if let Some(plog) = plog_signal.read().as_ref() {
-                PlogDisplay { plog: plog.clone() }
+                PlogDisplay { plog: plog.clone(), on_remove: handle_remove }
             } else {
                 p { class: "italic text-gray-400", "Your Plog is empty." }
             }
@@ -401,122 +330,88 @@ pub fn PlogControls(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
 // === SECTION: Add Operation ===
 
 #[component]
-fn AddOperationForm(
-    bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>,
-    unlock_script: String,
-) -> Element {
-    let storage = use_context::<StorageProvider>();
-    let mut plog_signal = use_context::<Signal<Option<Log>>>();
-    let key_manager = use_context::<Signal<Option<KeyMan>>>();
+fn AddOperationForm() -> Element {
+    let plog_signal = use_context::<Signal<Option<Log>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut staged = use_context::<Signal<StagedChanges>>();
 
     let mut key = use_signal(String::new);
     let mut value = use_signal(String::new);
-    let mut rotate_key = use_signal(|| false);
+    // A file to attach as the value instead, with its name
+    let mut attachment = use_signal(|| None::<(String, Vec<u8>)>);
     let mut error = use_signal(|| None::<String>);
-    let mut submitting = use_signal(|| false);
+    // The algorithm of the key to rotate to, the current one's by default
+    let mut rotate_codec = use_signal(|| {
+        key_manager
+            .peek()
+            .as_ref()
+            .map(WalletKeys::signing_codec)
+            .unwrap_or_default()
+    });
 
-    let handle_submit = move |_| {
-        // e.prevent_default();
-        submitting.set(true);
+    let parse_key = move || {
         let k = key().trim().to_string();
-        let v = value().trim().to_string();
-        if k.is_empty() || v.is_empty() {
-            error.set(Some("Key and Value cannot be empty.".into()));
-            submitting.set(false);
-            return;
+        if k.is_empty() {
+            return Err("Key cannot be empty.".to_string());
         }
-        error.set(None);
-
-        let mut additional_ops = vec![];
-        additional_ops.push(OpParams::UseStr {
-            key: ProvenanceKey::try_from(k.clone()).unwrap_or_default(),
-            s: v.clone(),
-        });
-
-        let should_rotate_key = *rotate_key.read();
-        let mut new_secret_key: Option<multikey::Multikey> = None;
+        ProvenanceKey::try_from(k.clone()).map_err(|e| format!("Invalid key {k}: {e}"))
+    };
 
-        if should_rotate_key {
-            // Generate a new secret key
-            let generated_key = KeyMan::generate_key(&multicodec::Codec::Ed25519Priv).unwrap();
-            let public_key = generated_key.conv_view().unwrap().to_public_key().unwrap();
-            new_secret_key = Some(generated_key);
+    let handle_stage = move |_| {
+        let op_key = match parse_key() {
+            Ok(op_key) => op_key,
+            Err(e) => {
+                error.set(Some(e));
+                return;
+            }
+        };
+        let v = value().trim().to_string();
+        let change = match attachment() {
+            Some((name, data)) => Change::File(name, data),
+            None if v.is_empty() => {
+                error.set(Some("Key and Value cannot be empty.".into()));
+                return;
+            }
+            None => Change::Str(v),
+        };
+        error.set(None);
+        staged.write().stage(&op_key, change);
+        key.set(String::new());
+        value.set(String::new());
+        attachment.set(None);
+    };
 
-            // Add an operation to update the /pubkey in the Plog with the new public key
-            additional_ops.push(OpParams::UseKey {
-                key: PubkeyParams::KEY_PATH.into(),
-                mk: public_key,
-            });
+    let handle_delete = move |_| {
+        let op_key = match parse_key() {
+            Ok(op_key) => op_key,
+            Err(e) => {
+                error.set(Some(e));
+                return;
+            }
+        };
+        if !is_removable(&op_key) {
+            error.set(Some(format!("{op_key} cannot be removed.")));
+            return;
         }
-
-        if let Some(peer) = bs_peer_signal.read().as_ref() {
-            let mut peer_clone = peer.clone();
-            let storage = storage.clone();
-            let unlock_script = unlock_script.clone();
-            let km_clone = key_manager.clone();
-            let mut key = key;
-            let mut value = value;
-            let mut submitting = submitting;
-            spawn(async move {
-                let update_cfg = bs::update::Config::builder()
-                    .unlock(Script::Code(provenance_log::Key::default(), unlock_script))
-                    .entry_signing_key(PubkeyParams::KEY_PATH.into())
-                    .additional_ops(additional_ops)
-                    .build();
-
-                if let Err(e) = peer_clone.update(update_cfg).await {
-                    tracing::error!("Failed to update plog: {}", e); // TODO: Need to show this to the user.
-                } else {
-                    // If key rotation was requested, update the key manager's mapping
-                    if should_rotate_key {
-                        if let Some(sk) = new_secret_key {
-                            let new_fingerprint = sk
-                                .fingerprint_view()
-                                .unwrap()
-                                .fingerprint(multicodec::Codec::Sha2256)
-                                .unwrap();
-                            km_clone
-                                .read()
-                                .as_ref()
-                                .unwrap()
-                                .store_secret_key(PubkeyParams::KEY_PATH.into(), sk)
-                                .unwrap();
-                            km_clone
-                                .read()
-                                .as_ref()
-                                .unwrap()
-                                .update_path_mapping(
-                                    PubkeyParams::KEY_PATH.into(),
-                                    new_fingerprint.into(),
-                                )
-                                .unwrap();
-                        }
-                    }
-                    bs_peer_signal.set(Some(peer_clone.clone()));
-                }
-                if let Some(ref plog) = peer_clone.plog() {
-                    let plog_bytes: Vec<u8> = plog.clone().into();
-                    if let Err(e) = storage.save(VLAD_STORAGE_KEY, &plog_bytes) {
-                        tracing::error!("Failed to save Plog to storage: {}", e);
-                    } else {
-                        plog_signal.set(Some(plog.clone()));
-                    }
-                }
-                submitting.set(false);
-                key.set(String::new());
-                value.set(String::new());
-            });
-        } else {
-            error.set(Some("Peer is not initialized.".to_string()));
-            submitting.set(false);
+        let is_set = plog_signal
+            .read()
+            .as_ref()
+            .is_some_and(|plog| current_values(plog).contains_key(&op_key.to_string()));
+        if !is_set {
+            error.set(Some(format!("{op_key} is not set.")));
+            return;
         }
+        error.set(None);
+        staged.write().stage(&op_key, Change::Delete);
+        key.set(String::new());
+        value.set(String::new());
     };
 
     rsx! {
         form {
             class: "flex flex-col gap-2 bg-white border border-green-200 rounded-lg p-4 shadow-sm",
-            onsubmit: handle_submit,
-            h4 { class: "text-lg font-semibold text-green-700", "Add Operation" }
+            onsubmit: handle_stage,
+            h4 { class: "text-lg font-semibold text-green-700", "Stage Operation" }
             div {
                 class: "flex gap-2 w-full",
                 input {
@@ -531,23 +426,67 @@ fn AddOperationForm(
                             input.insert(0, '/');
                         }
                         input = input.replace(" ", "/");
-                        input = input.replace(|c: char| !c.is_alphanumeric() && c != '/', "/");
+                        input = input
+                            .replace(|c: char| !c.is_alphanumeric() && !"/-_.".contains(c), "/");
                         key.set(input.clone());
                     },
                 }
                 input {
                     class: "flex-1 p-2 border rounded focus:outline-none focus:ring-2 focus:ring-green-400 font-mono text-xs",
-                    placeholder: "Value",
+                    placeholder: if attachment.read().is_some() { "Attached file" } else { "Value" },
                     name: "value",
                     value: "{value}",
                     autocomplete: "off",
+                    disabled: attachment.read().is_some(),
                     oninput: move |e| value.set(e.value().clone()),
                 }
                 button {
                     class: "px-3 py-2 bg-green-500 hover:bg-green-600 text-white rounded font-bold transition",
                     r#type: "submit",
-                    disabled: *submitting.read(),
-                    if *submitting.read() { "Adding..." } else { "Add" }
+                    "Stage"
+                }
+                button {
+                    class: "px-3 py-2 border border-red-300 text-red-600 hover:bg-red-50 rounded transition",
+                    r#type: "button",
+                    onclick: handle_delete,
+                    "Stage delete"
+                }
+            }
+            div {
+                class: "flex items-center gap-2 text-xs",
+                label { r#for: "attachment", class: "text-gray-700", "Attach file" }
+                input {
+                    r#type: "file",
+                    id: "attachment",
+                    class: "text-xs",
+                    onchange: move |e| async move {
+                        let Some(file_engine) = e.files() else {
+                            return;
+                        };
+                        let Some(name) = file_engine.files().into_iter().next() else {
+                            attachment.set(None);
+                            return;
+                        };
+                        match file_engine.read_file(&name).await {
+                            Some(data) if data.len() > blobs::INLINE_LIMIT => {
+                                error.set(Some(BlobError::TooLarge(data.len()).to_string()))
+                            }
+                            Some(data) => attachment.set(Some((name, data))),
+                            None => error.set(Some(format!("Failed to read {name}"))),
+                        }
+                    },
+                }
+                if let Some((name, data)) = attachment() {
+                    span {
+                        class: "font-mono text-gray-600 truncate",
+                        {attachment_label(&name, data.len())}
+                    }
+                    button {
+                        class: "px-2 border rounded hover:bg-gray-100",
+                        r#type: "button",
+                        onclick: move |_| attachment.set(None),
+                        "Clear"
+                    }
                 }
             }
             div {
@@ -555,15 +494,38 @@ fn AddOperationForm(
                 input {
                     r#type: "checkbox",
                     id: "rotate_key",
-                    checked: *rotate_key.read(),
-                    oninput: move |e| rotate_key.set(e.value().parse().unwrap_or(false)),
+                    checked: staged.read().rotate_key().is_some(),
+                    oninput: move |e| {
+                        let rotate = e.value().parse().unwrap_or(false);
+                        staged.write().set_rotate_key(rotate.then_some(rotate_codec()));
+                    },
                     class: "form-checkbox h-4 w-4 text-green-600 transition duration-150 ease-in-out bg-neutral-300",
                 }
                 label {
                     r#for: "rotate_key",
                     class: "ml-2 block text-sm text-gray-900",
-                    "Rotate Key"
+                    "Rotate to the pre-committed key"
                 }
+                // The key rotated to is whatever was committed to, so the choice only
+                // applies to the next commitment
+                span { class: "ml-2 text-xs text-gray-600", "and commit to a new" }
+                select {
+                    class: "ml-1 p-1 border rounded text-xs font-mono",
+                    title: "The algorithm of the key committed to for the rotation after this one, not of the key rotated to now",
+                    onchange: move |e| {
+                        let Ok(codec) = e.value().parse() else {
+                            return;
+                        };
+                        rotate_codec.set(codec);
+                        if staged.read().rotate_key().is_some() {
+                            staged.write().set_rotate_key(Some(codec));
+                        }
+                    },
+                    for &codec in KeyCodec::available() {
+                        option { value: "{codec}", selected: codec == rotate_codec(), "{codec}" }
+                    }
+                }
+                span { class: "ml-1 text-xs text-gray-600", "key" }
             }
             if let Some(err) = error() {
                 p { class: "text-red-500 text-xs mt-1", "{err}" }
@@ -572,6 +534,38 @@ fn AddOperationForm(
     }
 }
 
+/// Adds an entry with `ops` to our Plog, then shows and republishes the updated Plog.
+///
+/// On error nothing has changed, so the caller can keep the ops to try again.
+pub(crate) async fn commit_ops(
+    mut bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>,
+    mut plog_signal: Signal<Option<Log>>,
+    storage: &StorageProvider,
+    keys: &WalletKeys,
+    ops: Vec<OpParams>,
+    rotate_key: Option<KeyCodec>,
+) -> Result<(), plog::UpdateError> {
+    let Some(mut peer) = bs_peer_signal.peek().clone() else {
+        return Err(plog::UpdateError::NotInitialized);
+    };
+    plog::update(&mut peer, storage, keys, ops, rotate_key).await?;
+    if let Some(plog) = peer.plog() {
+        plog_signal.set(Some(plog.clone()));
+    }
+    bs_peer_signal.set(Some(peer.clone()));
+    if let Err(e) = peer.record_plog_to_dht().await {
+        tracing::error!("Failed to publish Plog records: {}", e);
+    }
+    Ok(())
+}
+
+/// The attached file's name and size.
+pub(crate) fn attachment_label(path: &str, len: usize) -> String {
+    // Desktop gives full paths, the web only file names
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    format!("{name} ({len} bytes)")
+}
+
 // === SECTION: ConnectionsPanel ===
 
 #[derive(Clone, Debug, PartialEq)]
@@ -737,7 +731,7 @@ fn PeerList(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
     let mut peer_vlad_input = use_signal(String::new);
     let mut searching = use_signal(|| false);
     let mut search_status = use_signal(|| None::<String>);
-    let mut peer_list = use_context::<Signal<HashMap<Vlad, Option<ResolvedPlog>>>>();
+    let mut peer_list = use_context::<Signal<FollowList>>();
 
     let peer_clone = peer;
     let handle_add_peer = move |_| {
@@ -759,83 +753,37 @@ fn PeerList(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
             searching.set(false);
             return;
         }
-        peer_list.with_mut(|map| map.insert(vlad_ty.clone(), None));
-        let vlad_bytes: Vec<u8> = vlad_ty.clone().into();
+        peer_list.with_mut(|map| map.insert(vlad_ty.clone(), FollowedPeer::default()));
 
         let peer = peer_clone;
         spawn(async move {
-            let network_client = {
-                let peer_guard = peer.read();
-                let Some(peer) = peer_guard.as_ref() else {
-                    search_status.set(Some("Peer not initialized".to_string()));
-                    searching.set(false);
-                    return;
-                };
-                let Some(network_client) = peer.network_client.clone() else {
-                    search_status.set(Some(
-                        "Search incomplete, Network client not initialized".to_string(),
-                    ));
-                    searching.set(false);
-                    return;
-                };
-                network_client
+            let Some(bs_peer) = peer.peek().clone() else {
+                search_status.set(Some("Peer not initialized".to_string()));
+                searching.set(false);
+                return;
             };
-            let Ok(vlad) = Vlad::try_from(vlad_bytes.as_slice()) else {
-                search_status.set(Some(format!(
-                    "Could not convert VLAD bytes to Vlad type: {}",
-                    vlad
-                )));
+            let Some(network_client) = bs_peer.network_client.clone() else {
+                search_status.set(Some(
+                    "Search incomplete, Network client not initialized".to_string(),
+                ));
                 searching.set(false);
                 return;
             };
-            if let Err(e) = network_client.subscribe(vlad.to_string()).await {
+            if let Err(e) = network_client.subscribe(vlad_ty.to_string()).await {
                 search_status.set(Some(format!(
                     "Failed to subscribe to VLAD: {}, Error: {}",
-                    vlad, e
+                    vlad_ty, e
                 )));
                 searching.set(false);
                 return;
             }
-            let cid_bytes = {
-                let mut retries = 0;
-                loop {
-                    match network_client.get_record(vlad_bytes.clone()).await {
-                        Ok(bytes) => break Ok(bytes),
-                        Err(e) => {
-                            if retries >= 5 {
-                                // Increased retries for DHT propagation
-                                tracing::error!("Failed to get record after 5 retries: {}", e);
-                                break Err(e);
-                            }
-                            retries += 1;
-                            tokio::time::sleep(std::time::Duration::from_secs(2u64.pow(retries)))
-                                .await;
-                        }
-                    }
-                }
-            };
-            let Ok(cid_bytes) = cid_bytes else {
-                search_status.set(Some(format!("Could not find peer with VLAD: {}", vlad)));
+            events::subscribe_recovery(&bs_peer, &vlad_ty).await;
+            let Some(plog) = events::resolve_plog(&bs_peer, &vlad_ty).await else {
+                search_status.set(Some(format!("Could not find peer with VLAD: {}", vlad_ty)));
                 searching.set(false);
                 return;
             };
-            let head = match multicid::Cid::try_from(cid_bytes.as_slice()) {
-                Ok(head) => head,
-                Err(e) => {
-                    search_status.set(Some(format!(
-                        "Could not get VLAD: {}, Failed to resolve plog: {}",
-                        vlad, e
-                    )));
-                    searching.set(false);
-                    return;
-                }
-            };
-            let Ok(rebuilt_plog) = network_client.resolve_plog(&head).await else {
-                search_status.set(Some(format!("Could not convert head of VLAD: {}", vlad)));
-                searching.set(false);
-                return;
-            };
-            peer_list.with_mut(|map| map.insert(vlad_ty, Some(rebuilt_plog)));
+            peer_list.with_mut(|map| map.insert(vlad_ty, FollowedPeer::resolved(plog)));
             search_status.set(None);
             searching.set(false);
             peer_vlad_input.set("".to_string());
@@ -887,11 +835,11 @@ fn PeerList(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
 }
 
 #[component]
-fn PeerItems(peers: HashMap<Vlad, Option<ResolvedPlog>>) -> Element {
+fn PeerItems(peers: FollowList) -> Element {
     rsx! {
         ul {
             class: "list-none flex flex-col gap-2",
-            for (index, (vlad, maybe_plog)) in peers.iter().enumerate() {
+            for (index, (vlad, followed)) in peers.iter().enumerate() {
                 li {
                     key: "{index}",
                     div {
@@ -909,11 +857,15 @@ fn PeerItems(peers: HashMap<Vlad, Option<ResolvedPlog>>) -> Element {
                                 "Copy"
                             }
                         }
-                        if let Some(plog) = maybe_plog {
+                        if let Some(plog) = &followed.plog {
                             div {
                                 class: "border-t pt-2 flex flex-col gap-1",
                                 h4 { class: "font-semibold text-xs text-green-800", "Plog Details" }
-                                PlogDisplay { plog: plog.log.clone() }
+                                if let Some(ago) = followed.resolved_ago() {
+                                    span { class: "text-xs text-gray-500", "Last resolved {ago}" }
+                                }
+                                ProfileCard { plog: plog.clone() }
+                                PlogDisplay { plog: plog.clone() }
                             }
                         } else {
                             div {
@@ -929,7 +881,37 @@ fn PeerItems(peers: HashMap<Vlad, Option<ResolvedPlog>>) -> Element {
 }
 
 #[component]
-fn PlogDisplay(plog: provenance_log::Log) -> Element {
+fn PlogDisplay(
+    plog: provenance_log::Log,
+    /// Offers to remove keys that are currently set, for our own Plog
+    on_remove: Option<EventHandler<ProvenanceKey>>,
+) -> Element {
+    // The entry each currently set key was last updated in, which is where it can be removed
+    let mut latest = HashMap::<String, usize>::new();
+    if on_remove.is_some() {
+        for (idx, (_count, entry, _kvp)) in plog
+            .verify()
+            .enumerate()
+            .filter_map(|(idx, v)| v.ok().map(|v| (idx, v)))
+        {
+            for op in entry.ops() {
+                match op {
+                    provenance_log::Op::Update(key, _) if is_removable(key) => {
+                        latest.insert(key.to_string(), idx);
+                    }
+                    provenance_log::Op::Delete(key) => {
+                        latest.remove(&key.to_string());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let rotations = prerotation::check_rotations(&plog)
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
     rsx! {
         div {
             class: "p-2 border rounded bg-neutral-100 text-green-800",
@@ -945,7 +927,12 @@ fn PlogDisplay(plog: provenance_log::Log) -> Element {
                                 li {
                                     class: "mb-1",
                                     span { class: "font-mono text-xs mr-2", "Entry {idx}:" }
-                                    DisplayEntry { entry: entry.clone(), pubkey: entry.ops().filter_map(|op| {
+                                    DisplayEntry {
+                                        entry: entry.clone(),
+                                        removable: latest.iter().filter(|(_, i)| **i == idx).map(|(key, _)| key.clone()).collect::<Vec<_>>(),
+                                        on_remove,
+                                        rotation: rotations.get(&idx).copied(),
+                                        pubkey: entry.ops().filter_map(|op| {
                                         if let provenance_log::Op::Update(key, value) = op {
                                             if key == &provenance_log::Key::from(bs::params::anykey::PubkeyParams::KEY_PATH) {
                                                 if let provenance_log::Value::Data(data) = value {
@@ -977,17 +964,67 @@ fn PlogDisplay(plog: provenance_log::Log) -> Element {
 }
 
 #[component]
-fn DisplayEntry(entry: provenance_log::Entry, pubkey: Option<Multikey>) -> Element {
+fn DisplayEntry(
+    entry: provenance_log::Entry,
+    pubkey: Option<Multikey>,
+    /// Keys that offer a remove action in this entry
+    removable: Vec<String>,
+    on_remove: Option<EventHandler<ProvenanceKey>>,
+    /// Whether the entry rotates to the key committed to before, if it rotates or changes
+    /// the commitment
+    rotation: Option<RotationCheck>,
+) -> Element {
     rsx! {
         div {
             class: "flex flex-col gap-1",
             for op in entry.ops() {
-                DisplayOp { op: op.clone() }
+                DisplayOp {
+                    op: op.clone(),
+                    on_remove: match op {
+                        provenance_log::Op::Update(key, _) if removable.contains(&key.to_string()) => on_remove,
+                        _ => None,
+                    },
+                }
+            }
+            if pubkey.is_none() && rotation == Some(RotationCheck::Mismatch) {
+                span {
+                    class: "px-1 rounded bg-red-100 text-red-700 font-bold text-xs",
+                    title: "The commitment to the next key was replaced or deleted without rotating, the key may have leaked",
+                    "⚠ entry changes the pre-commitment"
+                }
             }
             if let Some(pk) = pubkey {
                 div {
                     class: "flex gap-2 items-center text-xs",
                     span { class: "font-mono text-gray-500", "Public Key:" }
+                    span {
+                        class: "px-1 rounded bg-blue-100 font-mono text-blue-800",
+                        title: "Key algorithm",
+                        {KeyCodec::of(&pk).map_or("unknown", KeyCodec::name)}
+                    }
+                    match rotation {
+                        Some(RotationCheck::Committed) => rsx! {
+                            span { class: "px-1 rounded bg-green-100 text-green-800", "pre-committed rotation" }
+                        },
+                        Some(RotationCheck::Uncommitted) => rsx! {
+                            span { class: "px-1 rounded bg-gray-200 text-gray-700", "rotation without a pre-commitment" }
+                        },
+                        Some(RotationCheck::Mismatch) => rsx! {
+                            span {
+                                class: "px-1 rounded bg-red-100 text-red-700 font-bold",
+                                title: "This key is not the one the plog committed to, the previous key may have leaked",
+                                "⚠ rotation breaks the pre-commitment"
+                            }
+                        },
+                        Some(RotationCheck::Recovered(approvals)) => rsx! {
+                            span {
+                                class: "px-1 rounded bg-yellow-100 text-yellow-800",
+                                title: "The guardians moved the plog to a new key",
+                                "recovery approved by {approvals} guardian(s)"
+                            }
+                        },
+                        None => rsx! {},
+                    }
                     span { class: "font-mono text-blue-700 break-all", "{pk.fingerprint_view().unwrap().fingerprint(multicodec::Codec::Sha2256).unwrap():?}" }
                 }
             }
@@ -996,7 +1033,23 @@ fn DisplayEntry(entry: provenance_log::Entry, pubkey: Option<Multikey>) -> Eleme
 }
 
 #[component]
-fn DisplayOp(op: provenance_log::Op) -> Element {
+fn DisplayOp(op: provenance_log::Op, on_remove: Option<EventHandler<ProvenanceKey>>) -> Element {
+    if let (Some(on_remove), provenance_log::Op::Update(key, _)) = (on_remove, &op) {
+        let key = key.clone();
+        return rsx! {
+            div {
+                class: "flex gap-2 items-center justify-between",
+                DisplayOp { op: op.clone() }
+                button {
+                    class: "px-2 border rounded text-xs text-red-600 hover:bg-red-50",
+                    title: "Remove {key}",
+                    onclick: move |_| on_remove.call(key.clone()),
+                    "Remove"
+                }
+            }
+        };
+    }
+
     match op {
         provenance_log::Op::Noop(key) => rsx! {
             div {
@@ -1029,11 +1082,30 @@ fn DisplayOp(op: provenance_log::Op) -> Element {
             },
             provenance_log::Value::Data(data) => rsx! {
                 div {
-                    class: "flex gap-2 items-center text-xs",
+                    class: "flex gap-2 items-start text-xs",
                     span { class: "font-mono text-green-900", "{key}" }
-                    span { class: "font-mono text-gray-700", "{data.len()} bytes" }
+                    AttachmentView { key: key.to_string(), data }
                 }
             },
         },
     }
 }
+
+/// Keys the Plog itself depends on, which must not be removed.
+const PROTECTED_KEYS: &[&str] = &[
+    "/pubkey",
+    "/entrykey",
+    "/vlad/",
+    prerotation::COMMITMENT_PATH,
+    recovery::GUARDIANS_PREFIX,
+    recovery::THRESHOLD_PATH,
+    recovery::APPROVALS_PREFIX,
+];
+
+/// Whether a key can be removed from our Plog.
+fn is_removable(key: &ProvenanceKey) -> bool {
+    let key = key.to_string();
+    !PROTECTED_KEYS
+        .iter()
+        .any(|protected| key.starts_with(protected))
+}
@@ -0,0 +1,633 @@
+//! A typed profile on top of Plog keys, so everyone's link-in-bio fields line up.
+//!
+//! | Path              | Value                                  |
+//! |-------------------|----------------------------------------|
+//! | `/profile/name`   | display name                           |
+//! | `/profile/avatar` | image, stored inline                   |
+//! | `/links/<label>`  | `http(s)://` URL                       |
+//! | `/contact/<kind>` | one of [CONTACT_KINDS]                 |
+//!
+//! Any other key can still be set from the raw key form.
+use crate::attachment::{is_image, AttachmentImage};
+use crate::staged::{Change, StagedChanges};
+use dioxus::prelude::*;
+use provenance_log::{Key as ProvenanceKey, Log, Op, Value};
+use std::collections::BTreeMap;
+use vaiber_node::blobs::{self, BlobError};
+
+const NAME_PATH: &str = "/profile/name";
+const AVATAR_PATH: &str = "/profile/avatar";
+const LINKS_PREFIX: &str = "/links/";
+const CONTACT_PREFIX: &str = "/contact/";
+
+/// The kinds of contact details a profile can have.
+const CONTACT_KINDS: &[&str] = &["email", "phone", "matrix", "nostr", "pgp"];
+
+const MAX_NAME_CHARS: usize = 64;
+const MAX_LABEL_CHARS: usize = 32;
+const MAX_VALUE_CHARS: usize = 256;
+
+/// The current value of every key in `plog`, after replaying its entries in order.
+pub(crate) fn current_values(plog: &Log) -> BTreeMap<String, Value> {
+    let mut values = BTreeMap::new();
+    for (_count, entry, _kvp) in plog.verify().filter_map(Result::ok) {
+        for op in entry.ops() {
+            match op {
+                Op::Update(key, value) => {
+                    values.insert(key.to_string(), value.clone());
+                }
+                Op::Delete(key) => {
+                    values.remove(&key.to_string());
+                }
+                Op::Noop(_) => {}
+            }
+        }
+    }
+    values
+}
+
+/// The profile fields found in a Plog.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ProfileData {
+    pub name: String,
+    /// The avatar's data value
+    pub avatar: Option<Vec<u8>>,
+    /// `(label, url)` pairs, sorted by label
+    pub links: Vec<(String, String)>,
+    /// `(kind, value)` pairs, sorted by kind
+    pub contacts: Vec<(String, String)>,
+}
+
+impl ProfileData {
+    pub fn from_plog(plog: &Log) -> Self {
+        let mut profile = ProfileData::default();
+        for (key, value) in current_values(plog) {
+            match (key.as_str(), value) {
+                (NAME_PATH, Value::Str(name)) => profile.name = name,
+                (AVATAR_PATH, Value::Data(data)) => profile.avatar = Some(data),
+                (path, Value::Str(value)) => {
+                    if let Some(label) = field_label(LINKS_PREFIX, path) {
+                        profile.links.push((label.to_string(), value));
+                    } else if let Some(kind) = field_label(CONTACT_PREFIX, path) {
+                        profile.contacts.push((kind.to_string(), value));
+                    }
+                }
+                _ => {}
+            }
+        }
+        profile
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty()
+            && self.avatar.is_none()
+            && self.links.is_empty()
+            && self.contacts.is_empty()
+    }
+
+    /// Checks every field, returning the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)?;
+        for (label, url) in &self.links {
+            validate_label(label)?;
+            validate_url(url)?;
+        }
+        for (kind, value) in &self.contacts {
+            validate_contact(kind, value)?;
+        }
+        let mut labels = self
+            .links
+            .iter()
+            .map(|(label, _)| label)
+            .collect::<Vec<_>>();
+        labels.sort();
+        if labels.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Each link needs a different label.".to_string());
+        }
+        let mut kinds = self
+            .contacts
+            .iter()
+            .map(|(kind, _)| kind)
+            .collect::<Vec<_>>();
+        kinds.sort();
+        if kinds.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Each kind of contact can only be set once.".to_string());
+        }
+        Ok(())
+    }
+
+    /// The changes that turn the `current` profile into this one, leaving the avatar alone.
+    pub fn changes_from(
+        &self,
+        current: &ProfileData,
+    ) -> Result<Vec<(ProvenanceKey, Change)>, String> {
+        self.validate()?;
+
+        let mut ops = Vec::new();
+        if self.name != current.name {
+            ops.push(if self.name.is_empty() {
+                delete(NAME_PATH)?
+            } else {
+                use_str(NAME_PATH, &self.name)?
+            });
+        }
+        diff_fields(LINKS_PREFIX, &current.links, &self.links, &mut ops)?;
+        diff_fields(CONTACT_PREFIX, &current.contacts, &self.contacts, &mut ops)?;
+        Ok(ops)
+    }
+}
+
+/// Deletes the fields under `prefix` that are gone, and sets the ones that are new or changed.
+fn diff_fields(
+    prefix: &str,
+    current: &[(String, String)],
+    edited: &[(String, String)],
+    ops: &mut Vec<(ProvenanceKey, Change)>,
+) -> Result<(), String> {
+    for (name, _) in current {
+        if !edited.iter().any(|(edited_name, _)| edited_name == name) {
+            ops.push(delete(&format!("{prefix}{name}"))?);
+        }
+    }
+    for field in edited {
+        if !current.contains(field) {
+            ops.push(use_str(&format!("{prefix}{}", field.0), &field.1)?);
+        }
+    }
+    Ok(())
+}
+
+/// The label of a link or kind of contact at `path`, if it is one the editor can save again.
+///
+/// Keys set from the raw key form, like `/links/a/b`, aren't profile fields, so they are left
+/// out rather than blocking every later edit of the profile. They still show as plain keys.
+fn field_label<'a>(prefix: &str, path: &'a str) -> Option<&'a str> {
+    let label = path.strip_prefix(prefix)?;
+    let valid = if prefix == CONTACT_PREFIX {
+        CONTACT_KINDS.contains(&label)
+    } else {
+        validate_label(label).is_ok()
+    };
+    valid.then_some(label)
+}
+
+fn key(path: &str) -> Result<ProvenanceKey, String> {
+    ProvenanceKey::try_from(path.to_string()).map_err(|e| format!("Invalid key {path}: {e}"))
+}
+
+fn use_str(path: &str, s: &str) -> Result<(ProvenanceKey, Change), String> {
+    Ok((key(path)?, Change::Str(s.to_string())))
+}
+
+fn delete(path: &str) -> Result<(ProvenanceKey, Change), String> {
+    Ok((key(path)?, Change::Delete))
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("Name must be at most {MAX_NAME_CHARS} characters."));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Name cannot contain control characters.".to_string());
+    }
+    Ok(())
+}
+
+/// Link labels become part of the key, so they are kept to lowercase letters, digits and `-`.
+fn validate_label(label: &str) -> Result<(), String> {
+    if label.is_empty() || label.len() > MAX_LABEL_CHARS {
+        return Err(format!(
+            "Link labels must be 1 to {MAX_LABEL_CHARS} characters."
+        ));
+    }
+    if !label
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(format!(
+            "Link label {label:?} can only contain a-z, 0-9 and '-'."
+        ));
+    }
+    Ok(())
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| format!("Link {url:?} must start with https:// or http://."))?;
+    if rest.is_empty() || url.len() > MAX_VALUE_CHARS || url.chars().any(char::is_whitespace) {
+        return Err(format!("Link {url:?} is not a valid URL."));
+    }
+    Ok(())
+}
+
+fn validate_contact(kind: &str, value: &str) -> Result<(), String> {
+    if !CONTACT_KINDS.contains(&kind) {
+        return Err(format!("Unknown kind of contact {kind:?}."));
+    }
+    if value.is_empty() || value.len() > MAX_VALUE_CHARS {
+        return Err(format!(
+            "The {kind} contact must be 1 to {MAX_VALUE_CHARS} characters."
+        ));
+    }
+    let valid = match kind {
+        "email" => value
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.')),
+        "phone" => value
+            .chars()
+            .all(|c| c.is_ascii_digit() || " +-().".contains(c)),
+        "matrix" => value.starts_with('@') && value.contains(':'),
+        _ => !value.chars().any(char::is_control),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("{value:?} is not a valid {kind} contact."))
+    }
+}
+
+/// Edits the profile fields of our own Plog, staging them to be signed in a single entry.
+#[component]
+pub(crate) fn ProfileEditor() -> Element {
+    let plog_signal = use_context::<Signal<Option<Log>>>();
+    let mut staged = use_context::<Signal<StagedChanges>>();
+
+    let current = use_memo(move || {
+        plog_signal
+            .read()
+            .as_ref()
+            .map(ProfileData::from_plog)
+            .unwrap_or_default()
+    });
+    let mut edited = use_signal(|| current.peek().clone());
+    // The profile `edited` started from, to tell whether there are local edits
+    let mut edited_from = use_signal(|| current.peek().clone());
+    // A new avatar to attach, or Some(None) to remove the current one
+    let mut new_avatar = use_signal(|| None::<Option<Vec<u8>>>);
+    let mut error = use_signal(|| None::<String>);
+
+    // Once the staged avatar is committed, it is the current one
+    use_effect(move || {
+        let _ = current.read();
+        new_avatar.set(None);
+    });
+
+    // Follow a new plog, e.g. after signing or loading it, unless there are local edits
+    use_effect(move || {
+        let current = current();
+        if *edited.peek() == *edited_from.peek() {
+            edited.set(current.clone());
+        }
+        edited_from.set(current);
+    });
+
+    let handle_stage = move |_| {
+        let current = current();
+        let mut changes = match edited.read().changes_from(&current) {
+            Ok(changes) => changes,
+            Err(e) => {
+                error.set(Some(e));
+                return;
+            }
+        };
+        match new_avatar() {
+            Some(Some(data)) => match key(AVATAR_PATH) {
+                Ok(avatar) => changes.push((avatar, Change::File("avatar".to_string(), data))),
+                Err(e) => error.set(Some(e)),
+            },
+            Some(None) if current.avatar.is_some() => match delete(AVATAR_PATH) {
+                Ok(change) => changes.push(change),
+                Err(e) => error.set(Some(e)),
+            },
+            _ => {}
+        }
+        if changes.is_empty() {
+            error.set(Some("Nothing changed.".to_string()));
+            return;
+        }
+        error.set(None);
+        let mut staged_changes = staged.write();
+        for (key, change) in changes {
+            staged_changes.stage(&key, change);
+        }
+    };
+
+    let avatar_preview = match new_avatar() {
+        Some(avatar) => avatar,
+        None => current().avatar,
+    };
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 bg-white border border-green-200 rounded-lg p-4 shadow-sm text-xs",
+            onsubmit: handle_stage,
+            h4 { class: "text-lg font-semibold text-green-700", "Profile" }
+            div {
+                class: "flex items-center gap-2",
+                if let Some(avatar) = avatar_preview {
+                    AttachmentImage { data: avatar, class: "h-12 w-12 rounded-full border object-cover" }
+                    button {
+                        class: "px-2 border rounded hover:bg-gray-100",
+                        r#type: "button",
+                        onclick: move |_| new_avatar.set(Some(None)),
+                        "Remove avatar"
+                    }
+                }
+                label { r#for: "avatar", class: "text-gray-700", "Avatar" }
+                input {
+                    r#type: "file",
+                    id: "avatar",
+                    accept: "image/*",
+                    onchange: move |e| async move {
+                        let Some(file_engine) = e.files() else {
+                            return;
+                        };
+                        let Some(name) = file_engine.files().into_iter().next() else {
+                            return;
+                        };
+                        match file_engine.read_file(&name).await {
+                            Some(data) if data.len() > blobs::INLINE_LIMIT => {
+                                error.set(Some(BlobError::TooLarge(data.len()).to_string()))
+                            }
+                            Some(data) if is_image(&data) => new_avatar.set(Some(Some(data))),
+                            Some(_) => error.set(Some("Avatar must be a PNG, JPEG, GIF or WebP image.".to_string())),
+                            None => error.set(Some(format!("Failed to read {name}"))),
+                        }
+                    },
+                }
+            }
+            input {
+                class: "p-2 border rounded focus:outline-none focus:ring-2 focus:ring-green-400",
+                placeholder: "Name",
+                value: "{edited.read().name}",
+                oninput: move |e| edited.write().name = e.value(),
+            }
+            h5 { class: "font-semibold text-gray-700", "Links" }
+            for (idx, (label, url)) in edited.read().links.iter().enumerate() {
+                div {
+                    key: "link-{idx}",
+                    class: "flex gap-2",
+                    input {
+                        class: "w-28 p-1 border rounded font-mono",
+                        placeholder: "label",
+                        value: "{label}",
+                        oninput: move |e| edited.write().links[idx].0 = e.value().trim().to_lowercase(),
+                    }
+                    input {
+                        class: "flex-1 p-1 border rounded font-mono",
+                        placeholder: "https://",
+                        value: "{url}",
+                        oninput: move |e| edited.write().links[idx].1 = e.value().trim().to_string(),
+                    }
+                    button {
+                        class: "px-2 border rounded hover:bg-gray-100",
+                        r#type: "button",
+                        onclick: move |_| { edited.write().links.remove(idx); },
+                        "Remove"
+                    }
+                }
+            }
+            button {
+                class: "self-start px-2 border rounded hover:bg-gray-100",
+                r#type: "button",
+                onclick: move |_| edited.write().links.push(Default::default()),
+                "Add link"
+            }
+            h5 { class: "font-semibold text-gray-700", "Contact" }
+            for (idx, (kind, value)) in edited.read().contacts.iter().enumerate() {
+                div {
+                    key: "contact-{idx}",
+                    class: "flex gap-2",
+                    select {
+                        class: "w-28 p-1 border rounded font-mono",
+                        onchange: move |e| edited.write().contacts[idx].0 = e.value(),
+                        for option_kind in CONTACT_KINDS {
+                            option { value: "{option_kind}", selected: kind == option_kind, "{option_kind}" }
+                        }
+                    }
+                    input {
+                        class: "flex-1 p-1 border rounded font-mono",
+                        value: "{value}",
+                        oninput: move |e| edited.write().contacts[idx].1 = e.value().trim().to_string(),
+                    }
+                    button {
+                        class: "px-2 border rounded hover:bg-gray-100",
+                        r#type: "button",
+                        onclick: move |_| { edited.write().contacts.remove(idx); },
+                        "Remove"
+                    }
+                }
+            }
+            button {
+                class: "self-start px-2 border rounded hover:bg-gray-100",
+                r#type: "button",
+                onclick: move |_| edited.write().contacts.push((CONTACT_KINDS[0].to_string(), String::new())),
+                "Add contact"
+            }
+            button {
+                class: "px-3 py-2 bg-green-500 hover:bg-green-600 text-white rounded font-bold transition",
+                r#type: "submit",
+                "Stage profile changes"
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500 mt-1", "{err}" }
+            }
+        }
+    }
+}
+
+/// A peer's profile, if their Plog has one.
+#[component]
+pub(crate) fn ProfileCard(plog: Log) -> Element {
+    let profile = ProfileData::from_plog(&plog);
+    if profile.is_empty() {
+        return rsx! {};
+    }
+
+    rsx! {
+        div {
+            class: "flex gap-3 items-start p-2 bg-white border border-green-100 rounded-lg",
+            if let Some(avatar) = profile.avatar {
+                AttachmentImage { data: avatar, class: "h-12 w-12 rounded-full border object-cover" }
+            }
+            div {
+                class: "flex flex-col gap-1 text-xs",
+                if !profile.name.is_empty() {
+                    span { class: "text-base font-semibold text-green-900", "{profile.name}" }
+                }
+                for (label, url) in profile.links {
+                    // Only ever link to valid http(s) URLs, whatever the Plog says
+                    if validate_url(&url).is_ok() {
+                        a {
+                            class: "text-blue-700 underline break-all",
+                            href: "{url}",
+                            target: "_blank",
+                            rel: "noopener noreferrer",
+                            "{label}"
+                        }
+                    }
+                }
+                for (kind, value) in profile.contacts {
+                    span {
+                        class: "text-gray-700 break-all",
+                        span { class: "font-mono text-gray-500 mr-1", "{kind}:" }
+                        "{value}"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// The `(path, change)` pairs of `ops`, to compare against.
+    fn paths(ops: Vec<(ProvenanceKey, Change)>) -> Vec<(String, Change)> {
+        ops.into_iter()
+            .map(|(key, change)| (key.to_string(), change))
+            .collect()
+    }
+
+    #[test]
+    fn only_http_urls_are_links() {
+        assert!(validate_url("https://example.com").is_ok());
+        assert!(validate_url("http://example.com/a?b=c").is_ok());
+        for url in [
+            "javascript:alert(1)",
+            "ftp://example.com",
+            "example.com",
+            "https://",
+            "https://exa mple.com",
+        ] {
+            assert!(validate_url(url).is_err(), "{url} should be rejected");
+        }
+        let long = format!("https://{}", "a".repeat(MAX_VALUE_CHARS));
+        assert!(validate_url(&long).is_err());
+    }
+
+    #[test]
+    fn contacts_are_checked_by_kind() {
+        assert!(validate_contact("email", "me@example.com").is_ok());
+        assert!(validate_contact("email", "me@localhost").is_err());
+        assert!(validate_contact("email", "@example.com").is_err());
+        assert!(validate_contact("phone", "+1 (555) 010-0000").is_ok());
+        assert!(validate_contact("phone", "call me").is_err());
+        assert!(validate_contact("matrix", "@me:example.org").is_ok());
+        assert!(validate_contact("matrix", "me:example.org").is_err());
+        assert!(validate_contact("nostr", "npub1xyz").is_ok());
+        assert!(validate_contact("nostr", "npub\u{0}").is_err());
+        assert!(validate_contact("email", "").is_err());
+        assert!(validate_contact("fax", "123").is_err());
+    }
+
+    #[test]
+    fn link_labels_must_be_unique_and_fit_in_a_key() {
+        let mut profile = ProfileData {
+            links: pairs(&[("blog", "https://example.com")]),
+            ..Default::default()
+        };
+        assert!(profile.validate().is_ok());
+
+        let long = "a".repeat(MAX_LABEL_CHARS + 1);
+        for label in ["", "a/b", "Blog", "my blog", long.as_str()] {
+            profile.links[0].0 = label.to_string();
+            assert!(profile.validate().is_err(), "{label:?} should be rejected");
+        }
+
+        profile.links = pairs(&[("blog", "https://a.com"), ("blog", "https://b.com")]);
+        assert!(profile.validate().is_err());
+
+        profile.links.clear();
+        profile.contacts = pairs(&[("email", "a@b.com"), ("email", "c@d.com")]);
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn only_fields_the_editor_can_save_are_loaded() {
+        assert_eq!(field_label(LINKS_PREFIX, "/links/blog"), Some("blog"));
+        assert_eq!(field_label(LINKS_PREFIX, "/links/a/b"), None);
+        assert_eq!(field_label(LINKS_PREFIX, "/links/Blog"), None);
+        assert_eq!(field_label(LINKS_PREFIX, "/profile/name"), None);
+        assert_eq!(field_label(CONTACT_PREFIX, "/contact/email"), Some("email"));
+        assert_eq!(field_label(CONTACT_PREFIX, "/contact/fax"), None);
+    }
+
+    #[test]
+    fn the_diff_deletes_removed_fields_and_sets_changed_ones() {
+        let current = pairs(&[
+            ("blog", "https://blog.example.com"),
+            ("code", "https://code.example.com"),
+            ("home", "https://example.com"),
+        ]);
+        let edited = pairs(&[
+            ("home", "https://example.com"),
+            ("code", "https://git.example.com"),
+            ("shop", "https://shop.example.com"),
+        ]);
+        let mut ops = Vec::new();
+        diff_fields(LINKS_PREFIX, &current, &edited, &mut ops).unwrap();
+        assert_eq!(
+            paths(ops),
+            vec![
+                ("/links/blog".to_string(), Change::Delete),
+                (
+                    "/links/code".to_string(),
+                    Change::Str("https://git.example.com".to_string())
+                ),
+                (
+                    "/links/shop".to_string(),
+                    Change::Str("https://shop.example.com".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn changes_from_covers_the_name_and_refuses_invalid_profiles() {
+        let current = ProfileData {
+            name: "Old".to_string(),
+            contacts: pairs(&[("email", "a@b.com")]),
+            ..Default::default()
+        };
+        assert!(current.changes_from(&current).unwrap().is_empty());
+
+        let renamed = ProfileData {
+            name: "New".to_string(),
+            ..current.clone()
+        };
+        assert_eq!(
+            paths(renamed.changes_from(&current).unwrap()),
+            vec![(NAME_PATH.to_string(), Change::Str("New".to_string()))]
+        );
+
+        let unnamed = ProfileData {
+            name: String::new(),
+            contacts: Vec::new(),
+            ..current.clone()
+        };
+        assert_eq!(
+            paths(unnamed.changes_from(&current).unwrap()),
+            vec![
+                (NAME_PATH.to_string(), Change::Delete),
+                ("/contact/email".to_string(), Change::Delete),
+            ]
+        );
+
+        let invalid = ProfileData {
+            links: pairs(&[("blog", "javascript:alert(1)")]),
+            ..current.clone()
+        };
+        assert!(invalid.changes_from(&current).is_err());
+    }
+}
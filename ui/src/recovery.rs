@@ -0,0 +1,678 @@
+//! Social recovery in the apps: naming guardians, guarding the peers we follow, and recovering a
+//! lost identity through its guardians. The protocol itself is in [vaiber_node::recovery].
+//!
+//! Recovery messages arrive as [NodeUpdate::Recovery](vaiber_node::NodeUpdate::Recovery) and
+//! are kept in the [RecoveryInbox] the Peer component provides.
+use crate::mnemonic::PendingAttach;
+use crate::peer::commit_ops;
+use crate::wallet::{off_ui_thread, KeyCodec, KeyMan, WalletKeys};
+use crate::StorageProvider;
+use bs_peer::peer::{DefaultBsPeer, ResolverExt as _};
+use dioxus::logger::tracing;
+use dioxus::prelude::*;
+use multicid::Vlad;
+use multikey::{Multikey, Views as _};
+use provenance_log::Log;
+use std::collections::HashSet;
+use vaiber_node::follow::FollowList;
+use vaiber_node::recovery::{self, Approval, Guardian, Guardians, RecoveryError, RecoveryMessage};
+use vaiber_node::{events, plog, wallet};
+
+/// The recovery messages received so far, with the VLAD whose topic they came on.
+pub(crate) type RecoveryInbox = Vec<(Vlad, RecoveryMessage)>;
+
+/// The guardians our Plog currently names.
+fn saved(plog: &Option<Log>) -> Result<Guardians, String> {
+    match plog {
+        Some(plog) => Guardians::from_plog(plog).map_err(|e| e.to_string()),
+        None => Ok(Guardians::default()),
+    }
+}
+
+/// The fingerprint of the public key `key`, for people to compare out of band.
+fn fingerprint(key: &[u8]) -> String {
+    Multikey::try_from(key)
+        .ok()
+        .and_then(|key| {
+            key.fingerprint_view()
+                .ok()?
+                .fingerprint(multicodec::Codec::Sha2256)
+                .ok()
+        })
+        .map_or_else(
+            || "invalid key".to_string(),
+            |fingerprint| format!("{fingerprint:?}"),
+        )
+}
+
+/// Names the guardians who can recover our Plog, from the offers of peers we follow, and how
+/// many of them have to approve a recovery.
+///
+/// The locks only let a rotation change the guardians, so saving them rotates the key.
+#[component]
+pub(crate) fn GuardiansEditor(bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let plog_signal = use_context::<Signal<Option<Log>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let peer_list = use_context::<Signal<FollowList>>();
+    let inbox = use_context::<Signal<RecoveryInbox>>();
+
+    // The guardians being edited, None while they are the saved ones
+    let mut draft = use_signal(|| None::<Guardians>);
+    let mut status = use_signal(|| None::<String>);
+    let mut saving = use_signal(|| false);
+
+    let edited = move || {
+        draft
+            .peek()
+            .clone()
+            .unwrap_or_else(|| saved(&plog_signal.peek()).unwrap_or_default())
+    };
+    let mut add = move |guardian: Guardian| {
+        let mut guardians = edited();
+        if guardians.position(&guardian.vlad).is_none() {
+            guardians.guardians.push(guardian);
+            guardians.threshold = guardians.threshold.max(1);
+        }
+        draft.set(Some(guardians));
+    };
+    let mut remove = move |index: usize| {
+        let mut guardians = edited();
+        guardians.guardians.remove(index);
+        guardians.threshold = guardians.threshold.min(guardians.guardians.len());
+        draft.set(Some(guardians));
+    };
+
+    let handle_save = move |_| {
+        let Some(keys) = key_manager.read().clone() else {
+            status.set(Some("Wallet is locked.".to_string()));
+            return;
+        };
+        let ops = saved(&plog_signal.peek())
+            .and_then(|current| edited().changes(&current).map_err(|e| e.to_string()));
+        let ops = match ops {
+            Ok(ops) if ops.is_empty() => {
+                status.set(Some("Nothing changed.".to_string()));
+                return;
+            }
+            Ok(ops) => ops,
+            Err(e) => {
+                status.set(Some(e));
+                return;
+            }
+        };
+        let storage = storage.clone();
+        saving.set(true);
+        status.set(None);
+        spawn(async move {
+            let codec = keys.signing_codec();
+            match commit_ops(
+                bs_peer_signal,
+                plog_signal,
+                &storage,
+                &keys,
+                ops,
+                Some(codec),
+            )
+            .await
+            {
+                Ok(()) => {
+                    draft.set(None);
+                    status.set(Some("Guardians saved.".to_string()));
+                }
+                Err(e) => status.set(Some(e.to_string())),
+            }
+            saving.set(false);
+        });
+    };
+
+    let plog = plog_signal.read().clone();
+    let Some(own) = plog.as_ref().map(|plog| plog.vlad.clone()) else {
+        return rsx! {};
+    };
+    let shown = match draft() {
+        Some(guardians) => guardians,
+        None => match saved(&plog) {
+            Ok(guardians) => guardians,
+            Err(e) => return rsx! { p { class: "text-red-500 text-xs", "{e}" } },
+        },
+    };
+
+    // Offers on our recovery topic, the latest from each guardian, checked against their Plog
+    let mut offers = Vec::<(Vlad, Result<Guardian, String>)>::new();
+    for (_, message) in inbox.read().iter().filter(|(vlad, _)| vlad == &own) {
+        let RecoveryMessage::Offer {
+            guardian,
+            key,
+            signature,
+        } = message
+        else {
+            continue;
+        };
+        let Ok(vlad) = Vlad::try_from_str(guardian) else {
+            continue;
+        };
+        let checked = match peer_list
+            .read()
+            .get(&vlad)
+            .and_then(|followed| followed.plog.clone())
+        {
+            Some(their_plog) => {
+                recovery::verify_offer(&their_plog, &own, key, signature).map_err(|e| e.to_string())
+            }
+            None => Err("Follow them to check this offer.".to_string()),
+        };
+        offers.retain(|(offered, _)| offered != &vlad);
+        offers.push((vlad, checked));
+    }
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 text-xs",
+            p {
+                class: "text-gray-600",
+                "Guardians can together move your Plog to a new wallet if you lose your password. Peers you follow offer to guard you from their Tracked Peers list, and their offers show up here."
+            }
+            if shown.is_empty() {
+                p { class: "italic text-gray-400", "No guardians yet." }
+            }
+            for (index, guardian) in shown.guardians.iter().enumerate() {
+                div {
+                    key: "{guardian.vlad}",
+                    class: "flex gap-2 items-center justify-between",
+                    span { class: "font-mono truncate", "{guardian.vlad}" }
+                    button {
+                        class: "px-2 border rounded text-red-600 hover:bg-red-50",
+                        r#type: "button",
+                        onclick: move |_| remove(index),
+                        "Remove"
+                    }
+                }
+            }
+            if !shown.is_empty() {
+                label {
+                    class: "flex gap-2 items-center",
+                    "Approvals needed"
+                    input {
+                        class: "w-16 p-1 border rounded",
+                        r#type: "number",
+                        min: "1",
+                        max: "{shown.guardians.len()}",
+                        value: "{shown.threshold}",
+                        oninput: move |e| {
+                            if let Ok(threshold) = e.value().parse() {
+                                let mut guardians = edited();
+                                guardians.threshold = threshold;
+                                draft.set(Some(guardians));
+                            }
+                        },
+                    }
+                    span { class: "text-gray-600", "of {shown.guardians.len()}" }
+                }
+            }
+            for (vlad, checked) in offers {
+                div {
+                    key: "{vlad}",
+                    class: "flex gap-2 items-center justify-between bg-blue-50 rounded p-1",
+                    span { class: "font-mono truncate", "Offer from {vlad}" }
+                    match checked {
+                        Ok(guardian) if shown.position(&guardian.vlad).is_none() => rsx! {
+                            button {
+                                class: "px-2 border rounded hover:bg-gray-100",
+                                r#type: "button",
+                                onclick: move |_| add(guardian.clone()),
+                                "Add"
+                            }
+                        },
+                        Ok(_) => rsx! { span { class: "text-gray-500", "Guardian" } },
+                        Err(e) => rsx! { span { class: "text-red-500", "{e}" } },
+                    }
+                }
+            }
+            div {
+                class: "flex gap-2",
+                button {
+                    class: "px-3 py-2 bg-green-500 hover:bg-green-600 text-white rounded font-bold transition disabled:bg-gray-400",
+                    r#type: "button",
+                    title: "Only a rotation can change the guardians, so this rotates to the pre-committed key",
+                    disabled: saving() || draft.read().is_none(),
+                    onclick: handle_save,
+                    if saving() { "Saving..." } else { "Save guardians" }
+                }
+                button {
+                    class: "px-3 py-2 border rounded hover:bg-gray-100 disabled:text-gray-400",
+                    r#type: "button",
+                    disabled: saving() || draft.read().is_none(),
+                    onclick: move |_| draft.set(None),
+                    "Discard"
+                }
+            }
+            if let Some(msg) = status() {
+                p { class: "text-gray-700", "{msg}" }
+            }
+        }
+    }
+}
+
+/// Something a guardian signs, as the VLAD they guard with, for the wallet `keys`.
+type Sign = Box<dyn FnOnce(&WalletKeys, &Vlad) -> Result<RecoveryMessage, RecoveryError>>;
+
+/// Offers to guard the peers we follow, and approves their recovery requests.
+#[component]
+pub(crate) fn Guarding(peer: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
+    let plog_signal = use_context::<Signal<Option<Log>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let peer_list = use_context::<Signal<FollowList>>();
+    let inbox = use_context::<Signal<RecoveryInbox>>();
+
+    let mut status = use_signal(|| None::<String>);
+
+    // Signs a message as us and sends it on the recovery topic of `vlad`
+    let mut send = move |vlad: Vlad, sign: Sign| {
+        let Some(keys) = key_manager.read().clone() else {
+            status.set(Some("Wallet is locked.".to_string()));
+            return;
+        };
+        let Some(own) = plog_signal.peek().as_ref().map(|plog| plog.vlad.clone()) else {
+            status.set(Some("Plog is not initialized.".to_string()));
+            return;
+        };
+        let Some(bs_peer) = peer.peek().clone() else {
+            status.set(Some("Peer not initialized".to_string()));
+            return;
+        };
+        let message = match sign(&keys, &own) {
+            Ok(message) => message,
+            Err(e) => {
+                status.set(Some(e.to_string()));
+                return;
+            }
+        };
+        spawn(async move {
+            match events::send_recovery(&bs_peer, &vlad, &message).await {
+                Ok(()) => status.set(Some(format!("Sent to {vlad}."))),
+                Err(e) => status.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let Some(own) = plog_signal.read().as_ref().map(|plog| plog.vlad.clone()) else {
+        return rsx! {};
+    };
+    let peers = peer_list.read().clone();
+    if peers.is_empty() {
+        return rsx! {};
+    }
+
+    let mut guarded = Vec::new();
+    for (vlad, followed) in peers.iter() {
+        let guards = followed
+            .plog
+            .as_ref()
+            .and_then(|plog| Guardians::from_plog(plog).ok())
+            .is_some_and(|guardians| guardians.position(&own).is_some());
+        let mut requests = Vec::new();
+        for (_, message) in inbox.read().iter().filter(|(from, _)| from == vlad) {
+            if let RecoveryMessage::Request {
+                new_pubkey,
+                commitment,
+            } = message
+            {
+                requests.push((new_pubkey.clone(), commitment.clone()));
+            }
+        }
+        guarded.push((vlad.clone(), guards, requests));
+    }
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 text-xs",
+            h4 { class: "font-semibold", "Guarding" }
+            p {
+                class: "text-gray-600",
+                "Offer to guard a peer so they can add you as a guardian. When they ask for a recovery, only approve once you made sure, by other means than this app, that the request is theirs and the key fingerprint matches."
+            }
+            for (vlad, guards, requests) in guarded {
+                GuardedPeer {
+                    key: "{vlad}",
+                    vlad: vlad.clone(),
+                    guards,
+                    requests,
+                    on_offer: move |vlad: Vlad| {
+                        let guardee = vlad.clone();
+                        send(vlad, Box::new(move |keys, own| recovery::offer(keys, own, &guardee)));
+                    },
+                    on_approve: move |(vlad, new_pubkey, commitment): (Vlad, Vec<u8>, Vec<u8>)| {
+                        let guardee = vlad.clone();
+                        send(
+                            vlad,
+                            Box::new(move |keys, own| {
+                                recovery::approve(keys, own, &guardee, &new_pubkey, &commitment)
+                            }),
+                        );
+                    },
+                }
+            }
+            if let Some(msg) = status() {
+                p { class: "text-gray-700", "{msg}" }
+            }
+        }
+    }
+}
+
+/// A followed peer we can offer to guard, or guard already, with their recovery requests.
+#[component]
+fn GuardedPeer(
+    vlad: Vlad,
+    /// Whether their Plog names us as a guardian
+    guards: bool,
+    /// The new key and commitment of each recovery they asked for
+    requests: Vec<(Vec<u8>, Vec<u8>)>,
+    on_offer: EventHandler<Vlad>,
+    on_approve: EventHandler<(Vlad, Vec<u8>, Vec<u8>)>,
+) -> Element {
+    let offer_vlad = vlad.clone();
+    rsx! {
+        div {
+            class: "p-2 border rounded flex flex-col gap-1",
+            div {
+                class: "flex gap-2 items-center justify-between",
+                span { class: "font-mono truncate", "{vlad}" }
+                if guards {
+                    span { class: "px-1 rounded bg-green-100 text-green-800", "you guard them" }
+                } else {
+                    button {
+                        class: "px-2 border rounded hover:bg-gray-100",
+                        r#type: "button",
+                        onclick: move |_| on_offer.call(offer_vlad.clone()),
+                        "Offer to guard"
+                    }
+                }
+            }
+            if guards {
+                for (new_pubkey, commitment) in requests {
+                    RecoveryRequest {
+                        key: "{fingerprint(&new_pubkey)}",
+                        vlad: vlad.clone(),
+                        new_pubkey,
+                        commitment,
+                        on_approve,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A request to recover the Plog of `vlad` with `new_pubkey`, for a guardian to approve.
+#[component]
+fn RecoveryRequest(
+    vlad: Vlad,
+    new_pubkey: Vec<u8>,
+    commitment: Vec<u8>,
+    on_approve: EventHandler<(Vlad, Vec<u8>, Vec<u8>)>,
+) -> Element {
+    let shown = fingerprint(&new_pubkey);
+    rsx! {
+        div {
+            class: "flex gap-2 items-center justify-between bg-yellow-50 rounded p-1",
+            span { class: "font-mono break-all", "Recovery to the key {shown}" }
+            button {
+                class: "px-2 border border-yellow-400 rounded hover:bg-yellow-100",
+                r#type: "button",
+                onclick: move |_| on_approve.call((vlad.clone(), new_pubkey.clone(), commitment.clone())),
+                "Approve"
+            }
+        }
+    }
+}
+
+/// Asks the guardians of the pending VLAD to recover its Plog with this wallet, collects their
+/// approvals, and recovers the Plog once enough of them are in.
+#[component]
+pub(crate) fn RequestRecovery(
+    bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>,
+    vlad: Vlad,
+) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let mut plog_signal = use_context::<Signal<Option<Log>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut pending_attach = use_context::<Signal<Option<PendingAttach>>>();
+    let inbox = use_context::<Signal<RecoveryInbox>>();
+
+    // The guardians of the Plog being recovered, once it was found
+    let mut guardians = use_signal(|| None::<Guardians>);
+    let mut status = use_signal(|| None::<String>);
+    let mut busy = use_signal(|| false);
+
+    let Some(keys) = key_manager.read().clone() else {
+        return rsx! {};
+    };
+    let request = recovery::request(&keys);
+    let (new_pubkey, commitment) = match &request {
+        Ok(RecoveryMessage::Request {
+            new_pubkey,
+            commitment,
+        }) => (new_pubkey.clone(), commitment.clone()),
+        Ok(_) => return rsx! {},
+        Err(e) => return rsx! { p { class: "text-red-500 text-xs", "{e}" } },
+    };
+
+    let approvals = inbox
+        .read()
+        .iter()
+        .filter(|(from, _)| from == &vlad)
+        .filter_map(|(_, message)| match message {
+            RecoveryMessage::Approval(approval) if approval.new_pubkey == new_pubkey => {
+                Some(approval.clone())
+            }
+            _ => None,
+        })
+        .collect::<Vec<Approval>>();
+    let approved = guardians().map(|guardians| {
+        let by = approvals
+            .iter()
+            .filter_map(|approval| {
+                recovery::verify_approval(&guardians, approval, &new_pubkey, &commitment).ok()
+            })
+            .collect::<HashSet<_>>();
+        (by.len(), guardians.threshold)
+    });
+
+    let request_vlad = vlad.clone();
+    let handle_request = move |_| {
+        let Some(peer) = bs_peer_signal.peek().clone() else {
+            status.set(Some("Peer not initialized".to_string()));
+            return;
+        };
+        let Ok(message) = request.as_ref().map(Clone::clone) else {
+            return;
+        };
+        let vlad = request_vlad.clone();
+        busy.set(true);
+        status.set(Some("Looking up the VLAD...".to_string()));
+        spawn(async move {
+            let Some(plog) = events::resolve_plog(&peer, &vlad).await else {
+                status.set(Some(format!("Could not find the plog of {vlad}")));
+                busy.set(false);
+                return;
+            };
+            match Guardians::from_plog(&plog) {
+                Ok(found) if found.is_empty() => {
+                    status.set(Some(RecoveryError::NoGuardians.to_string()))
+                }
+                Ok(found) => {
+                    guardians.set(Some(found));
+                    // Their approvals come back on the same topic
+                    events::subscribe_recovery(&peer, &vlad).await;
+                    match events::send_recovery(&peer, &vlad, &message).await {
+                        Ok(()) => status.set(Some(
+                            "Request sent, ask your guardians to approve it.".to_string(),
+                        )),
+                        Err(e) => status.set(Some(e.to_string())),
+                    }
+                }
+                Err(e) => status.set(Some(e.to_string())),
+            }
+            busy.set(false);
+        });
+    };
+
+    let recover_vlad = vlad.clone();
+    let handle_recover = move |_| {
+        let Some(mut peer) = bs_peer_signal.peek().clone() else {
+            status.set(Some("Peer not initialized".to_string()));
+            return;
+        };
+        let (storage, keys, vlad, approvals) = (
+            storage.clone(),
+            keys.clone(),
+            recover_vlad.clone(),
+            approvals.clone(),
+        );
+        busy.set(true);
+        status.set(Some("Recovering...".to_string()));
+        spawn(async move {
+            match plog::recover(&mut peer, &storage, &keys, &vlad, &approvals).await {
+                Ok(()) => {
+                    if let Some(plog) = peer.plog() {
+                        plog_signal.set(Some(plog.clone()));
+                    }
+                    bs_peer_signal.set(Some(peer.clone()));
+                    pending_attach.set(None);
+                    status.set(None);
+                    if let Err(e) = peer.record_plog_to_dht().await {
+                        tracing::error!("Failed to publish Plog records: {}", e);
+                    }
+                }
+                Err(e) => status.set(Some(e.to_string())),
+            }
+            busy.set(false);
+        });
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 border-t border-blue-200 pt-2",
+            h4 { class: "font-semibold text-blue-800", "Recover with your guardians" }
+            p {
+                class: "text-gray-600",
+                "Without your words, your guardians can move the Plog to this wallet. Send them the request, and tell them by other means that it is yours, with this key fingerprint:"
+            }
+            p { class: "font-mono break-all", {fingerprint(&new_pubkey)} }
+            button {
+                class: "py-2 px-4 rounded-md bg-blue-500 text-white hover:bg-blue-600 disabled:bg-gray-400 transition",
+                r#type: "button",
+                disabled: busy(),
+                onclick: handle_request,
+                if guardians.read().is_some() { "Send the request again" } else { "Ask my guardians" }
+            }
+            if let Some((approved, threshold)) = approved {
+                p { class: "text-gray-700", "{approved} of the {threshold} approvals needed" }
+                button {
+                    class: "py-2 px-4 rounded-md bg-green-500 text-white hover:bg-green-600 disabled:bg-gray-400 transition",
+                    r#type: "button",
+                    disabled: busy() || approved < threshold,
+                    onclick: handle_recover,
+                    "Recover Plog"
+                }
+            }
+            if let Some(msg) = status() {
+                p { class: "text-gray-700", "{msg}" }
+            }
+        }
+    }
+}
+
+/// Creates a new wallet under a new username and password to recover a lost VLAD through its
+/// guardians, for the Create Wallet screen.
+///
+/// Once created, the wallet is unlocked, `on_created` gets its encrypted seed, and the VLAD is
+/// left pending, for [RequestRecovery] to recover.
+#[component]
+pub(crate) fn RecoverWithGuardians(on_created: EventHandler<Vec<u8>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let mut key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut pending_attach = use_context::<Signal<Option<PendingAttach>>>();
+
+    let mut vlad_input = use_signal(String::new);
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut creating = use_signal(|| false);
+
+    let handle_create = move |_| {
+        let Ok(vlad) = Vlad::try_from_str(vlad_input().trim()) else {
+            error.set(Some("VLAD invalid".to_string()));
+            return;
+        };
+        let storage = storage.clone();
+        error.set(None);
+        creating.set(true);
+        spawn(async move {
+            let (name, pass, target) = (username(), password(), vlad.clone());
+            let created = off_ui_thread(async move {
+                wallet::create_for_recovery(&storage, &target, &name, &pass).await
+            })
+            .await;
+            match created {
+                Ok(wallet) => {
+                    match wallet.encrypted_seed() {
+                        Ok(seed) => on_created.call(seed),
+                        Err(e) => error.set(Some(format!("Error encrypting seed: {e}"))),
+                    }
+                    pending_attach.set(Some(PendingAttach(vlad)));
+                    match WalletKeys::new(wallet.seed(), KeyCodec::default()) {
+                        Ok(keys) => key_manager.set(Some(keys)),
+                        Err(e) => error.set(Some(e.to_string())),
+                    }
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            creating.set(false);
+        });
+    };
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 mt-2 text-sm",
+            onsubmit: handle_create,
+            p {
+                class: "text-xs text-gray-600",
+                "Lost your password and words? Create a new wallet for your VLAD, then ask your guardians to move your Plog to it."
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md font-mono text-xs",
+                r#type: "text",
+                placeholder: "VLAD of your Plog",
+                value: "{vlad_input}",
+                oninput: move |e| vlad_input.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "text",
+                autocomplete: "username",
+                placeholder: "New username",
+                value: "{username}",
+                oninput: move |e| username.set(e.value()),
+            }
+            input {
+                class: "w-full px-3 py-2 border border-gray-300 rounded-md",
+                r#type: "password",
+                autocomplete: "new-password",
+                placeholder: "New password",
+                value: "{password}",
+                oninput: move |e| password.set(e.value()),
+            }
+            button {
+                class: "w-full py-2 px-4 rounded-md bg-blue-500 text-white hover:bg-blue-600 disabled:bg-gray-400 transition",
+                r#type: "submit",
+                disabled: creating() || vlad_input().trim().is_empty(),
+                if creating() { "Creating..." } else { "Create Wallet for Recovery" }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500 text-xs", "{err}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Changes to our own Plog that are collected first, then signed together as a single entry.
+//!
+//! The raw key form, the profile editor and the remove buttons all stage their changes here,
+//! so editing several fields only creates one entry, one storage write and one DHT publish.
+use crate::peer::{attachment_label, commit_ops};
+use crate::profile::current_values;
+use crate::wallet::{KeyCodec, KeyMan, WalletKeys};
+use crate::StorageProvider;
+use bs::update::OpParams;
+use bs_peer::peer::DefaultBsPeer;
+use dioxus::prelude::*;
+use provenance_log::{Key as ProvenanceKey, Log, Value};
+use vaiber_node::blobs;
+
+/// What a staged change does to its key.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Change {
+    /// Set the key to a string
+    Str(String),
+    /// Attach a file as the key's value, with the file's name
+    File(String, Vec<u8>),
+    /// Remove the key
+    Delete,
+}
+
+/// The changes waiting to be signed, at most one per key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct StagedChanges {
+    /// `(key, change)` pairs, in the order they were staged
+    changes: Vec<(String, Change)>,
+    /// Rotate the signing key to a new key of this codec
+    rotate_key: Option<KeyCodec>,
+}
+
+impl StagedChanges {
+    /// Stages `change`, replacing any change already staged for `key`.
+    pub fn stage(&mut self, key: &ProvenanceKey, change: Change) {
+        let key = key.to_string();
+        self.changes.retain(|(staged, _)| staged != &key);
+        self.changes.push((key, change));
+    }
+
+    pub fn unstage(&mut self, key: &str) {
+        self.changes.retain(|(staged, _)| staged != key);
+    }
+
+    /// Drops the changes that were `committed`, keeping any staged since.
+    pub fn remove_committed(&mut self, committed: &StagedChanges) {
+        self.changes
+            .retain(|change| !committed.changes.contains(change));
+        if committed.rotate_key.is_some() {
+            self.rotate_key = None;
+        }
+    }
+
+    pub fn rotate_key(&self) -> Option<KeyCodec> {
+        self.rotate_key
+    }
+
+    pub fn set_rotate_key(&mut self, rotate_key: Option<KeyCodec>) {
+        self.rotate_key = rotate_key;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.rotate_key.is_none()
+    }
+
+    /// The number of changes, counting a key rotation as one.
+    pub fn len(&self) -> usize {
+        self.changes.len() + usize::from(self.rotate_key.is_some())
+    }
+
+    /// The ops for a single entry.
+    pub fn ops(&self) -> Result<Vec<OpParams>, String> {
+        let mut ops = Vec::with_capacity(self.changes.len());
+        for (path, change) in &self.changes {
+            let key = ProvenanceKey::try_from(path.clone())
+                .map_err(|e| format!("Invalid key {path}: {e}"))?;
+            ops.push(match change {
+                Change::Str(s) => OpParams::UseStr { key, s: s.clone() },
+                Change::File(_, data) => {
+                    blobs::attach(key, data.clone()).map_err(|e| e.to_string())?
+                }
+                Change::Delete => OpParams::Delete { key },
+            });
+        }
+        Ok(ops)
+    }
+}
+
+/// A short description of a value in the diff preview.
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Nil => "Nil".to_string(),
+        Value::Str(s) => s.clone(),
+        Value::Data(data) => format!("{} bytes of data", data.len()),
+    }
+}
+
+/// Shows the staged changes against the current values, and signs them as one entry.
+#[component]
+pub(crate) fn StagedChangesPanel(bs_peer_signal: Signal<Option<DefaultBsPeer<KeyMan>>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+    let plog_signal = use_context::<Signal<Option<Log>>>();
+    let key_manager = use_context::<Signal<Option<WalletKeys>>>();
+    let mut staged = use_context::<Signal<StagedChanges>>();
+
+    let mut error = use_signal(|| None::<String>);
+    let mut committing = use_signal(|| false);
+
+    let current = use_memo(move || {
+        plog_signal
+            .read()
+            .as_ref()
+            .map(current_values)
+            .unwrap_or_default()
+    });
+
+    let handle_commit = move |_| {
+        let Some(keys) = key_manager.read().clone() else {
+            error.set(Some("Wallet is locked.".to_string()));
+            return;
+        };
+        let changes = staged.read().clone();
+        let storage = storage.clone();
+        error.set(None);
+        committing.set(true);
+        spawn(async move {
+            let result = match changes.ops() {
+                Ok(ops) => commit_ops(
+                    bs_peer_signal,
+                    plog_signal,
+                    &storage,
+                    &keys,
+                    ops,
+                    changes.rotate_key(),
+                )
+                .await
+                .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => staged.write().remove_committed(&changes),
+                Err(e) => error.set(Some(e)),
+            }
+            committing.set(false);
+        });
+    };
+
+    if staged.read().is_empty() {
+        return rsx! {};
+    }
+
+    let count = staged.read().len();
+    let changes = staged.read().changes.clone();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 bg-yellow-50 border border-yellow-300 rounded-lg p-4 shadow-sm text-xs",
+            h4 { class: "text-lg font-semibold text-yellow-800", "Staged changes" }
+            ul {
+                class: "flex flex-col gap-1 font-mono",
+                for (path, change) in changes {
+                    li {
+                        key: "{path}",
+                        class: "flex gap-2 items-center justify-between",
+                        DiffLine { path: path.clone(), change, current: current.read().get(&path).cloned() }
+                        button {
+                            class: "px-2 border rounded hover:bg-gray-100",
+                            r#type: "button",
+                            onclick: {
+                                let path = path.clone();
+                                move |_| staged.write().unstage(&path)
+                            },
+                            "Unstage"
+                        }
+                    }
+                }
+                if let Some(codec) = staged.read().rotate_key() {
+                    li {
+                        class: "flex gap-2 items-center justify-between",
+                        span { class: "text-purple-700", "↻ rotate to the pre-committed key, and commit to a new {codec} key" }
+                        button {
+                            class: "px-2 border rounded hover:bg-gray-100",
+                            r#type: "button",
+                            onclick: move |_| staged.write().set_rotate_key(None),
+                            "Unstage"
+                        }
+                    }
+                }
+            }
+            div {
+                class: "flex gap-2 justify-end",
+                button {
+                    class: "px-3 py-2 border rounded hover:bg-gray-100",
+                    r#type: "button",
+                    disabled: committing(),
+                    onclick: move |_| staged.set(StagedChanges::default()),
+                    "Discard"
+                }
+                button {
+                    class: "px-3 py-2 bg-green-500 hover:bg-green-600 text-white rounded font-bold transition",
+                    r#type: "button",
+                    disabled: committing(),
+                    onclick: handle_commit,
+                    if committing() { "Signing..." } else { "Sign & commit {count} change(s)" }
+                }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500 mt-1", "{err}" }
+            }
+        }
+    }
+}
+
+/// One staged change, next to the value it replaces.
+#[component]
+fn DiffLine(path: String, change: Change, current: Option<Value>) -> Element {
+    let new = match change {
+        Change::Str(s) => s,
+        Change::File(name, data) => attachment_label(&name, data.len()),
+        Change::Delete => {
+            return match current {
+                Some(old) => rsx! {
+                    span {
+                        class: "text-red-700 break-all",
+                        "- {path} "
+                        span { class: "line-through text-gray-500", {describe(&old)} }
+                    }
+                },
+                None => rsx! {
+                    span { class: "text-gray-500 break-all", "- {path} (not set)" }
+                },
+            };
+        }
+    };
+
+    match current {
+        Some(old) => rsx! {
+            span {
+                class: "text-blue-700 break-all",
+                "~ {path} "
+                span { class: "line-through text-gray-500", {describe(&old)} }
+                " → {new}"
+            }
+        },
+        None => rsx! {
+            span { class: "text-green-700 break-all", "+ {path} = {new}" }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str) -> ProvenanceKey {
+        ProvenanceKey::try_from(path.to_string()).unwrap()
+    }
+
+    #[test]
+    fn staging_a_key_again_replaces_its_change() {
+        let mut staged = StagedChanges::default();
+        staged.stage(&key("/profile/name"), Change::Str("Old".to_string()));
+        staged.stage(&key("/links/blog"), Change::Delete);
+        staged.stage(&key("/profile/name"), Change::Str("New".to_string()));
+        assert_eq!(
+            staged.changes,
+            vec![
+                ("/links/blog".to_string(), Change::Delete),
+                ("/profile/name".to_string(), Change::Str("New".to_string())),
+            ]
+        );
+
+        staged.unstage("/links/blog");
+        staged.set_rotate_key(Some(KeyCodec::Ed25519));
+        assert_eq!(staged.len(), 2);
+    }
+
+    #[test]
+    fn committing_keeps_what_was_staged_since() {
+        let mut staged = StagedChanges::default();
+        staged.stage(&key("/profile/name"), Change::Str("Old".to_string()));
+        staged.set_rotate_key(Some(KeyCodec::Ed25519));
+        let committed = staged.clone();
+
+        staged.stage(&key("/profile/name"), Change::Str("New".to_string()));
+        staged.stage(&key("/links/blog"), Change::Delete);
+        staged.remove_committed(&committed);
+        assert_eq!(staged.rotate_key(), None);
+        assert_eq!(staged.len(), 2);
+
+        staged.remove_committed(&staged.clone());
+        assert!(staged.is_empty());
+    }
+
+    #[test]
+    fn every_change_becomes_one_op() {
+        let mut staged = StagedChanges::default();
+        staged.stage(&key("/profile/name"), Change::Str("Name".to_string()));
+        staged.stage(&key("/links/blog"), Change::Delete);
+        staged.stage(
+            &key("/profile/avatar"),
+            Change::File("avatar".to_string(), vec![1, 2, 3]),
+        );
+        let ops = staged.ops().unwrap();
+        assert_eq!(ops.len(), 3);
+        assert!(matches!(&ops[0], OpParams::UseStr { s, .. } if s == "Name"));
+        assert!(matches!(&ops[1], OpParams::Delete { .. }));
+        assert!(matches!(&ops[2], OpParams::UseBin { data, .. } if data == &[1, 2, 3]));
+
+        staged.stage(
+            &key("/profile/avatar"),
+            Change::File("avatar".to_string(), vec![0; blobs::INLINE_LIMIT + 1]),
+        );
+        assert!(staged.ops().is_err());
+    }
+}
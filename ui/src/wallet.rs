@@ -1,64 +1,132 @@
-//! Username and password based wallet Dioxus component.
-use bs_wallets::memory::InMemoryKeyManager;
-use dioxus::{logger::tracing, prelude::*};
-use multicodec::Codec;
-use provenance_log::key::key_paths::ValidatedKeyParams;
-use seed_keeper_core::{
-    credentials::{Credentials, MinString, Wallet},
-    seed::rand_seed,
-};
-
-use crate::storage::StorageProvider;
-
-pub(crate) type KeyMan = InMemoryKeyManager<bs_peer::Error>;
+//! Username and password based wallet Dioxus component, one per identity.
+use dioxus::prelude::*;
+use seed_keeper_core::{credentials::Wallet, seed::rand_seed};
+use std::future::Future;
+use vaiber_node::autolock::{AutoLockSettings, LockReason};
+use vaiber_node::wallet::{self, WalletError};
+use vaiber_node::{identity, mnemonic, SEED_STORAGE_KEY as STORAGE_KEY};
+use zeroize::Zeroizing;
+
+use crate::autolock::{AutoLock, AutoLockEditor};
+use crate::backup::{BackupExport, RestoreBackup};
+use crate::identity::{Identities, IdentityPicker};
+use crate::mnemonic::{MnemonicBackup, PendingAttach, RecoverFromWords};
+use crate::recovery::RecoverWithGuardians;
+use crate::StorageProvider;
+
+pub(crate) use vaiber_node::{KeyCodec, KeyMan, WalletKeys};
 
 const PEERPIPER_P_SVG: Asset = asset!("/assets/p.svg");
-const STORAGE_KEY: &str = "SEED_KEEPER_ENCRYPTED_SEED";
 const MIN_LENGTH: usize = 8;
 
+/// Runs `work`, which derives keys from passwords with Argon2, on a blocking thread so the UI
+/// stays responsive meanwhile.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn off_ui_thread<T: Send + 'static>(
+    work: impl Future<Output = T> + Send + 'static,
+) -> T {
+    let runtime = tokio::runtime::Handle::current();
+    match tokio::task::spawn_blocking(move || runtime.block_on(work)).await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Runs `work` in place, the browser has no thread to move it to.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn off_ui_thread<T>(work: impl Future<Output = T>) -> T {
+    work.await
+}
+
 #[component]
 pub fn WalletComponent(content: Element) -> Element {
-    let storage = use_context::<StorageProvider>();
-    let mut key_manager_signal = use_signal(|| {
-        if cfg!(feature = "dev") {
-            let codec = Codec::Ed25519Priv;
+    let root = use_context::<StorageProvider>();
+    let active = use_signal(|| identity::DEFAULT_IDENTITY.to_string());
+    use_context_provider(|| Identities { root, active });
 
-            let key_manager = KeyMan::default();
-            let secret_key = KeyMan::generate_from_seed(&codec, &*rand_seed())
-                .expect("Failed to generate key from seed");
+    rsx! {
+        // Keyed on the identity, so switching identities remounts everything below
+        IdentityWallet { key: "{active}", content }
+    }
+}
 
-            let key_path = bs::params::anykey::PubkeyParams::KEY_PATH;
-            key_manager
-                .store_secret_key(key_path.into(), secret_key)
-                .expect("Failed to store key");
-            Some(key_manager)
+/// The wallet of the active identity, with its own storage for everything below it.
+#[component]
+fn IdentityWallet(content: Element) -> Element {
+    let Identities { root, active } = use_context::<Identities>();
+    let storage = use_hook(|| {
+        if cfg!(feature = "dev") {
+            identity::dev_storage(&root)
         } else {
-            None::<KeyMan>
+            identity::storage(&root, &active.peek())
         }
+        .expect("identity names are checked when added")
+    });
+    use_context_provider(|| storage.clone());
+    // Leaving the identity forgets its at-rest key
+    use_drop({
+        let storage = storage.clone();
+        move || storage.lock()
     });
 
+    let mut key_manager_signal = use_signal(|| None::<WalletKeys>);
+
     // Provide Key Manager for children components
     // This allows child components to access the key manager
     use_context_provider(|| key_manager_signal);
 
+    // The VLAD to attach to once the peer is up, after recovering from words
+    let pending_attach = use_signal(|| None::<PendingAttach>);
+    use_context_provider(|| pending_attach);
+
     // State for the form
     let mut username = use_signal(String::new);
     let mut password = use_signal(String::new);
+    // The signing key algorithm for a new wallet's plog
+    let mut key_codec = use_signal(KeyCodec::default);
     let mut error_message = use_signal(String::new);
     let mut success_message = use_signal(String::new);
 
+    let mut encrypted_seed = use_signal(|| None::<Vec<u8>>);
+    let mut wallet_exists = use_signal(|| false);
+    let mut is_loading_wallet = use_signal(|| false);
+    let mut show_backup = use_signal(|| false);
+    let mut show_credentials = use_signal(|| false);
+    let mut show_auto_lock = use_signal(|| false);
+    let auto_lock_settings = use_signal(AutoLockSettings::default);
+    // The recovery words of a wallet just created, until they were confirmed
+    let mut new_words = use_signal(|| None::<Zeroizing<Vec<String>>>);
+
     // Try to load existing seed from storage
-    let mut encrypted_seed = use_signal(|| {
-        if storage.exists(STORAGE_KEY) && !cfg!(feature = "dev") {
-            storage.load(STORAGE_KEY).ok()
-        } else {
-            None
+    let seed_storage = storage.clone();
+    use_future(move || {
+        let storage = seed_storage.clone();
+        async move {
+            if cfg!(feature = "dev") {
+                // Skip the login with a throwaway wallet, in storage of its own
+                if let Err(err) = storage.delete_all("").await {
+                    error_message.set(format!("Failed to clear dev data: {err}"));
+                    return;
+                }
+                let seed = rand_seed();
+                storage.unlock(&*seed);
+                match WalletKeys::new(&*seed, KeyCodec::default()) {
+                    Ok(keys) => key_manager_signal.set(Some(keys)),
+                    Err(e) => error_message.set(format!("Failed to derive dev keys: {e}")),
+                }
+                return;
+            }
+            match storage.try_load(STORAGE_KEY).await {
+                Ok(Some(seed)) => {
+                    encrypted_seed.set(Some(seed));
+                    wallet_exists.set(true);
+                }
+                Ok(None) => {}
+                Err(err) => error_message.set(format!("Failed to load wallet: {err}")),
+            }
         }
     });
 
-    let mut wallet_exists = use_signal(|| encrypted_seed().is_some());
-    let mut is_loading_wallet = use_signal(|| false);
-
     let inputs_valid =
         use_memo(move || username().len() >= MIN_LENGTH && password().len() >= MIN_LENGTH);
 
@@ -73,155 +141,137 @@ pub fn WalletComponent(content: Element) -> Element {
     };
 
     // use wallet to configure key manager
-    let mut configure_key_manager = move |wallet: &Wallet| {
-        // Create a new key manager
-        let key_manager = KeyMan::default();
-
-        // Choose the crypto algorithm/codec (e.g., Ed25519)
-        let codec = Codec::Ed25519Priv;
-
-        // Generate the key from seed
-        let secret_key = KeyMan::generate_from_seed(&codec, wallet.seed())
-            .expect("Failed to generate key from seed");
-
-        // Store the key with a path
-        let key_path = bs::params::anykey::PubkeyParams::KEY_PATH;
-        key_manager
-            .store_secret_key(key_path.into(), secret_key)
-            .expect("Failed to store key");
+    let unlock_storage = storage.clone();
+    // An existing plog decides the codec itself, see WalletKeys::sync
+    let configure_key_manager = move |wallet: &Wallet, codec: KeyCodec| {
+        // Data at rest is encrypted with a key derived from the same seed
+        unlock_storage.unlock(wallet.seed());
 
         // Set the key manager signal
-        key_manager_signal.set(Some(key_manager));
+        match WalletKeys::new(wallet.seed(), codec) {
+            Ok(keys) => key_manager_signal.set(Some(keys)),
+            Err(e) => error_message.set(e.to_string()),
+        }
     };
 
     // Reset wallet data
     let reset_wallet = {
         let storage = storage.clone();
         move |_| {
-            // Clear storage
-            if let Err(err) = storage.delete(STORAGE_KEY) {
-                error_message.set(format!("Failed to clear wallet data: {err}"));
-                return;
-            }
+            let storage = storage.clone();
+            let root = root.clone();
+            spawn(async move {
+                // Clear everything of this identity, a new wallet could not read any of it
+                let cleared = if cfg!(feature = "dev") {
+                    storage
+                        .delete_all("")
+                        .await
+                        .map_err(identity::IdentityError::from)
+                } else {
+                    identity::clear(&root, &active.peek()).await
+                };
+                if let Err(err) = cleared {
+                    error_message.set(format!("Failed to clear wallet data: {err}"));
+                    return;
+                }
 
-            // Reset state
-            encrypted_seed.set(None);
-            wallet_exists.set(false);
-            key_manager_signal.set(None);
-            username.set(String::new());
-            password.set(String::new());
-            error_message.set(String::new());
-            success_message.set("Wallet data cleared successfully".to_string());
+                // Reset state
+                storage.lock();
+                encrypted_seed.set(None);
+                wallet_exists.set(false);
+                key_manager_signal.set(None);
+                username.set(String::new());
+                password.set(String::new());
+                error_message.set(String::new());
+                success_message.set("Wallet data cleared successfully".to_string());
+            });
         }
     };
 
     // Lock wallet (just clears the without deleting storage)
-    let lock_wallet = move |_| {
+    let lock_storage = storage.clone();
+    let lock_wallet = move |message: &str| {
+        // The peer holds the key manager too, and is dropped with the content
+        if let Some(keys) = key_manager_signal.peek().as_ref() {
+            keys.lock();
+        }
+        lock_storage.lock();
+        show_backup.set(false);
+        show_credentials.set(false);
+        show_auto_lock.set(false);
+        new_words.set(None);
         key_manager_signal.set(None);
         username.set(String::new());
         password.set(String::new());
-        success_message.set("Wallet locked successfully".to_string());
+        success_message.set(message.to_string());
     };
 
-    let handle_submit = move |evt: FormEvent| {
-        tracing::debug!("Form submitted");
-
+    let handle_submit = move |_| {
         // Clear messages
         error_message.set(String::new());
         success_message.set(String::new());
 
-        // Validate inputs
-        if username().is_empty() || password().is_empty() {
-            error_message.set("Username and password cannot be empty".to_string());
-            return;
-        }
-
-        // Enforce minimum length
-        tracing::info!("Enforcing input lengths");
         if username.read().len() < MIN_LENGTH || password.read().len() < MIN_LENGTH {
             error_message.set(format!(
                 "Username and password must be at least {MIN_LENGTH} characters"
             ));
             return;
         }
+        // A new wallet needs a key algorithm that works
+        let creating = !wallet_exists();
+        if creating {
+            if let Err(err) = key_codec().ensure_available() {
+                error_message.set(err.to_string());
+                return;
+            }
+        }
 
-        if wallet_exists() {
-            // Loading existing wallet
-            is_loading_wallet.set(true);
-
-            if let Some(stored_seed) = encrypted_seed() {
-                // Create credentials with existing encrypted seed
-                let username_result = MinString::new(&username());
-                let password_result = MinString::new(&password());
-
-                if let (Ok(username_min), Ok(password_min)) = (username_result, password_result) {
-                    let credentials = Credentials {
-                        username: username_min,
-                        password: password_min,
-                        encrypted_seed: Some(stored_seed),
-                    };
-
-                    // Try to load wallet with provided credentials
-                    match Wallet::new(credentials) {
-                        Ok(wallet) => {
-                            success_message.set("Wallet loaded successfully".to_string());
-                            configure_key_manager(&wallet);
-                        }
-                        Err(err) => {
-                            error_message.set(format!("Failed to load wallet: {err}. Please check your username and password."));
-                        }
-                    }
+        let storage = storage.clone();
+        let mut configure_key_manager = configure_key_manager.clone();
+        let (name, pass, codec) = (username(), password(), key_codec());
+        is_loading_wallet.set(true);
+        spawn(async move {
+            let opened = off_ui_thread(async move {
+                if creating {
+                    wallet::create(&storage, &name, &pass).await
                 } else {
-                    error_message.set("Invalid username or password".to_string());
+                    wallet::open(&storage, &name, &pass).await
                 }
-            } else {
-                error_message
-                    .set("No encrypted seed found. Please create a wallet first.".to_string());
-            }
-
+            })
+            .await;
             is_loading_wallet.set(false);
-        } else {
-            // Creating new wallet
-            let username_result = MinString::new(&username());
-            let password_result = MinString::new(&password());
-
-            if let (Ok(username_min), Ok(password_min)) = (username_result, password_result) {
-                let credentials = Credentials {
-                    username: username_min,
-                    password: password_min,
-                    encrypted_seed: None, // New wallet, no seed yet
-                };
 
-                // Create wallet
-                match Wallet::new(credentials) {
-                    Ok(wallet) => match wallet.encrypted_seed() {
-                        Ok(seed) => {
-                            let seed_clone = seed.clone();
-                            encrypted_seed.set(Some(seed_clone.clone()));
-
-                            // Save to storage
-                            if let Err(err) = storage.save(STORAGE_KEY, &seed_clone) {
-                                error_message.set(format!("Failed to save wallet: {err}"));
-                                return;
-                            }
-
-                            wallet_exists.set(true);
-                            success_message
-                                .set("Wallet created and saved successfully".to_string());
-
-                            // Clear inputs
-                            username.set(String::new());
-                            password.set(String::new());
-                            configure_key_manager(&wallet);
-                        }
+            match opened {
+                Ok(wallet) if creating => {
+                    match wallet.encrypted_seed() {
+                        Ok(seed) => encrypted_seed.set(Some(seed)),
                         Err(err) => error_message.set(format!("Error encrypting seed: {err}")),
-                    },
-                    Err(err) => error_message.set(format!("Error creating wallet: {err}")),
+                    }
+                    wallet_exists.set(true);
+                    success_message.set("Wallet created and saved successfully".to_string());
+
+                    // Clear inputs
+                    username.set(String::new());
+                    password.set(String::new());
+                    match mnemonic::to_words(wallet.seed()) {
+                        Ok(words) => new_words.set(Some(words)),
+                        Err(err) => error_message.set(err.to_string()),
+                    }
+                    configure_key_manager(&wallet, codec);
                 }
-            } else {
-                error_message.set("Invalid username or password".to_string());
+                Ok(wallet) => {
+                    success_message.set("Wallet loaded successfully".to_string());
+                    configure_key_manager(&wallet, KeyCodec::default());
+                }
+                Err(err) if creating => {
+                    error_message.set(format!("Error creating wallet: {err}"));
+                }
+                Err(err @ WalletError::Credentials(_)) => error_message.set(format!(
+                    "Failed to load wallet: {err}. Please check your username and password."
+                )),
+                Err(err) => error_message.set(format!("Failed to load wallet: {err}")),
             }
-        }
+        });
     };
 
     // Get formatted encrypted seed for display
@@ -256,7 +306,7 @@ pub fn WalletComponent(content: Element) -> Element {
                 p { class: "text-xs break-all text-gray-600", "{seed_str}" }
                 div {
                     class: "my-2 text-xs text-gray-500",
-                    "Optionally backup this encrypted seed securely to restore your wallet"
+                    "Once unlocked, use Backup to save this seed together with your Plog, keys and followed peers"
                 }
             }
         }
@@ -272,6 +322,7 @@ pub fn WalletComponent(content: Element) -> Element {
                 div {
                     class: "flex items-center",
                     img { src: PEERPIPER_P_SVG, alt: "PeerPiper Logo", class: "w-6 h-6" }
+                    span { class: "ml-1 text-xs text-gray-600", "{active}" }
                 }
 
                 // Control buttons
@@ -280,11 +331,56 @@ pub fn WalletComponent(content: Element) -> Element {
                     button {
                         class: "text-xs bg-white border border-green-600 text-green-600 py-1 px-2 rounded-md hover:bg-green-50 transition",
                         r#type: "button",
-                        onclick: lock_wallet,
+                        onclick: move |_| show_backup.toggle(),
+                        "Backup"
+                    }
+                    button {
+                        class: "text-xs bg-white border border-green-600 text-green-600 py-1 px-2 rounded-md hover:bg-green-50 transition",
+                        r#type: "button",
+                        onclick: move |_| show_credentials.toggle(),
+                        "Password"
+                    }
+                    button {
+                        class: "text-xs bg-white border border-green-600 text-green-600 py-1 px-2 rounded-md hover:bg-green-50 transition",
+                        r#type: "button",
+                        onclick: move |_| show_auto_lock.toggle(),
+                        "Auto-lock"
+                    }
+                    button {
+                        class: "text-xs bg-white border border-green-600 text-green-600 py-1 px-2 rounded-md hover:bg-green-50 transition",
+                        r#type: "button",
+                        onclick: {
+                            let mut lock_wallet = lock_wallet.clone();
+                            move |_| lock_wallet("Wallet locked successfully")
+                        },
                         "Lock"
                     }
                 }
             }
+            if show_backup() {
+                div { class: "mt-3", BackupExport {} }
+            }
+            if show_auto_lock() {
+                div { class: "mt-3", AutoLockEditor { settings: auto_lock_settings } }
+            }
+            AutoLock {
+                settings: auto_lock_settings,
+                on_lock: {
+                    let mut lock_wallet = lock_wallet.clone();
+                    move |reason: LockReason| lock_wallet(reason.message())
+                }
+            }
+            if show_credentials() {
+                div {
+                    class: "mt-3",
+                    ChangeCredentials {
+                        on_changed: move |seed| {
+                            encrypted_seed.set(Some(seed));
+                            show_credentials.set(false);
+                        }
+                    }
+                }
+            }
         }
     };
 
@@ -300,6 +396,11 @@ pub fn WalletComponent(content: Element) -> Element {
                 // Minimized wallet controls in corner
                 {active_wallet_ui}
 
+                // The words of a new wallet, shown over everything until confirmed
+                if let Some(words) = new_words() {
+                    MnemonicBackup { words, on_done: move |_| new_words.set(None) }
+                }
+
                 // Full viewport for children components
                 div {
                     class: "w-full h-full",
@@ -327,6 +428,9 @@ pub fn WalletComponent(content: Element) -> Element {
                         }
                     }
 
+                    // Lock to switch, the picker remounts the wallet of the chosen identity
+                    IdentityPicker {}
+
                     // Show seed info if available
                     {seed_ui}
 
@@ -362,6 +466,24 @@ pub fn WalletComponent(content: Element) -> Element {
                                 }
                             }
 
+                            if !wallet_exists() {
+                                div { class: "space-y-2",
+                                    label { class: "block text-sm font-medium text-gray-700", for: "key_codec", "Signing key" }
+                                    select {
+                                        class: "w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-green-500",
+                                        id: "key_codec",
+                                        onchange: move |evt| {
+                                            if let Ok(codec) = evt.value().parse() {
+                                                key_codec.set(codec);
+                                            }
+                                        },
+                                        for &codec in KeyCodec::available() {
+                                            option { value: "{codec}", selected: codec == key_codec(), "{codec}" }
+                                        }
+                                    }
+                                }
+                            }
+
                             // Submit button inside the form
                             button {
                                 class: "w-full mt-4 py-2 px-4 rounded-md transition",
@@ -380,6 +502,46 @@ pub fn WalletComponent(content: Element) -> Element {
                             }
                         }
 
+                        // Restoring only makes sense without a wallet, it would be replaced
+                        if !wallet_exists() {
+                            details {
+                                class: "mt-2",
+                                summary { class: "cursor-pointer text-sm text-gray-600", "Restore from backup" }
+                                RestoreBackup {
+                                    on_restored: move |seed| {
+                                        encrypted_seed.set(Some(seed));
+                                        wallet_exists.set(true);
+                                        error_message.set(String::new());
+                                        success_message.set("Wallet restored from backup".to_string());
+                                    }
+                                }
+                            }
+                            details {
+                                class: "mt-2",
+                                summary { class: "cursor-pointer text-sm text-gray-600", "Recover from words" }
+                                RecoverFromWords {
+                                    on_recovered: move |seed| {
+                                        encrypted_seed.set(Some(seed));
+                                        wallet_exists.set(true);
+                                        error_message.set(String::new());
+                                        success_message.set("Wallet recovered from its words".to_string());
+                                    }
+                                }
+                            }
+                            details {
+                                class: "mt-2",
+                                summary { class: "cursor-pointer text-sm text-gray-600", "Recover with guardians" }
+                                RecoverWithGuardians {
+                                    on_created: move |seed| {
+                                        encrypted_seed.set(Some(seed));
+                                        wallet_exists.set(true);
+                                        error_message.set(String::new());
+                                        success_message.set("Wallet created, ask your guardians to recover your Plog".to_string());
+                                    }
+                                }
+                            }
+                        }
+
                         // Reset button (only show if wallet exists but not accessible)
                         if wallet_exists() {
                             button {
@@ -409,3 +571,117 @@ pub fn WalletComponent(content: Element) -> Element {
         }
     }
 }
+
+/// Changes the username and password of the unlocked wallet, keeping its seed and so its VLAD.
+///
+/// `on_changed` gets the newly encrypted seed.
+#[component]
+fn ChangeCredentials(on_changed: EventHandler<Vec<u8>>) -> Element {
+    let storage = use_context::<StorageProvider>();
+
+    let mut old_username = use_signal(String::new);
+    let mut old_password = use_signal(String::new);
+    let mut new_username = use_signal(String::new);
+    let mut new_password = use_signal(String::new);
+    let mut confirm = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut changing = use_signal(|| false);
+
+    let handle_change = move |_| {
+        if new_password() != confirm() {
+            error.set(Some("The new passwords do not match.".to_string()));
+            return;
+        }
+        if new_username().len() < MIN_LENGTH || new_password().len() < MIN_LENGTH {
+            error.set(Some(format!(
+                "Username and password must be at least {MIN_LENGTH} characters"
+            )));
+            return;
+        }
+        let storage = storage.clone();
+        error.set(None);
+        changing.set(true);
+        let (old_name, old_pass) = (old_username(), old_password());
+        let (new_name, new_pass) = (new_username(), new_password());
+        spawn(async move {
+            let changed = off_ui_thread(async move {
+                wallet::change_credentials(&storage, &old_name, &old_pass, &new_name, &new_pass)
+                    .await
+            })
+            .await;
+            match changed.map(|wallet| wallet.encrypted_seed()) {
+                Ok(Ok(seed)) => {
+                    for mut field in [
+                        old_username,
+                        old_password,
+                        new_username,
+                        new_password,
+                        confirm,
+                    ] {
+                        field.set(String::new());
+                    }
+                    on_changed.call(seed);
+                }
+                Ok(Err(e)) => error.set(Some(format!("Error encrypting seed: {e}"))),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            changing.set(false);
+        });
+    };
+
+    rsx! {
+        form {
+            class: "flex flex-col gap-2 text-xs w-64",
+            onsubmit: handle_change,
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "text",
+                autocomplete: "username",
+                placeholder: "Current username",
+                value: "{old_username}",
+                oninput: move |e| old_username.set(e.value()),
+            }
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "password",
+                autocomplete: "current-password",
+                placeholder: "Current password",
+                value: "{old_password}",
+                oninput: move |e| old_password.set(e.value()),
+            }
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "text",
+                autocomplete: "off",
+                placeholder: "New username",
+                value: "{new_username}",
+                oninput: move |e| new_username.set(e.value()),
+            }
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "password",
+                autocomplete: "new-password",
+                placeholder: "New password",
+                value: "{new_password}",
+                oninput: move |e| new_password.set(e.value()),
+            }
+            input {
+                class: "px-2 py-1 border rounded",
+                r#type: "password",
+                autocomplete: "new-password",
+                placeholder: "Repeat the new password",
+                value: "{confirm}",
+                oninput: move |e| confirm.set(e.value()),
+            }
+            button {
+                class: "py-1 px-2 bg-green-500 text-white rounded-md hover:bg-green-600 disabled:bg-gray-400 transition",
+                r#type: "submit",
+                disabled: changing() || old_password().is_empty(),
+                if changing() { "Re-encrypting..." } else { "Change credentials" }
+            }
+            if let Some(err) = error() {
+                p { class: "text-red-500", "{err}" }
+            }
+        }
+    }
+}
@@ -16,7 +16,7 @@ fn main() {
 fn App() -> Element {
     // Build cool things ✌️
     let storage = storage::WebStorage::new();
-    let storage_provider = StorageProvider::new(storage);
+    let storage_provider = StorageProvider::new_encrypted(storage);
 
     // provide storgae in context for all child elements
     use_context_provider(|| storage_provider);
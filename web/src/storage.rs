@@ -1,42 +1,61 @@
-use base64::{Engine as _, engine::general_purpose::STANDARD_NO_PAD};
-use gloo_storage::{LocalStorage, Storage};
-use ui::WalletStorage;
+//! Browser storage. IndexedDB where available, `localStorage` otherwise.
+mod indexed_db;
+mod local;
+
+use indexed_db::IndexedDbStore;
+use local::LocalStore;
+use ui::{StorageError, WalletStorage};
 
 #[derive(Clone)]
-pub struct WebStorage;
+pub enum WebStorage {
+    IndexedDb(IndexedDbStore),
+    Local(LocalStore),
+}
 
-impl WalletStorage for WebStorage {
-    fn save(&self, key: &str, data: &[u8]) -> Result<(), String> {
-        // Convert binary data to Base64 string for storage
-        let encoded = STANDARD_NO_PAD.encode(data);
-        LocalStorage::set(key, encoded).map_err(|err| format!("Failed to save data: {:?}", err))
+impl WebStorage {
+    /// Picks IndexedDB when the browser supports it. Keys left in `localStorage` by
+    /// earlier versions are moved over the first time the database is opened.
+    pub fn new() -> Self {
+        if IndexedDbStore::is_available() {
+            WebStorage::IndexedDb(IndexedDbStore::new())
+        } else {
+            web_sys::console::warn_1(&"IndexedDB unavailable, falling back to localStorage".into());
+            WebStorage::Local(LocalStore)
+        }
     }
 
-    fn load(&self, key: &str) -> Result<Vec<u8>, String> {
-        // Retrieve Base64 string and convert back to binary
-        let encoded: String =
-            LocalStorage::get(key).map_err(|err| format!("Failed to load data: {:?}", err))?;
+    fn backend(&self) -> &dyn WalletStorage {
+        match self {
+            WebStorage::IndexedDb(store) => store,
+            WebStorage::Local(store) => store,
+        }
+    }
+}
 
-        STANDARD_NO_PAD
-            .decode(&encoded)
-            .map_err(|err| format!("Failed to decode data: {:?}", err))
+#[async_trait::async_trait(?Send)]
+impl WalletStorage for WebStorage {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.backend().save(key, data).await
     }
 
-    fn delete(&self, key: &str) -> Result<(), String> {
-        // Remove the key from local storage
-        LocalStorage::delete(key);
-        Ok(())
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.backend().load(key).await
     }
 
-    fn exists(&self, key: &str) -> bool {
-        // Check if key exists in local storage
-        LocalStorage::get::<String>(key).is_ok()
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.backend().delete(key).await
     }
-}
 
-// You might also want to add a constructor
-impl WebStorage {
-    pub fn new() -> Self {
-        WebStorage
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        self.backend().list_keys(prefix).await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        self.backend().compare_and_swap(key, expected, new).await
     }
 }
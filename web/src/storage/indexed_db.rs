@@ -0,0 +1,238 @@
+//! IndexedDB backend, storing raw bytes as `Uint8Array`s.
+use super::local::LocalStore;
+use indexed_db_futures::prelude::*;
+use js_sys::Uint8Array;
+use send_wrapper::SendWrapper;
+use std::cell::RefCell;
+use std::rc::Rc;
+use ui::{StorageError, WalletStorage};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{DomException, console};
+
+const DB_NAME: &str = "vaiber";
+const DB_VERSION: u32 = 1;
+/// Wallet data, keyed by storage key
+const DATA_STORE: &str = "data";
+/// Bookkeeping that isn't wallet data, such as the migration marker
+const META_STORE: &str = "meta";
+const MIGRATED_KEY: &str = "migrated_from_local_storage";
+/// Every key the `localStorage` backend wrote: the encrypted seed and the plog. Other scripts
+/// on the origin may use `localStorage` too, so nothing else is touched.
+const LOCAL_STORAGE_KEYS: &[&str] = &["SEED_KEEPER_ENCRYPTED_SEED", "VLAD_STORAGE_KEY"];
+
+#[derive(Clone)]
+pub struct IndexedDbStore {
+    /// Opened lazily on first use. IndexedDB handles are single threaded, as is the browser.
+    db: SendWrapper<Rc<RefCell<Option<Rc<IdbDatabase>>>>>,
+}
+
+/// Maps IndexedDB failures onto the matching [StorageError].
+fn dom_error(key: &str, err: DomException) -> StorageError {
+    match err.name().as_str() {
+        "QuotaExceededError" => StorageError::QuotaExceeded,
+        _ => StorageError::Backend(format!("{key}: {}", err.message())),
+    }
+}
+
+impl IndexedDbStore {
+    pub fn new() -> Self {
+        Self {
+            db: SendWrapper::new(Rc::default()),
+        }
+    }
+
+    /// Whether this browser exposes IndexedDB at all.
+    pub fn is_available() -> bool {
+        web_sys::window()
+            .and_then(|window| window.indexed_db().ok().flatten())
+            .is_some()
+    }
+
+    async fn db(&self) -> Result<Rc<IdbDatabase>, StorageError> {
+        if let Some(db) = self.db.borrow().as_ref() {
+            return Ok(db.clone());
+        }
+
+        let mut request =
+            IdbDatabase::open_u32(DB_NAME, DB_VERSION).map_err(|err| dom_error(DB_NAME, err))?;
+        request.set_on_upgrade_needed(Some(|evt: &IdbVersionChangeEvent| -> Result<(), JsValue> {
+            let names: Vec<String> = evt.db().object_store_names().collect();
+            for store in [DATA_STORE, META_STORE] {
+                if !names.iter().any(|name| name == store) {
+                    evt.db().create_object_store(store)?;
+                }
+            }
+            Ok(())
+        }));
+        let db = Rc::new(request.await.map_err(|err| dom_error(DB_NAME, err))?);
+
+        migrate_local_storage(&db).await?;
+
+        *self.db.borrow_mut() = Some(db.clone());
+        Ok(db)
+    }
+
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let db = self.db().await?;
+        let tx = db
+            .transaction_on_one(DATA_STORE)
+            .map_err(|err| dom_error(key, err))?;
+        let store = tx
+            .object_store(DATA_STORE)
+            .map_err(|err| dom_error(key, err))?;
+        let value = store
+            .get_owned(key)
+            .map_err(|err| dom_error(key, err))?
+            .await
+            .map_err(|err| dom_error(key, err))?;
+        value.map(|value| bytes_from_js(key, value)).transpose()
+    }
+}
+
+fn bytes_from_js(key: &str, value: JsValue) -> Result<Vec<u8>, StorageError> {
+    value
+        .dyn_into::<Uint8Array>()
+        .map(|array| array.to_vec())
+        .map_err(|_| StorageError::Corrupted {
+            key: key.to_string(),
+            reason: "stored value is not a byte array".to_string(),
+        })
+}
+
+/// Copies the [LOCAL_STORAGE_KEYS] the `localStorage` backend wrote into IndexedDB, once.
+///
+/// Copied keys are removed from `localStorage`, and the marker is written in the same
+/// transaction as the data, so an interrupted migration simply runs again.
+async fn migrate_local_storage(db: &IdbDatabase) -> Result<(), StorageError> {
+    let tx = db
+        .transaction_on_multi_with_mode(&[DATA_STORE, META_STORE], IdbTransactionMode::Readwrite)
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?;
+    let meta = tx
+        .object_store(META_STORE)
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?;
+    let migrated = meta
+        .get_owned(MIGRATED_KEY)
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?
+        .await
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?;
+    if migrated.is_some() {
+        return Ok(());
+    }
+
+    let local = LocalStore;
+    let data = tx
+        .object_store(DATA_STORE)
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?;
+    let mut copied = Vec::new();
+    for &key in LOCAL_STORAGE_KEYS {
+        // Missing, or not written by us if it doesn't decode
+        let Ok(bytes) = local.load(key).await else {
+            continue;
+        };
+        data.put_key_val_owned(key, &Uint8Array::from(bytes.as_slice()))
+            .map_err(|err| dom_error(key, err))?;
+        copied.push(key);
+    }
+    meta.put_key_val_owned(MIGRATED_KEY, &JsValue::TRUE)
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?;
+    tx.await
+        .into_result()
+        .map_err(|err| dom_error(MIGRATED_KEY, err))?;
+
+    for key in &copied {
+        local.delete(key).await?;
+    }
+    if !copied.is_empty() {
+        console::log_1(&format!("Migrated {} keys from localStorage", copied.len()).into());
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait(?Send)]
+impl WalletStorage for IndexedDbStore {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let db = self.db().await?;
+        let tx = db
+            .transaction_on_one_with_mode(DATA_STORE, IdbTransactionMode::Readwrite)
+            .map_err(|err| dom_error(key, err))?;
+        let store = tx
+            .object_store(DATA_STORE)
+            .map_err(|err| dom_error(key, err))?;
+        store
+            .put_key_val_owned(key, &Uint8Array::from(data))
+            .map_err(|err| dom_error(key, err))?;
+        tx.await.into_result().map_err(|err| dom_error(key, err))
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.read(key)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let db = self.db().await?;
+        let tx = db
+            .transaction_on_one_with_mode(DATA_STORE, IdbTransactionMode::Readwrite)
+            .map_err(|err| dom_error(key, err))?;
+        let store = tx
+            .object_store(DATA_STORE)
+            .map_err(|err| dom_error(key, err))?;
+        store.delete_owned(key).map_err(|err| dom_error(key, err))?;
+        tx.await.into_result().map_err(|err| dom_error(key, err))
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let db = self.db().await?;
+        let tx = db
+            .transaction_on_one(DATA_STORE)
+            .map_err(|err| dom_error(prefix, err))?;
+        let store = tx
+            .object_store(DATA_STORE)
+            .map_err(|err| dom_error(prefix, err))?;
+        let keys = store
+            .get_all_keys()
+            .map_err(|err| dom_error(prefix, err))?
+            .await
+            .map_err(|err| dom_error(prefix, err))?;
+        Ok(keys
+            .iter()
+            .filter_map(|key| key.as_string())
+            .filter(|key| key.starts_with(prefix))
+            .collect())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        // The read and the write share one readwrite transaction, which IndexedDB runs atomically
+        let db = self.db().await?;
+        let tx = db
+            .transaction_on_one_with_mode(DATA_STORE, IdbTransactionMode::Readwrite)
+            .map_err(|err| dom_error(key, err))?;
+        let store = tx
+            .object_store(DATA_STORE)
+            .map_err(|err| dom_error(key, err))?;
+        let current = store
+            .get_owned(key)
+            .map_err(|err| dom_error(key, err))?
+            .await
+            .map_err(|err| dom_error(key, err))?
+            .map(|value| bytes_from_js(key, value))
+            .transpose()?;
+        if current.as_deref() != expected {
+            return Err(StorageError::Conflict(key.to_string()));
+        }
+        match new {
+            Some(data) => store
+                .put_key_val_owned(key, &Uint8Array::from(data))
+                .map(|_| ()),
+            None => store.delete_owned(key).map(|_| ()),
+        }
+        .map_err(|err| dom_error(key, err))?;
+        tx.await.into_result().map_err(|err| dom_error(key, err))
+    }
+}
@@ -0,0 +1,94 @@
+//! `localStorage` backend, base64 encoded. Used when IndexedDB is unavailable.
+use base64::{Engine as _, engine::general_purpose::STANDARD_NO_PAD};
+use gloo_storage::errors::StorageError as GlooError;
+use gloo_storage::{LocalStorage, Storage};
+use ui::{StorageError, WalletStorage};
+
+#[derive(Clone)]
+pub struct LocalStore;
+
+/// Maps `gloo_storage` failures onto the matching [StorageError].
+fn gloo_error(key: &str, err: GlooError) -> StorageError {
+    match err {
+        GlooError::KeyNotFound(_) => StorageError::NotFound(key.to_string()),
+        GlooError::SerdeError(err) => StorageError::Corrupted {
+            key: key.to_string(),
+            reason: err.to_string(),
+        },
+        GlooError::JsError(err) if err.name == "QuotaExceededError" => StorageError::QuotaExceeded,
+        GlooError::JsError(err) => StorageError::Backend(format!("{key}: {err}")),
+    }
+}
+
+impl LocalStore {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        // Retrieve Base64 string and convert back to binary
+        let encoded: String = match LocalStorage::get(key) {
+            Ok(encoded) => encoded,
+            Err(GlooError::KeyNotFound(_)) => return Ok(None),
+            Err(err) => return Err(gloo_error(key, err)),
+        };
+
+        STANDARD_NO_PAD
+            .decode(&encoded)
+            .map(Some)
+            .map_err(|err| StorageError::Corrupted {
+                key: key.to_string(),
+                reason: err.to_string(),
+            })
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        // Convert binary data to Base64 string for storage
+        let encoded = STANDARD_NO_PAD.encode(data);
+        LocalStorage::set(key, encoded).map_err(|err| gloo_error(key, err))
+    }
+}
+
+// localStorage is synchronous and single threaded, so none of these can interleave
+#[async_trait::async_trait(?Send)]
+impl WalletStorage for LocalStore {
+    async fn save(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.write(key, data)
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        self.read(key)?
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        // Remove the key from local storage
+        LocalStorage::delete(key);
+        Ok(())
+    }
+
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let storage = LocalStorage::raw();
+        let len = storage
+            .length()
+            .map_err(|err| StorageError::Backend(format!("{err:?}")))?;
+        Ok((0..len)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter(|key| key.starts_with(prefix))
+            .collect())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), StorageError> {
+        if self.read(key)?.as_deref() != expected {
+            return Err(StorageError::Conflict(key.to_string()));
+        }
+        match new {
+            Some(data) => self.write(key, data),
+            None => {
+                LocalStorage::delete(key);
+                Ok(())
+            }
+        }
+    }
+}